@@ -0,0 +1,112 @@
+use std::borrow::Cow;
+use numpy::{PyArray1, PyArray2, PyArray3, PyArrayMethods, PyReadonlyArray2, PyUntypedArrayMethods};
+use pyo3::prelude::*;
+use camport3_sys::*;
+use camport3_rs as rs;
+
+use crate::capture::Image;
+use crate::IntoPyResult;
+
+/// `TY_CAMERA_CALIB_INFO` of a component.
+#[pyclass(frozen, module = "camport3")]
+#[derive(Clone, Copy)]
+pub struct CalibInfo(pub(crate) TY_CAMERA_CALIB_INFO);
+
+#[pymethods]
+impl CalibInfo {
+    /// `(width, height)` of the image the intrinsics were calibrated at.
+    #[getter]
+    fn intrinsic_size(&self) -> (i32, i32) {
+        ({ self.0.intrinsicWidth }, { self.0.intrinsicHeight })
+    }
+
+    /// 3x3 camera matrix.
+    #[getter]
+    fn intrinsic<'py>(&self, py: Python<'py>) -> PyResult<Bound<'py, PyArray2<f32>>> {
+        PyArray1::from_slice(py, &{ self.0.intrinsic.data }).reshape([3, 3])
+    }
+
+    /// 4x4 transform to the depth camera.
+    #[getter]
+    fn extrinsic<'py>(&self, py: Python<'py>) -> PyResult<Bound<'py, PyArray2<f32>>> {
+        PyArray1::from_slice(py, &{ self.0.extrinsic.data }).reshape([4, 4])
+    }
+
+    /// 12 coefficients, in OpenCV order `k1, k2, p1, p2, k3, k4, k5, k6, s1, s2, s3, s4`.
+    #[getter]
+    fn distortion<'py>(&self, py: Python<'py>) -> Bound<'py, PyArray1<f32>> {
+        PyArray1::from_slice(py, &{ self.0.distortion.data })
+    }
+}
+
+/// `calib` with `intrinsic` in place of its own intrinsics, calibrated at `size`.
+fn with_intrinsic(
+    calib: &TY_CAMERA_CALIB_INFO, intrinsic: Option<rs::CameraIntrinsic>, size: (u32, u32),
+) -> TY_CAMERA_CALIB_INFO {
+    let Some(k) = intrinsic else { return *calib };
+    TY_CAMERA_CALIB_INFO {
+        intrinsicWidth: size.0 as i32,
+        intrinsicHeight: size.1 as i32,
+        intrinsic: k.into(),
+        ..*calib
+    }
+}
+
+/// Map a depth image to a `(height, width, 3)` `float32` point cloud.
+///
+/// `depth` is a depth `Image` or a `(height, width)` `uint16` array. The intrinsics of an image account for the
+/// image mode and ROI it was captured with and replace those of `calib`. An array is mapped with `calib` as is,
+/// which is wrong for images cropped by an ROI.
+///
+/// Zero depth maps to NaN. `scale_unit` is `TY_FLOAT_SCALE_UNIT` of the depth component.
+#[pyfunction]
+#[pyo3(signature = (calib, depth, scale_unit = 1.0))]
+pub fn depth_to_points<'py>(
+    py: Python<'py>, calib: &CalibInfo, depth: &Bound<'py, PyAny>, scale_unit: f32,
+) -> PyResult<Bound<'py, PyArray3<f32>>> {
+    let (calib, depth) = match depth.cast::<Image>() {
+        Ok(img) => {
+            let rs_img = &img.get().0;
+            (with_intrinsic(&calib.0, rs_img.intrinsic, (rs_img.width, rs_img.height)), Image::array(img)?)
+        }
+        Err(_) => (calib.0, depth.clone()),
+    };
+    let depth: PyReadonlyArray2<'py, u16> = depth.extract()?;
+    let [h, w] = *depth.shape() else { unreachable!() };
+    let pixels = match depth.as_slice() {
+        Ok(pixels) => Cow::Borrowed(pixels),
+        Err(_) => Cow::Owned(depth.as_array().iter().copied().collect()),
+    };
+    let points = PyArray3::<f32>::zeros(py, [h, w, 3], false);
+    // SAFETY: the array was just created, nothing else references it.
+    let out = unsafe { points.as_slice_mut() }?;
+    // SAFETY: `TY_VECT_3F` is three packed `f32`s.
+    let out = unsafe { std::slice::from_raw_parts_mut(out.as_mut_ptr().cast::<TY_VECT_3F>(), out.len() / 3) };
+    let (w, h) = (w as u32, h as u32);
+    py.detach(|| rs::depth_to_points_into(&calib, w, h, &pixels, scale_unit, out)).py()?;
+    Ok(points)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_with_intrinsic() {
+        let calib = TY_CAMERA_CALIB_INFO {
+            intrinsicWidth: 1280,
+            intrinsicHeight: 960,
+            intrinsic: rs::CameraIntrinsic { fx: 1000.0, fy: 1000.0, cx: 640.0, cy: 480.0 }.into(),
+            extrinsic: TY_CAMERA_EXTRINSIC { data: [1.0; 16] },
+            distortion: TY_CAMERA_DISTORTION { data: [0.5; 12] },
+        };
+        let k = rs::CameraIntrinsic { fx: 500.0, fy: 500.0, cx: 310.0, cy: 220.0 };
+        let out = with_intrinsic(&calib, Some(k), (400, 300));
+        assert_eq!(({ out.intrinsicWidth }, { out.intrinsicHeight }), (400, 300));
+        assert_eq!(rs::CameraIntrinsic::from(out.intrinsic), k);
+        assert_eq!({ out.distortion.data }, [0.5; 12]);
+
+        let same = with_intrinsic(&calib, None, (400, 300));
+        assert_eq!(({ same.intrinsicWidth }, { same.intrinsic.data }), (1280, { calib.intrinsic.data }));
+    }
+}
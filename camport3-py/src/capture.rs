@@ -0,0 +1,248 @@
+use std::sync::Arc;
+use numpy::ndarray::{ArrayView, IxDyn};
+use numpy::{Element, PyArray, PyArrayMethods};
+use pyo3::prelude::*;
+use camport3_sys::*;
+use camport3_rs as rs;
+use camport3_rs::PixelFormat;
+
+use crate::IntoPyResult;
+use crate::device::Device;
+
+/// Element type of the NumPy array exposing an image.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Dtype {
+    U8,
+    U16,
+    I16,
+}
+
+impl Dtype {
+    fn size(self) -> usize {
+        match self {
+            Dtype::U8 => 1,
+            Dtype::U16 | Dtype::I16 => 2,
+        }
+    }
+}
+
+/// Dtype and shape of an image of `len` bytes.
+///
+/// Compressed, packed and unknown formats, and buffers whose size does not match the format, are flat `uint8`.
+fn array_layout(format: TY_PIXEL_FORMAT, width: u32, height: u32, len: usize) -> (Dtype, Vec<usize>) {
+    use PixelFormat::*;
+    let (w, h) = (width as usize, height as usize);
+    let (dtype, shape) = match PixelFormat::from_repr(format) {
+        Some(Depth16 | Mono16 | TofIrMono16) => (Dtype::U16, vec![h, w]),
+        Some(Rgb48 | Bgr48) => (Dtype::U16, vec![h, w, 3]),
+        Some(Xyz48) => (Dtype::I16, vec![h, w, 3]),
+        Some(Mono | Bayer8Gb | Bayer8Bg | Bayer8Gr | Bayer8Rg) => (Dtype::U8, vec![h, w]),
+        Some(Rgb | Bgr) => (Dtype::U8, vec![h, w, 3]),
+        Some(Yuyv | Yvyu) => (Dtype::U8, vec![h, w, 2]),
+        _ => return (Dtype::U8, vec![len]),
+    };
+    if shape.iter().product::<usize>() * dtype.size() != len {
+        return (Dtype::U8, vec![len]);
+    }
+    (dtype, shape)
+}
+
+/// Read-only array over `data`, kept alive by `owner`; copied when `data` is misaligned for `T`.
+fn borrow_array<'py, T: Element + Copy>(
+    data: &[u8], shape: &[usize], owner: &Bound<'py, PyAny>,
+) -> PyResult<Bound<'py, PyAny>> {
+    let len = data.len() / size_of::<T>();
+    let array = if (data.as_ptr() as usize).is_multiple_of(align_of::<T>()) {
+        // SAFETY: aligned, `len` elements fit in `data`, and every bit pattern is a valid integer.
+        let view = unsafe { ArrayView::from_shape_ptr(IxDyn(shape), data.as_ptr().cast::<T>()) };
+        // SAFETY: `owner` holds the frozen image owning `data`, and the array is made read-only below.
+        unsafe { PyArray::borrow_from_array(&view, owner.clone()) }
+    } else {
+        let copy: Vec<T> = (0..len)
+            // SAFETY: in bounds; unaligned reads of plain integers.
+            .map(|i| unsafe { data.as_ptr().cast::<T>().add(i).read_unaligned() })
+            .collect();
+        PyArray::from_vec(owner.py(), copy).reshape(shape)?
+    };
+    array.call_method1("setflags", (false,))?;
+    Ok(array.into_any())
+}
+
+/// One image of a frame.
+#[pyclass(frozen, module = "camport3")]
+pub struct Image(pub(crate) rs::Image);
+
+#[pymethods]
+impl Image {
+    #[getter]
+    fn component(&self) -> TY_COMPONENT_ID {
+        self.0.component
+    }
+
+    /// Microseconds.
+    #[getter]
+    fn timestamp(&self) -> u64 {
+        self.0.timestamp
+    }
+
+    #[getter]
+    fn image_index(&self) -> i32 {
+        self.0.image_index
+    }
+
+    #[getter]
+    fn status(&self) -> i32 {
+        self.0.status
+    }
+
+    #[getter]
+    fn width(&self) -> u32 {
+        self.0.width
+    }
+
+    #[getter]
+    fn height(&self) -> u32 {
+        self.0.height
+    }
+
+    /// Raw `TY_PIXEL_FORMAT`.
+    #[getter]
+    fn pixel_format(&self) -> TY_PIXEL_FORMAT {
+        self.0.pixel_format
+    }
+
+    /// `(fx, fy, cx, cy)` of depth images under the capture's image mode and ROI, or `None`.
+    #[getter]
+    fn intrinsic(&self) -> Option<(f32, f32, f32, f32)> {
+        self.0.intrinsic.map(|k| (k.fx, k.fy, k.cx, k.cy))
+    }
+
+    /// Read-only array over the image data, without copying.
+    ///
+    /// Depth and 16-bit mono images are `uint16` `(height, width)`, 8-bit colour `(height, width, 3)`. Compressed
+    /// and packed formats are flat `uint8`.
+    pub(crate) fn array<'py>(slf: &Bound<'py, Self>) -> PyResult<Bound<'py, PyAny>> {
+        let img = &slf.get().0;
+        let data = img.data();
+        let (dtype, shape) = array_layout(img.pixel_format, img.width, img.height, data.len());
+        let owner = slf.as_any();
+        match dtype {
+            Dtype::U8 => borrow_array::<u8>(data, &shape, owner),
+            Dtype::U16 => borrow_array::<u16>(data, &shape, owner),
+            Dtype::I16 => borrow_array::<i16>(data, &shape, owner),
+        }
+    }
+}
+
+/// Images delivered together by one fetch.
+#[pyclass(frozen, module = "camport3")]
+pub struct Frame {
+    images: Vec<Py<Image>>,
+}
+
+impl Frame {
+    fn find(&self, py: Python<'_>, comp: TY_DEVICE_COMPONENT_LIST) -> Option<Py<Image>> {
+        self.images.iter().find(|img| img.get().0.is_component(comp)).map(|img| img.clone_ref(py))
+    }
+}
+
+#[pymethods]
+impl Frame {
+    #[getter]
+    fn images(&self, py: Python<'_>) -> Vec<Py<Image>> {
+        self.images.iter().map(|img| img.clone_ref(py)).collect()
+    }
+
+    /// Image of a component, `None` if the frame has none.
+    fn image(&self, py: Python<'_>, comp: TY_COMPONENT_ID) -> Option<Py<Image>> {
+        self.images.iter().find(|img| img.get().0.component == comp).map(|img| img.clone_ref(py))
+    }
+
+    #[getter]
+    fn depth(&self, py: Python<'_>) -> Option<Py<Image>> {
+        self.find(py, TY_DEVICE_COMPONENT_LIST::TY_COMPONENT_DEPTH_CAM)
+    }
+
+    #[getter]
+    fn left_ir(&self, py: Python<'_>) -> Option<Py<Image>> {
+        self.find(py, TY_DEVICE_COMPONENT_LIST::TY_COMPONENT_IR_CAM_LEFT)
+    }
+
+    #[getter]
+    fn right_ir(&self, py: Python<'_>) -> Option<Py<Image>> {
+        self.find(py, TY_DEVICE_COMPONENT_LIST::TY_COMPONENT_IR_CAM_RIGHT)
+    }
+
+    #[getter]
+    fn color(&self, py: Python<'_>) -> Option<Py<Image>> {
+        self.find(py, TY_DEVICE_COMPONENT_LIST::TY_COMPONENT_RGB_CAM)
+    }
+}
+
+/// Frame buffers queued on a device, see `camport3_rs::CaptureSession`.
+///
+/// Enable the components before creating the session, the buffer size depends on them.
+#[pyclass(module = "camport3")]
+pub struct CaptureSession(rs::CaptureSession);
+
+#[pymethods]
+impl CaptureSession {
+    #[new]
+    #[pyo3(signature = (device, buffer_count = rs::DEFAULT_BUFFER_COUNT))]
+    fn new(device: &Device, buffer_count: usize) -> PyResult<Self> {
+        rs::CaptureSession::new(Arc::clone(&device.inner), buffer_count).py().map(CaptureSession)
+    }
+
+    #[getter]
+    fn is_capturing(&self) -> bool {
+        self.0.is_capturing()
+    }
+
+    fn start(&mut self) -> PyResult<()> {
+        self.0.start().py()
+    }
+
+    fn stop(&mut self) -> PyResult<()> {
+        self.0.stop().py()
+    }
+
+    /// Wait at most `timeout_ms` (-1 waits forever) for the next frame, without holding the GIL.
+    #[pyo3(signature = (timeout_ms = -1))]
+    fn fetch_frame(&self, py: Python<'_>, timeout_ms: i32) -> PyResult<Frame> {
+        let frame = py.detach(|| self.0.fetch_frame(timeout_ms)).py()?;
+        let images = frame.into_images().into_iter()
+            .map(|img| Py::new(py, Image(img)))
+            .collect::<PyResult<_>>()?;
+        Ok(Frame { images })
+    }
+
+    fn __enter__(mut slf: PyRefMut<'_, Self>) -> PyResult<PyRefMut<'_, Self>> {
+        slf.0.start().py()?;
+        Ok(slf)
+    }
+
+    fn __exit__(&mut self, _exc_type: &Bound<'_, PyAny>, _exc: &Bound<'_, PyAny>, _tb: &Bound<'_, PyAny>) -> PyResult<()> {
+        if self.0.is_capturing() {
+            self.0.stop().py()?;
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_array_layout() {
+        let fmt = |f: PixelFormat| f as TY_PIXEL_FORMAT;
+        assert_eq!(array_layout(fmt(PixelFormat::Depth16), 4, 3, 24), (Dtype::U16, vec![3, 4]));
+        assert_eq!(array_layout(fmt(PixelFormat::Rgb), 4, 3, 36), (Dtype::U8, vec![3, 4, 3]));
+        assert_eq!(array_layout(fmt(PixelFormat::Yuyv), 4, 3, 24), (Dtype::U8, vec![3, 4, 2]));
+        assert_eq!(array_layout(fmt(PixelFormat::Xyz48), 2, 2, 24), (Dtype::I16, vec![2, 2, 3]));
+        // Compressed, and size mismatches, stay flat.
+        assert_eq!(array_layout(fmt(PixelFormat::Jpeg), 640, 480, 1000), (Dtype::U8, vec![1000]));
+        assert_eq!(array_layout(fmt(PixelFormat::Depth16), 4, 3, 20), (Dtype::U8, vec![20]));
+        assert_eq!(array_layout(0, 4, 3, 12), (Dtype::U8, vec![12]));
+    }
+}
@@ -0,0 +1,264 @@
+use std::sync::Arc;
+use pyo3::exceptions::{PyKeyError, PyTypeError};
+use pyo3::prelude::*;
+use pyo3::types::PyDict;
+use pyo3::IntoPyObjectExt;
+use camport3_sys::*;
+use camport3_rs as rs;
+use camport3_rs::FeatureId;
+
+use crate::IntoPyResult;
+use crate::calib::CalibInfo;
+
+fn interface_dict<'py>(py: Python<'py>, info: &rs::InterfaceInfo) -> PyResult<Bound<'py, PyDict>> {
+    let d = PyDict::new(py);
+    d.set_item("id", info.id())?;
+    d.set_item("name", info.name())?;
+    d.set_item("type", rs::fmt_ty_interface_type(info.type_()))?;
+    Ok(d)
+}
+
+fn device_dict<'py>(py: Python<'py>, info: &rs::DeviceBaseInfo) -> PyResult<Bound<'py, PyDict>> {
+    let d = PyDict::new(py);
+    d.set_item("id", info.id())?;
+    d.set_item("interface_id", info.iface().id())?;
+    d.set_item("vendor", info.vender_name())?;
+    d.set_item("model", info.model_name())?;
+    d.set_item("user_defined_name", info.user_defined_name())?;
+    d.set_item("hardware_version", info.hardware_version().to_string())?;
+    d.set_item("firmware_version", info.firmware_version().to_string())?;
+    if let Some(net) = info.get_net_info() {
+        d.set_item("ip", net.ip().ok().map(|ip| ip.to_string()))?;
+        d.set_item("mac", net.mac().ok().map(|mac| mac.to_string()))?;
+    }
+    Ok(d)
+}
+
+/// Library context; the SDK stays initialised while any context, interface or device is alive.
+#[pyclass(frozen, module = "camport3")]
+pub struct Context {
+    inner: rs::Context,
+}
+
+#[pymethods]
+impl Context {
+    #[new]
+    fn new() -> PyResult<Self> {
+        Ok(Context { inner: rs::Context::new().py()? })
+    }
+
+    /// SDK library version as `(major, minor, patch)`.
+    fn version(&self) -> PyResult<(u32, u32, u32)> {
+        Ok(self.inner.version().py()?.into())
+    }
+
+    /// Refresh and list the interfaces, as dicts.
+    fn interfaces<'py>(&self, py: Python<'py>) -> PyResult<Vec<Bound<'py, PyDict>>> {
+        let infos = py.detach(|| {
+            self.inner.update_interface_list()?;
+            self.inner.get_interface_list(0)
+        }).py()?;
+        infos.iter().map(|info| interface_dict(py, info)).collect()
+    }
+
+    /// Refresh and list the devices of all interfaces, as dicts.
+    fn devices<'py>(&self, py: Python<'py>) -> PyResult<Vec<Bound<'py, PyDict>>> {
+        let infos = py.detach(|| {
+            self.inner.update_interface_list()?;
+            let mut out = Vec::new();
+            for iface in self.inner.get_interface_list(0)? {
+                let iface = self.inner.open_interface(&iface.id())?;
+                iface.update_device_list()?;
+                out.extend(iface.get_device_list(0)?);
+            }
+            Ok(out)
+        }).py()?;
+        infos.iter().map(|info| device_dict(py, info)).collect()
+    }
+
+    fn open_interface(&self, py: Python<'_>, id: &str) -> PyResult<Interface> {
+        let inner = py.detach(|| self.inner.open_interface(id)).py()?;
+        Ok(Interface { inner })
+    }
+
+    /// Interface the device `id` is attached to, `None` if no interface has it.
+    fn find_device(&self, py: Python<'_>, id: &str) -> PyResult<Option<Interface>> {
+        let inner = py.detach(|| self.inner.find_device(id)).py()?;
+        Ok(inner.map(|inner| Interface { inner }))
+    }
+
+    /// Find the device `id` on any interface and open it.
+    fn open_device(&self, py: Python<'_>, id: &str) -> PyResult<Device> {
+        let dev = py.detach(|| {
+            let iface = self.inner.find_device(id)?.ok_or(rs::ErrorCode::DeviceOffline)?;
+            iface.open_device(id)
+        }).py()?;
+        Ok(Device::from(dev))
+    }
+}
+
+#[pyclass(frozen, module = "camport3")]
+pub struct Interface {
+    inner: rs::InterfaceHandle,
+}
+
+#[pymethods]
+impl Interface {
+    #[getter]
+    fn id(&self) -> &str {
+        self.inner.id()
+    }
+
+    /// Refresh and list the devices, as dicts.
+    fn devices<'py>(&self, py: Python<'py>) -> PyResult<Vec<Bound<'py, PyDict>>> {
+        let infos = py.detach(|| {
+            self.inner.update_device_list()?;
+            self.inner.get_device_list(0)
+        }).py()?;
+        infos.iter().map(|info| device_dict(py, info)).collect()
+    }
+
+    fn open_device(&self, py: Python<'_>, id: &str) -> PyResult<Device> {
+        py.detach(|| self.inner.open_device(id)).py().map(Device::from)
+    }
+
+    fn open_device_with_ip(&self, py: Python<'_>, ip: &str) -> PyResult<Device> {
+        py.detach(|| self.inner.open_device_with_ip(ip)).py().map(Device::from)
+    }
+}
+
+/// A feature given by ID or by the name the device reports for it.
+#[derive(FromPyObject)]
+enum FeatureRef {
+    Id(TY_FEATURE_ID),
+    Name(String),
+}
+
+/// An open device, closed when the last reference, including capture sessions, is gone.
+#[pyclass(frozen, module = "camport3")]
+pub struct Device {
+    pub(crate) inner: Arc<rs::DeviceHandle>,
+}
+
+impl From<rs::DeviceHandle> for Device {
+    fn from(dev: rs::DeviceHandle) -> Self {
+        Device { inner: Arc::new(dev) }
+    }
+}
+
+impl Device {
+    fn resolve(&self, comp: TY_COMPONENT_ID, feat: FeatureRef) -> PyResult<TY_FEATURE_ID> {
+        match feat {
+            FeatureRef::Id(id) => Ok(id),
+            FeatureRef::Name(name) => self.inner.get_feature_list(comp).py()?
+                .iter()
+                .find(|info| info.name() == name)
+                .map(|info| info.feature_id())
+                .ok_or_else(|| PyKeyError::new_err(name)),
+        }
+    }
+}
+
+#[pymethods]
+impl Device {
+    fn info<'py>(&self, py: Python<'py>) -> PyResult<Bound<'py, PyDict>> {
+        device_dict(py, &self.inner.get_device_info().py()?)
+    }
+
+    /// Bitmask of the components the device has.
+    fn components(&self) -> PyResult<TY_COMPONENT_ID> {
+        self.inner.get_component_ids().py()
+    }
+
+    fn enabled_components(&self) -> PyResult<TY_COMPONENT_ID> {
+        self.inner.get_enabled_components().py()
+    }
+
+    fn enable_components(&self, ids: TY_COMPONENT_ID) -> PyResult<()> {
+        self.inner.enable_components(ids).py()
+    }
+
+    fn disable_components(&self, ids: TY_COMPONENT_ID) -> PyResult<()> {
+        self.inner.disable_components(ids).py()
+    }
+
+    /// Features of a component, as dicts.
+    fn features<'py>(&self, py: Python<'py>, comp: TY_COMPONENT_ID) -> PyResult<Vec<Bound<'py, PyDict>>> {
+        self.inner.get_feature_list(comp).py()?
+            .iter()
+            .map(|info| {
+                let d = PyDict::new(py);
+                d.set_item("id", info.feature_id())?;
+                d.set_item("name", info.name())?;
+                d.set_item("readable", info.is_readable())?;
+                d.set_item("writable", info.is_writable())?;
+                d.set_item("writable_at_run", info.writable_at_run())?;
+                Ok(d)
+            })
+            .collect()
+    }
+
+    /// Read a feature, converted according to the type encoded in its ID.
+    ///
+    /// Struct features are not supported, apart from calibration data through `calib_info`.
+    fn get_feature(&self, py: Python<'_>, comp: TY_COMPONENT_ID, feat: FeatureRef) -> PyResult<Py<PyAny>> {
+        use TY_FEATURE_TYPE_LIST::*;
+        let feat = self.resolve(comp, feat)?;
+        let dev = &self.inner;
+        match feat.feature_type() {
+            t if t == TY_FEATURE_INT as u32 => dev.get_int(comp, feat).py()?.into_py_any(py),
+            t if t == TY_FEATURE_FLOAT as u32 => dev.get_float(comp, feat).py()?.into_py_any(py),
+            t if t == TY_FEATURE_ENUM as u32 => dev.get_enum(comp, feat).py()?.into_py_any(py),
+            t if t == TY_FEATURE_BOOL as u32 => dev.get_bool(comp, feat).py()?.into_py_any(py),
+            t if t == TY_FEATURE_STRING as u32 => dev.get_string(comp, feat).py()?.into_py_any(py),
+            t if t == TY_FEATURE_BYTEARRAY as u32 => {
+                pyo3::types::PyBytes::new(py, &dev.get_byte_array(comp, feat).py()?).into_py_any(py)
+            }
+            _ => Err(PyTypeError::new_err(format!("feature {feat:#x} has an unsupported type"))),
+        }
+    }
+
+    /// Write a feature; `value` must match the type encoded in its ID.
+    fn set_feature(&self, comp: TY_COMPONENT_ID, feat: FeatureRef, value: &Bound<'_, PyAny>) -> PyResult<()> {
+        use TY_FEATURE_TYPE_LIST::*;
+        let feat = self.resolve(comp, feat)?;
+        let dev = &self.inner;
+        match feat.feature_type() {
+            t if t == TY_FEATURE_INT as u32 => dev.set_int(comp, feat, value.extract()?).py(),
+            t if t == TY_FEATURE_FLOAT as u32 => dev.set_float(comp, feat, value.extract()?).py(),
+            t if t == TY_FEATURE_ENUM as u32 => dev.set_enum(comp, feat, value.extract()?).py(),
+            t if t == TY_FEATURE_BOOL as u32 => dev.set_bool(comp, feat, value.extract()?).py(),
+            t if t == TY_FEATURE_STRING as u32 => dev.set_string(comp, feat, &value.extract::<String>()?).py(),
+            t if t == TY_FEATURE_BYTEARRAY as u32 => dev.set_byte_array(comp, feat, &value.extract::<Vec<u8>>()?).py(),
+            _ => Err(PyTypeError::new_err(format!("feature {feat:#x} has an unsupported type"))),
+        }
+    }
+
+    /// `(min, max, inc)` of an int or float feature.
+    fn feature_range(&self, py: Python<'_>, comp: TY_COMPONENT_ID, feat: FeatureRef) -> PyResult<Py<PyAny>> {
+        let feat = self.resolve(comp, feat)?;
+        if feat.feature_type() == TY_FEATURE_TYPE_LIST::TY_FEATURE_FLOAT as u32 {
+            let r = self.inner.get_float_range(comp, feat).py()?;
+            (r.min(), r.max(), r.inc()).into_py_any(py)
+        } else {
+            let r = self.inner.get_int_range(comp, feat).py()?;
+            (r.min(), r.max(), r.inc()).into_py_any(py)
+        }
+    }
+
+    /// Entries of an enum feature as `(description, value)`.
+    fn enum_entries(&self, comp: TY_COMPONENT_ID, feat: FeatureRef) -> PyResult<Vec<(String, u32)>> {
+        let feat = self.resolve(comp, feat)?;
+        let entries = self.inner.get_enum_entries(comp, feat).py()?;
+        Ok(entries.iter().map(|e| (e.description().into_owned(), e.value())).collect())
+    }
+
+    /// `TY_STRUCT_CAM_CALIB_DATA` of a component.
+    fn calib_info(&self, comp: TY_COMPONENT_ID) -> PyResult<CalibInfo> {
+        self.inner.calib_info(comp).py().map(CalibInfo)
+    }
+
+    fn send_soft_trigger(&self) -> PyResult<()> {
+        self.inner.send_soft_trigger().py()
+    }
+}
@@ -0,0 +1,61 @@
+//! Python bindings, built as the `camport3` extension module with maturin.
+//!
+//! The classes are thin wrappers over `camport3-rs`, so Python sees the same reference counting, error codes and
+//! frame handling as Rust code.
+
+use pyo3::create_exception;
+use pyo3::exceptions::PyRuntimeError;
+use pyo3::prelude::*;
+use camport3_sys::*;
+
+mod device;
+mod capture;
+mod calib;
+
+pub use device::*;
+pub use capture::*;
+pub use calib::*;
+
+create_exception!(camport3, DeviceError, PyRuntimeError, "Error returned by the SDK or a device.");
+
+/// Convert crate errors at the Python boundary: `DeviceError(message, code)`, `code` being the `ErrorCode` name.
+pub(crate) trait IntoPyResult<T> {
+    fn py(self) -> PyResult<T>;
+}
+
+impl<T> IntoPyResult<T> for camport3_rs::Result<T> {
+    fn py(self) -> PyResult<T> {
+        self.map_err(|e| DeviceError::new_err((e.to_string(), format!("{:?}", e.errcode))))
+    }
+}
+
+#[pymodule]
+fn camport3(m: &Bound<'_, PyModule>) -> PyResult<()> {
+    use TY_DEVICE_COMPONENT_LIST::*;
+    m.add("DeviceError", m.py().get_type::<DeviceError>())?;
+    let components = [
+        ("COMPONENT_DEVICE", TY_COMPONENT_DEVICE),
+        ("COMPONENT_DEPTH_CAM", TY_COMPONENT_DEPTH_CAM),
+        ("COMPONENT_IR_CAM_LEFT", TY_COMPONENT_IR_CAM_LEFT),
+        ("COMPONENT_IR_CAM_RIGHT", TY_COMPONENT_IR_CAM_RIGHT),
+        ("COMPONENT_RGB_CAM_LEFT", TY_COMPONENT_RGB_CAM_LEFT),
+        ("COMPONENT_RGB_CAM_RIGHT", TY_COMPONENT_RGB_CAM_RIGHT),
+        ("COMPONENT_LASER", TY_COMPONENT_LASER),
+        ("COMPONENT_IMU", TY_COMPONENT_IMU),
+        ("COMPONENT_BRIGHT_HISTO", TY_COMPONENT_BRIGHT_HISTO),
+        ("COMPONENT_STORAGE", TY_COMPONENT_STORAGE),
+    ];
+    for (name, comp) in components {
+        m.add(name, comp as TY_COMPONENT_ID)?;
+    }
+    m.add("COMPONENT_RGB_CAM", TY_DEVICE_COMPONENT_LIST::TY_COMPONENT_RGB_CAM as TY_COMPONENT_ID)?;
+    m.add_class::<Context>()?;
+    m.add_class::<Interface>()?;
+    m.add_class::<Device>()?;
+    m.add_class::<CaptureSession>()?;
+    m.add_class::<Frame>()?;
+    m.add_class::<Image>()?;
+    m.add_class::<CalibInfo>()?;
+    m.add_function(wrap_pyfunction!(depth_to_points, m)?)?;
+    Ok(())
+}
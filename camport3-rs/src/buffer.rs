@@ -0,0 +1,130 @@
+use std::alloc::{self, Layout};
+use std::ptr::NonNull;
+
+use crate::ffi::*;
+
+/// Memory handed to `TYEnqueueBuffer` by a [`CaptureSession`](crate::CaptureSession).
+///
+/// # Safety
+/// `as_mut_ptr` must return the same pointer, valid for reads and writes of `size` bytes, for as long as the value
+/// lives, even after it was moved. The memory must not be used elsewhere while the session owns the buffer.
+pub unsafe trait FrameBuffer: Send + Sync {
+    fn as_mut_ptr(&mut self) -> *mut u8;
+    fn size(&self) -> usize;
+}
+
+unsafe impl FrameBuffer for Box<[u8]> {
+    fn as_mut_ptr(&mut self) -> *mut u8 {
+        <[u8]>::as_mut_ptr(self)
+    }
+
+    fn size(&self) -> usize {
+        self.len()
+    }
+}
+
+/// Provides the frame buffers of a capture session, e.g. page-aligned, huge page or shared memory.
+///
+/// Closures `Fn(usize) -> Result<Box<dyn FrameBuffer>>` are allocators too.
+pub trait BufferAllocator {
+    /// A buffer of at least `size` bytes.
+    fn allocate(&self, size: usize) -> Result<Box<dyn FrameBuffer>>;
+}
+
+impl<F: Fn(usize) -> Result<Box<dyn FrameBuffer>>> BufferAllocator for F {
+    fn allocate(&self, size: usize) -> Result<Box<dyn FrameBuffer>> {
+        self(size)
+    }
+}
+
+/// Zeroed buffers from the global allocator, the default.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct HeapAllocator;
+
+impl BufferAllocator for HeapAllocator {
+    fn allocate(&self, size: usize) -> Result<Box<dyn FrameBuffer>> {
+        Ok(Box::new(vec![0u8; size].into_boxed_slice()))
+    }
+}
+
+/// Zeroed buffers from the global allocator, aligned to `align` bytes.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct AlignedAllocator {
+    align: usize,
+}
+
+impl AlignedAllocator {
+    /// Aligned to 4 KiB pages.
+    pub const PAGE: AlignedAllocator = AlignedAllocator { align: 4096 };
+
+    /// Fails with [`ErrorCode::InvalidParameter`] unless `align` is a power of two.
+    pub fn new(align: usize) -> Result<Self> {
+        if !align.is_power_of_two() {
+            return Err(ErrorCode::InvalidParameter.into());
+        }
+        Ok(AlignedAllocator { align })
+    }
+
+    pub fn align(&self) -> usize {
+        self.align
+    }
+}
+
+impl BufferAllocator for AlignedAllocator {
+    fn allocate(&self, size: usize) -> Result<Box<dyn FrameBuffer>> {
+        let layout = Layout::from_size_align(size.max(1), self.align)
+            .map_err(|_| DeviceError::from(ErrorCode::InvalidParameter))?;
+        // SAFETY: the layout has a non-zero size.
+        let ptr = NonNull::new(unsafe { alloc::alloc_zeroed(layout) })
+            .ok_or_else(|| DeviceError::from(ErrorCode::OutOfMemory))?;
+        Ok(Box::new(AlignedBuffer { ptr, size, layout }))
+    }
+}
+
+#[derive(Debug)]
+struct AlignedBuffer {
+    ptr: NonNull<u8>,
+    size: usize,
+    layout: Layout,
+}
+
+// SAFETY: the buffer exclusively owns its allocation.
+unsafe impl Send for AlignedBuffer {}
+unsafe impl Sync for AlignedBuffer {}
+
+unsafe impl FrameBuffer for AlignedBuffer {
+    fn as_mut_ptr(&mut self) -> *mut u8 {
+        self.ptr.as_ptr()
+    }
+
+    fn size(&self) -> usize {
+        self.size
+    }
+}
+
+impl Drop for AlignedBuffer {
+    fn drop(&mut self) {
+        // SAFETY: allocated in `AlignedAllocator::allocate` with this layout.
+        unsafe { alloc::dealloc(self.ptr.as_ptr(), self.layout) };
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_aligned_allocator() {
+        assert!(AlignedAllocator::new(3).is_err());
+        let mut buf = AlignedAllocator::PAGE.allocate(10_000).unwrap();
+        assert_eq!(buf.size(), 10_000);
+        assert!((buf.as_mut_ptr() as usize).is_multiple_of(4096));
+
+        let mut buf = HeapAllocator.allocate(16).unwrap();
+        assert_eq!(buf.size(), 16);
+        assert!(!buf.as_mut_ptr().is_null());
+
+        let custom = |size| HeapAllocator.allocate(size * 2);
+        assert_eq!(custom.allocate(8).unwrap().size(), 16);
+    }
+}
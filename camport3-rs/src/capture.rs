@@ -0,0 +1,406 @@
+use std::ffi::c_void;
+use std::fmt;
+use std::sync::{Arc, Mutex, PoisonError};
+use camport3_sys::*;
+
+use crate::ffi::*;
+use crate::buffer::{BufferAllocator, FrameBuffer, HeapAllocator};
+use crate::depth::CameraIntrinsic;
+use crate::feature::DEPTH;
+
+/// Number of frame buffers queued by [`CaptureSession::new`] callers that have no preference.
+pub const DEFAULT_BUFFER_COUNT: usize = 2;
+
+/// One image of a fetched frame.
+///
+/// Images from [`CaptureSession::fetch_frame`] own a copy of the data. Images from
+/// [`CaptureSession::fetch_shared_frame`] point into the frame buffer, which is re-enqueued once the last image of
+/// the frame is dropped; cloning them is cheap.
+#[derive(Debug, Clone)]
+pub struct Image {
+    pub component: TY_COMPONENT_ID,
+    /// Timestamp in microseconds.
+    pub timestamp: u64,
+    /// Image index, used in trigger mode.
+    pub image_index: i32,
+    pub status: i32,
+    pub width: u32,
+    pub height: u32,
+    pub pixel_format: TY_PIXEL_FORMAT,
+    /// Intrinsics of depth images under the image mode and ROI of the session start, see
+    /// [`CaptureSession::depth_intrinsic`].
+    pub intrinsic: Option<CameraIntrinsic>,
+    data: ImageData,
+}
+
+#[derive(Debug, Clone)]
+enum ImageData {
+    Owned(Vec<u8>),
+    Shared { lease: Arc<FrameLease>, offset: usize, len: usize },
+}
+
+impl Image {
+    /// # Safety
+    /// `img.buffer` must be valid for `img.size` bytes.
+    unsafe fn from_raw(img: &TY_IMAGE_DATA) -> Self {
+        let size = img.size.max(0) as usize;
+        let data = unsafe { std::slice::from_raw_parts(img.buffer as *const u8, size) };
+        Self::with_data(img, ImageData::Owned(data.to_vec()))
+    }
+
+    /// Image pointing into the leased frame buffer, or a copy if the SDK placed it elsewhere.
+    ///
+    /// # Safety
+    /// `img.buffer` must be valid for `img.size` bytes.
+    unsafe fn from_lease(img: &TY_IMAGE_DATA, lease: &Arc<FrameLease>) -> Self {
+        let len = img.size.max(0) as usize;
+        let offset = (img.buffer as usize).wrapping_sub(lease.buffer as usize);
+        if offset.checked_add(len).is_none_or(|end| end > lease.size) {
+            return unsafe { Self::from_raw(img) };
+        }
+        Self::with_data(img, ImageData::Shared { lease: Arc::clone(lease), offset, len })
+    }
+
+    fn with_data(img: &TY_IMAGE_DATA, data: ImageData) -> Self {
+        Image {
+            component: img.componentID,
+            timestamp: img.timestamp,
+            image_index: img.imageIndex,
+            status: img.status,
+            width: img.width.max(0) as u32,
+            height: img.height.max(0) as u32,
+            pixel_format: img.pixelFormat,
+            intrinsic: None,
+            data,
+        }
+    }
+
+    pub fn is_component(&self, component: TY_DEVICE_COMPONENT_LIST) -> bool {
+        self.component == component as TY_COMPONENT_ID
+    }
+
+    pub fn data(&self) -> &[u8] {
+        match &self.data {
+            ImageData::Owned(data) => data,
+            // SAFETY: the range was checked against the buffer, which stays dequeued while the lease lives.
+            ImageData::Shared { lease, offset, len } => unsafe {
+                std::slice::from_raw_parts(lease.buffer.cast::<u8>().add(*offset), *len)
+            },
+        }
+    }
+
+    /// Whether the data points into a frame buffer still held from the SDK.
+    pub fn is_shared(&self) -> bool {
+        matches!(self.data, ImageData::Shared { .. })
+    }
+
+    /// The data, copied out of the frame buffer for shared images.
+    pub fn into_data(self) -> Vec<u8> {
+        match self.data {
+            ImageData::Owned(data) => data,
+            ImageData::Shared { .. } => self.data().to_vec(),
+        }
+    }
+}
+
+/// Images delivered together by one `TYFetchFrame` call.
+#[derive(Debug, Clone, Default)]
+pub struct Frame {
+    images: Vec<Image>,
+}
+
+impl Frame {
+    pub fn images(&self) -> &[Image] {
+        &self.images
+    }
+
+    pub fn into_images(self) -> Vec<Image> {
+        self.images
+    }
+
+    pub fn image(&self, component: TY_DEVICE_COMPONENT_LIST) -> Option<&Image> {
+        self.images.iter().find(|img| img.is_component(component))
+    }
+
+    pub fn depth(&self) -> Option<&Image> {
+        self.image(TY_DEVICE_COMPONENT_LIST::TY_COMPONENT_DEPTH_CAM)
+    }
+
+    pub fn left_ir(&self) -> Option<&Image> {
+        self.image(TY_DEVICE_COMPONENT_LIST::TY_COMPONENT_IR_CAM_LEFT)
+    }
+
+    pub fn right_ir(&self) -> Option<&Image> {
+        self.image(TY_DEVICE_COMPONENT_LIST::TY_COMPONENT_IR_CAM_RIGHT)
+    }
+
+    pub fn color(&self) -> Option<&Image> {
+        self.image(TY_DEVICE_COMPONENT_LIST::TY_COMPONENT_RGB_CAM)
+    }
+
+    /// Timestamp of the first image, in microseconds.
+    pub fn timestamp(&self) -> Option<u64> {
+        self.images.first().map(|img| img.timestamp)
+    }
+
+    /// Image index of the first image.
+    pub fn image_index(&self) -> Option<i32> {
+        self.images.first().map(|img| img.image_index)
+    }
+}
+
+#[cfg(test)]
+impl Image {
+    /// An empty image.
+    pub(crate) fn from_parts(component: TY_DEVICE_COMPONENT_LIST, image_index: i32, timestamp: u64) -> Self {
+        Image {
+            component: component as TY_COMPONENT_ID,
+            timestamp,
+            image_index,
+            status: 0,
+            width: 0,
+            height: 0,
+            pixel_format: 0,
+            intrinsic: None,
+            data: ImageData::Owned(Vec::new()),
+        }
+    }
+}
+
+#[cfg(test)]
+impl Frame {
+    /// A frame holding one empty depth image.
+    pub(crate) fn from_parts(image_index: i32, timestamp: u64) -> Self {
+        let depth = Image::from_parts(TY_DEVICE_COMPONENT_LIST::TY_COMPONENT_DEPTH_CAM, image_index, timestamp);
+        Frame { images: vec![depth] }
+    }
+}
+
+/// Frame buffers of a session, freed once the session and every shared frame are gone.
+struct BufferPool {
+    dev: Arc<DeviceHandle>,
+    buffers: Vec<Box<dyn FrameBuffer>>,
+    /// Cleared with the SDK queue; leases released afterwards keep their buffer out of the queue.
+    queued: Mutex<bool>,
+}
+
+impl fmt::Debug for BufferPool {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("BufferPool").field("buffers", &self.buffers.len()).finish_non_exhaustive()
+    }
+}
+
+impl BufferPool {
+    /// # Safety
+    /// `buffer` must be one of the pool's buffers, not currently queued, and `size` at most its size.
+    unsafe fn enqueue(&self, buffer: *mut c_void, size: usize) -> Result<()> {
+        let queued = self.queued.lock().unwrap_or_else(PoisonError::into_inner);
+        if !*queued {
+            return Ok(());
+        }
+        // SAFETY: the pool owns the buffer until the queue is cleared, which takes the same lock.
+        unsafe { ty_enqueue_buffer(&self.dev, buffer, size) }
+    }
+
+    fn clear_queue(&self) -> Result<()> {
+        let mut queued = self.queued.lock().unwrap_or_else(PoisonError::into_inner);
+        *queued = false;
+        ty_clear_buffer_queue(&self.dev)
+    }
+}
+
+/// A frame buffer dequeued by [`CaptureSession::fetch_shared_frame`], re-enqueued when dropped.
+struct FrameLease {
+    pool: Arc<BufferPool>,
+    buffer: *mut c_void,
+    size: usize,
+}
+
+// SAFETY: the buffer is only read while leased, and the pool it belongs to is `Send + Sync`.
+unsafe impl Send for FrameLease {}
+unsafe impl Sync for FrameLease {}
+
+impl fmt::Debug for FrameLease {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("FrameLease").field("buffer", &self.buffer).field("size", &self.size).finish()
+    }
+}
+
+impl Drop for FrameLease {
+    fn drop(&mut self) {
+        // SAFETY: `buffer` was dequeued by `TYFetchFrame` and no image references it any more.
+        if let Err(e) = unsafe { self.pool.enqueue(self.buffer, self.size) } {
+            log::warn!("TYEnqueueBuffer failed: {e}");
+        }
+    }
+}
+
+/// Frame buffers queued on a device, plus the capture state.
+///
+/// Dropping the session stops capturing and clears the SDK buffer queue. The buffers are freed once the session
+/// and all shared frames are gone.
+#[derive(Debug)]
+pub struct CaptureSession {
+    dev: Arc<DeviceHandle>,
+    pool: Arc<BufferPool>,
+    capturing: bool,
+    depth_intrinsic: Option<CameraIntrinsic>,
+}
+
+impl CaptureSession {
+    /// Allocate and enqueue `buffer_count` buffers of `TYGetFrameBufferSize` bytes.
+    ///
+    /// Components must be enabled before, the buffer size depends on them.
+    pub fn new(dev: Arc<DeviceHandle>, buffer_count: usize) -> Result<Self> {
+        Self::with_allocator(dev, buffer_count, &HeapAllocator)
+    }
+
+    /// Like [`new`](Self::new), with buffers from `allocator`.
+    ///
+    /// Fails with [`ErrorCode::WrongSize`] if a buffer is smaller than `TYGetFrameBufferSize`.
+    pub fn with_allocator(dev: Arc<DeviceHandle>, buffer_count: usize, allocator: &dyn BufferAllocator) -> Result<Self> {
+        let size = dev.get_frame_buffer_size()?;
+        let mut buffers = Vec::with_capacity(buffer_count);
+        let mut ptrs = Vec::with_capacity(buffer_count);
+        for _ in 0..buffer_count {
+            let mut buf = allocator.allocate(size)?;
+            if buf.size() < size {
+                return Err(ErrorCode::WrongSize.into());
+            }
+            ptrs.push(buf.as_mut_ptr());
+            buffers.push(buf);
+        }
+        let pool = Arc::new(BufferPool { dev: Arc::clone(&dev), buffers, queued: Mutex::new(true) });
+        let session = CaptureSession { dev, pool, capturing: false, depth_intrinsic: None };
+        for ptr in ptrs {
+            // SAFETY: the buffer is owned by the pool, which outlives the queue.
+            unsafe { session.pool.enqueue(ptr.cast(), size)? };
+        }
+        Ok(session)
+    }
+
+    pub fn device(&self) -> &Arc<DeviceHandle> {
+        &self.dev
+    }
+
+    pub fn is_capturing(&self) -> bool {
+        self.capturing
+    }
+
+    /// Start capturing and read the depth intrinsics attached to depth images until the next start.
+    pub fn start(&mut self) -> Result<()> {
+        self.depth_intrinsic = read_depth_intrinsic(&self.dev);
+        ty_start_capture(&self.dev)?;
+        self.capturing = true;
+        Ok(())
+    }
+
+    /// Depth intrinsics under the image mode and ROI read by the last [`start`](Self::start), `None` before or
+    /// without depth calibration.
+    pub fn depth_intrinsic(&self) -> Option<CameraIntrinsic> {
+        self.depth_intrinsic
+    }
+
+    pub fn stop(&mut self) -> Result<()> {
+        ty_stop_capture(&self.dev)?;
+        self.capturing = false;
+        Ok(())
+    }
+
+    /// Fetch the next frame, waiting at most `timeout_ms` (-1 waits forever), and re-enqueue its buffer.
+    ///
+    /// Fails with [`ErrorCode::DeviceOffline`] once the device reported `TY_EVENT_DEVICE_OFFLINE`.
+    pub fn fetch_frame(&self, timeout_ms: i32) -> Result<Frame> {
+        if self.dev.is_offline() {
+            return Err(ErrorCode::DeviceOffline.into());
+        }
+        let raw = ty_fetch_frame(&self.dev, timeout_ms)?;
+        let images = valid_images(&raw)
+            // SAFETY: image buffers point into the frame buffer, which is not re-enqueued yet.
+            .map(|img| self.with_intrinsic(unsafe { Image::from_raw(img) }))
+            .collect();
+        // SAFETY: `userBuffer` is one of the session's buffers.
+        unsafe { self.pool.enqueue(raw.userBuffer, raw.bufferSize.max(0) as usize)? };
+        Ok(Frame { images })
+    }
+
+    /// Fetch the next frame like [`fetch_frame`](Self::fetch_frame), without copying the images.
+    ///
+    /// The frame buffer goes back to the SDK queue once the last image of the frame, or of its clones, is dropped.
+    /// Holding on to `buffer_count` shared frames starves the device, later fetches then time out.
+    pub fn fetch_shared_frame(&self, timeout_ms: i32) -> Result<Frame> {
+        if self.dev.is_offline() {
+            return Err(ErrorCode::DeviceOffline.into());
+        }
+        let raw = ty_fetch_frame(&self.dev, timeout_ms)?;
+        let lease = Arc::new(FrameLease {
+            pool: Arc::clone(&self.pool),
+            buffer: raw.userBuffer,
+            size: raw.bufferSize.max(0) as usize,
+        });
+        let images = valid_images(&raw)
+            // SAFETY: image buffers point into the frame buffer, which stays dequeued while the lease lives.
+            .map(|img| self.with_intrinsic(unsafe { Image::from_lease(img, &lease) }))
+            .collect();
+        Ok(Frame { images })
+    }
+
+    fn with_intrinsic(&self, mut img: Image) -> Image {
+        if img.is_component(TY_DEVICE_COMPONENT_LIST::TY_COMPONENT_DEPTH_CAM) {
+            img.intrinsic = self.depth_intrinsic;
+        }
+        img
+    }
+}
+
+/// Intrinsics of the depth component, `None` on devices without depth calibration.
+fn read_depth_intrinsic(dev: &DeviceHandle) -> Option<CameraIntrinsic> {
+    dev.read_opt(DEPTH, TY_FEATURE_ID_LIST::TY_STRUCT_CAM_CALIB_DATA, |dev, _, _| dev.depth_intrinsic())
+        .unwrap_or_else(|e| {
+            log::debug!("depth intrinsics unavailable: {e}");
+            None
+        })
+}
+
+fn valid_images(raw: &TY_FRAME_DATA) -> impl Iterator<Item = &TY_IMAGE_DATA> {
+    let count = (raw.validCount.max(0) as usize).min(raw.image.len());
+    raw.image[..count].iter().filter(|img| !img.buffer.is_null())
+}
+
+impl Drop for CaptureSession {
+    fn drop(&mut self) {
+        if self.capturing {
+            if let Err(e) = ty_stop_capture(&self.dev) {
+                log::warn!("TYStopCapture failed: {e}");
+            }
+        }
+        if let Err(e) = self.pool.clear_queue() {
+            log::warn!("TYClearBufferQueue failed: {e}");
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const VALID_ID: &str = "eth-30:0e:d5:57:c2:ea9b04a8c0";
+
+    #[test]
+    fn test_capture() {
+        let ctx = Context::new().unwrap();
+        ctx.update_interface_list().unwrap();
+        let iface = ctx.open_interface(VALID_ID).unwrap();
+        iface.update_device_list().unwrap();
+        let dev_id = iface.get_device_list(0).unwrap()[0].id().into_owned();
+        let dev = Arc::new(iface.open_device(&dev_id).unwrap());
+        dev.enable_components(TY_DEVICE_COMPONENT_LIST::TY_COMPONENT_DEPTH_CAM as TY_COMPONENT_ID).unwrap();
+
+        let mut session = CaptureSession::new(dev, DEFAULT_BUFFER_COUNT).unwrap();
+        session.start().unwrap();
+        let frame = session.fetch_frame(2000).unwrap();
+        let depth = frame.depth().unwrap();
+        assert!(depth.width > 0 && depth.height > 0);
+        assert!(!depth.data().is_empty());
+    }
+}
@@ -0,0 +1,263 @@
+use serde::{Deserialize, Serialize};
+use camport3_sys::*;
+
+use crate::ffi::*;
+use crate::feature::DEPTH;
+use crate::exposure::Rect;
+
+/// `TY_ENUM_DEPTH_QUALITY`.
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Eq, Hash)]
+#[serde(rename_all = "snake_case")]
+#[repr(u32)]
+pub enum DepthQuality {
+    Basic = TY_DEPTH_QUALITY_LIST::TY_DEPTH_QUALITY_BASIC as u32,
+    Medium = TY_DEPTH_QUALITY_LIST::TY_DEPTH_QUALITY_MEDIUM as u32,
+    High = TY_DEPTH_QUALITY_LIST::TY_DEPTH_QUALITY_HIGH as u32,
+}
+
+impl DepthQuality {
+    fn from_raw(raw: u32) -> Option<Self> {
+        [DepthQuality::Basic, DepthQuality::Medium, DepthQuality::High].into_iter().find(|q| *q as u32 == raw)
+    }
+}
+
+/// Depth range, quality and sensor ROI of the depth component. `None` fields are absent on read and left alone on
+/// apply.
+#[derive(Serialize, Deserialize, Debug, Clone, Default, PartialEq)]
+#[serde(default)]
+pub struct DepthConfig {
+    /// `TY_INT_DEPTH_MIN_MM`.
+    pub min_mm: Option<i32>,
+    /// `TY_INT_DEPTH_MAX_MM`.
+    pub max_mm: Option<i32>,
+    pub quality: Option<DepthQuality>,
+    /// `TY_BOOL_DEPTH_POSTPROC`.
+    pub postproc: Option<bool>,
+    /// `TY_BOOL_UNDISTORTION`.
+    pub undistortion: Option<bool>,
+    /// `TY_FLOAT_SCALE_UNIT`, millimetres per depth value.
+    pub scale_unit: Option<f32>,
+    /// `TY_INT_OFFSET_X`, `TY_INT_OFFSET_Y`, `TY_INT_WIDTH` and `TY_INT_HEIGHT`, in pixels of the current image mode
+    /// and within its size.
+    pub roi: Option<Rect>,
+}
+
+/// Pinhole intrinsics `fx`, `fy`, `cx`, `cy` of a `TY_CAMERA_INTRINSIC`, in pixels.
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq)]
+pub struct CameraIntrinsic {
+    pub fx: f32,
+    pub fy: f32,
+    pub cx: f32,
+    pub cy: f32,
+}
+
+impl From<TY_CAMERA_INTRINSIC> for CameraIntrinsic {
+    fn from(raw: TY_CAMERA_INTRINSIC) -> Self {
+        let d = raw.data;
+        CameraIntrinsic { fx: d[0], fy: d[4], cx: d[2], cy: d[5] }
+    }
+}
+
+impl From<CameraIntrinsic> for TY_CAMERA_INTRINSIC {
+    fn from(k: CameraIntrinsic) -> Self {
+        TY_CAMERA_INTRINSIC { data: [k.fx, 0.0, k.cx, 0.0, k.fy, k.cy, 0.0, 0.0, 1.0] }
+    }
+}
+
+impl CameraIntrinsic {
+    /// Intrinsics of the same camera at another resolution of the full field of view.
+    pub fn scaled(&self, from: (u32, u32), to: (u32, u32)) -> Self {
+        let sx = to.0 as f32 / from.0.max(1) as f32;
+        let sy = to.1 as f32 / from.1.max(1) as f32;
+        CameraIntrinsic { fx: self.fx * sx, fy: self.fy * sy, cx: self.cx * sx, cy: self.cy * sy }
+    }
+
+    /// Intrinsics of images cropped to `roi`.
+    pub fn cropped(&self, roi: Rect) -> Self {
+        CameraIntrinsic { cx: self.cx - roi.x as f32, cy: self.cy - roi.y as f32, ..*self }
+    }
+}
+
+fn check_depth_range(min_mm: Option<i32>, max_mm: Option<i32>) -> Result<()> {
+    match (min_mm, max_mm) {
+        (Some(min), Some(max)) if min >= max => Err(ErrorCode::InvalidParameter.into()),
+        _ => Ok(()),
+    }
+}
+
+fn read_roi(dev: &DeviceHandle) -> Result<Rect> {
+    use TY_FEATURE_ID_LIST::*;
+    let int = |feat| -> Result<u32> { Ok(dev.get_int(DEPTH, feat)?.max(0) as u32) };
+    Ok(Rect::new(int(TY_INT_OFFSET_X)?, int(TY_INT_OFFSET_Y)?, int(TY_INT_WIDTH)?, int(TY_INT_HEIGHT)?))
+}
+
+/// Full depth image size at the current image mode, before the ROI crop.
+///
+/// Falls back to `TY_INT_WIDTH_MAX` x `TY_INT_HEIGHT_MAX` on devices without `TY_ENUM_IMAGE_MODE`.
+fn image_size(dev: &DeviceHandle) -> Result<(u32, u32)> {
+    use TY_FEATURE_ID_LIST::*;
+    if dev.has_feature(DEPTH, TY_ENUM_IMAGE_MODE)? {
+        let mode = dev.get_image_mode(DEPTH)?;
+        return Ok((mode.width(), mode.height()));
+    }
+    let int = |feat| -> Result<u32> { Ok(dev.get_int(DEPTH, feat)?.max(0) as u32) };
+    Ok((int(TY_INT_WIDTH_MAX)?, int(TY_INT_HEIGHT_MAX)?))
+}
+
+impl DepthConfig {
+    pub fn read(dev: &DeviceHandle) -> Result<Self> {
+        use TY_FEATURE_ID_LIST::*;
+        let quality = dev.read_opt(DEPTH, TY_ENUM_DEPTH_QUALITY, |dev, _, _| {
+            let raw = dev.get_enum(DEPTH, TY_ENUM_DEPTH_QUALITY)?;
+            DepthQuality::from_raw(raw).ok_or_else(|| ErrorCode::NotImplemented.into())
+        })?;
+        let roi = dev.read_opt(DEPTH, TY_INT_OFFSET_X, |dev, _, _| read_roi(dev))?;
+        Ok(DepthConfig {
+            min_mm: dev.read_opt(DEPTH, TY_INT_DEPTH_MIN_MM, DeviceHandle::get_int)?,
+            max_mm: dev.read_opt(DEPTH, TY_INT_DEPTH_MAX_MM, DeviceHandle::get_int)?,
+            quality,
+            postproc: dev.read_opt(DEPTH, TY_BOOL_DEPTH_POSTPROC, DeviceHandle::get_bool)?,
+            undistortion: dev.read_opt(DEPTH, TY_BOOL_UNDISTORTION, DeviceHandle::get_bool)?,
+            scale_unit: dev.read_opt(DEPTH, TY_FLOAT_SCALE_UNIT, DeviceHandle::get_float)?,
+            roi,
+        })
+    }
+
+    /// Check the `Some` fields against the device and each other without writing anything.
+    ///
+    /// A minimum depth not below the maximum, including the device's current one when only one side is set, fails
+    /// with [`ErrorCode::InvalidParameter`]. An ROI outside the image or values outside their feature's range fail
+    /// with [`ErrorCode::OutOfRange`], a quality the device does not list with [`ErrorCode::NotPermitted`].
+    pub fn validate(&self, dev: &DeviceHandle) -> Result<()> {
+        use TY_FEATURE_ID_LIST::*;
+        let current = |feat, value: Option<i32>| match value {
+            Some(v) => Ok(Some(v)),
+            None => dev.read_opt(DEPTH, feat, DeviceHandle::get_int),
+        };
+        if self.min_mm.is_some() || self.max_mm.is_some() {
+            check_depth_range(current(TY_INT_DEPTH_MIN_MM, self.min_mm)?, current(TY_INT_DEPTH_MAX_MM, self.max_mm)?)?;
+        }
+        for (feat, value) in [(TY_INT_DEPTH_MIN_MM, self.min_mm), (TY_INT_DEPTH_MAX_MM, self.max_mm)] {
+            if value.is_some_and(|v| !dev.get_int_range(DEPTH, feat).is_ok_and(|r| r.contains(v))) {
+                return Err(ErrorCode::OutOfRange.into());
+            }
+        }
+        if let Some(quality) = self.quality {
+            let entries = dev.get_enum_entries(DEPTH, TY_ENUM_DEPTH_QUALITY)?;
+            if !entries.iter().any(|e| e.value() == quality as u32) {
+                return Err(ErrorCode::NotPermitted.into());
+            }
+        }
+        if let Some(unit) = self.scale_unit {
+            if !dev.get_float_range(DEPTH, TY_FLOAT_SCALE_UNIT)?.contains(unit) {
+                return Err(ErrorCode::OutOfRange.into());
+            }
+        }
+        if let Some(roi) = self.roi {
+            let (width, height) = image_size(dev)?;
+            if !roi.fits_in(width, height) {
+                return Err(ErrorCode::OutOfRange.into());
+            }
+        }
+        Ok(())
+    }
+
+    /// [`validate`](Self::validate), then write the `Some` fields.
+    pub fn apply(&self, dev: &DeviceHandle) -> Result<()> {
+        use TY_FEATURE_ID_LIST::*;
+        self.validate(dev)?;
+        // Widen before narrowing so the device never sees min >= max in between.
+        let current_max = dev.read_opt(DEPTH, TY_INT_DEPTH_MAX_MM, DeviceHandle::get_int)?;
+        let mut range = [(TY_INT_DEPTH_MIN_MM, self.min_mm), (TY_INT_DEPTH_MAX_MM, self.max_mm)];
+        if matches!((self.min_mm, current_max), (Some(min), Some(max)) if min >= max) {
+            range.reverse();
+        }
+        for (feat, value) in range {
+            if let Some(value) = value {
+                dev.set_int(DEPTH, feat, value)?;
+            }
+        }
+        if let Some(quality) = self.quality {
+            dev.set_enum(DEPTH, TY_ENUM_DEPTH_QUALITY, quality as u32)?;
+        }
+        if let Some(on) = self.postproc {
+            dev.set_bool(DEPTH, TY_BOOL_DEPTH_POSTPROC, on)?;
+        }
+        if let Some(on) = self.undistortion {
+            dev.set_bool(DEPTH, TY_BOOL_UNDISTORTION, on)?;
+        }
+        if let Some(unit) = self.scale_unit {
+            dev.set_float(DEPTH, TY_FLOAT_SCALE_UNIT, unit)?;
+        }
+        if let Some(roi) = self.roi {
+            // Offsets go to zero first so the new size always fits, then move into place.
+            dev.set_int_checked(DEPTH, TY_INT_OFFSET_X, 0)?;
+            dev.set_int_checked(DEPTH, TY_INT_OFFSET_Y, 0)?;
+            dev.set_int_checked(DEPTH, TY_INT_WIDTH, roi.width as i32)?;
+            dev.set_int_checked(DEPTH, TY_INT_HEIGHT, roi.height as i32)?;
+            dev.set_int_checked(DEPTH, TY_INT_OFFSET_X, roi.x as i32)?;
+            dev.set_int_checked(DEPTH, TY_INT_OFFSET_Y, roi.y as i32)?;
+        }
+        Ok(())
+    }
+}
+
+/// Intrinsics matching depth frames under the current image mode and ROI.
+///
+/// The calibration of `TY_STRUCT_CAM_CALIB_DATA` is scaled from its calibration size to the image size of the mode,
+/// which accounts for binning, and shifted by the ROI offset, so point cloud math on cropped frames stays correct.
+fn roi_intrinsic(calib: &TY_CAMERA_CALIB_INFO, image: (u32, u32), roi: Rect) -> CameraIntrinsic {
+    let calib_size = (calib.intrinsicWidth.max(0) as u32, calib.intrinsicHeight.max(0) as u32);
+    CameraIntrinsic::from(calib.intrinsic).scaled(calib_size, image).cropped(roi)
+}
+
+impl DeviceHandle {
+    /// Depth camera intrinsics for frames captured with the current image mode and ROI, see [`DepthConfig::roi`].
+    ///
+    /// Capture sessions attach it to depth images, see [`Image::intrinsic`](crate::Image::intrinsic).
+    pub fn depth_intrinsic(&self) -> Result<CameraIntrinsic> {
+        let calib: TY_CAMERA_CALIB_INFO = self.get_struct(DEPTH, TY_FEATURE_ID_LIST::TY_STRUCT_CAM_CALIB_DATA)?;
+        let roi = if self.has_feature(DEPTH, TY_FEATURE_ID_LIST::TY_INT_OFFSET_X)? {
+            read_roi(self)?
+        } else {
+            Rect::default()
+        };
+        Ok(roi_intrinsic(&calib, image_size(self)?, roi))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_roi_intrinsic() {
+        let k = CameraIntrinsic { fx: 500.0, fy: 500.0, cx: 320.0, cy: 240.0 };
+        let calib = TY_CAMERA_CALIB_INFO {
+            intrinsicWidth: 640,
+            intrinsicHeight: 480,
+            intrinsic: k.into(),
+            extrinsic: TY_CAMERA_EXTRINSIC { data: [0.0; 16] },
+            distortion: TY_CAMERA_DISTORTION { data: [0.0; 12] },
+        };
+        let full = roi_intrinsic(&calib, (1280, 960), Rect::new(0, 0, 1280, 960));
+        assert_eq!(full, CameraIntrinsic { fx: 1000.0, fy: 1000.0, cx: 640.0, cy: 480.0 });
+
+        let cropped = roi_intrinsic(&calib, (1280, 960), Rect::new(100, 200, 800, 400));
+        assert_eq!(cropped, CameraIntrinsic { fx: 1000.0, fy: 1000.0, cx: 540.0, cy: 280.0 });
+        assert_eq!(CameraIntrinsic::from(TY_CAMERA_INTRINSIC::from(cropped)), cropped);
+
+        // A binned mode halves the calibration, then the ROI offset applies in binned pixels.
+        let binned = roi_intrinsic(&calib, (320, 240), Rect::new(10, 20, 200, 100));
+        assert_eq!(binned, CameraIntrinsic { fx: 250.0, fy: 250.0, cx: 150.0, cy: 100.0 });
+    }
+
+    #[test]
+    fn test_depth_range() {
+        assert!(check_depth_range(Some(100), Some(2000)).is_ok());
+        assert!(check_depth_range(None, Some(2000)).is_ok());
+        assert_eq!(check_depth_range(Some(2000), Some(2000)).unwrap_err().errcode, ErrorCode::InvalidParameter);
+        assert_eq!(DepthQuality::from_raw(4), Some(DepthQuality::High));
+        assert_eq!(DepthQuality::from_raw(3), None);
+    }
+}
@@ -0,0 +1,260 @@
+use std::ops::RangeInclusive;
+use serde::{Deserialize, Serialize};
+use camport3_sys::*;
+
+use crate::ffi::*;
+use crate::feature::DEVICE;
+
+/// Digital output mode, `TY_E_DO_MODE`.
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Eq)]
+pub enum DoMode {
+    Low,
+    High,
+    /// Frequency in Hz, 1 to 1000, and duty cycle in percent, 1 to 100.
+    Pwm { freq: u32, duty: u32 },
+    /// Output follows the camera trigger.
+    CameraTrigger,
+}
+
+impl DoMode {
+    fn raw(self) -> TY_E_DO_MODE {
+        use TY_E_DO_MODE_LIST::*;
+        (match self {
+            DoMode::Low => TY_DO_LOW,
+            DoMode::High => TY_DO_HIGH,
+            DoMode::Pwm { .. } => TY_DO_PWM,
+            DoMode::CameraTrigger => TY_DO_CAM_TRIG,
+        }) as TY_E_DO_MODE
+    }
+
+    fn from_raw(raw: &TY_DO_WORKMODE) -> Option<Self> {
+        use TY_E_DO_MODE_LIST::*;
+        let mode = raw.mode;
+        Some(match mode {
+            m if m == TY_DO_LOW as u32 => DoMode::Low,
+            m if m == TY_DO_HIGH as u32 => DoMode::High,
+            m if m == TY_DO_PWM as u32 => DoMode::Pwm { freq: raw.freq, duty: raw.duty },
+            m if m == TY_DO_CAM_TRIG as u32 => DoMode::CameraTrigger,
+            _ => return None,
+        })
+    }
+}
+
+/// Digital output supply, `TY_E_VOLT_T`.
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Eq)]
+#[repr(u32)]
+pub enum DoVoltage {
+    External = TY_E_VOLT_T_LIST::TY_EXT_SUP as u32,
+    V5 = TY_E_VOLT_T_LIST::TY_DO_5V as u32,
+    V12 = TY_E_VOLT_T_LIST::TY_DO_12V as u32,
+}
+
+impl DoVoltage {
+    fn from_raw(raw: TY_E_VOLT_T) -> Option<Self> {
+        [DoVoltage::External, DoVoltage::V5, DoVoltage::V12].into_iter().find(|v| *v as u32 == raw)
+    }
+}
+
+/// Digital input mode, `TY_E_DI_MODE`.
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Eq)]
+#[repr(u32)]
+pub enum DiMode {
+    Poll = TY_E_DI_MODE_LIST::TY_DI_POLL as u32,
+    FallingEdge = TY_E_DI_MODE_LIST::TY_DI_NE_INT as u32,
+    RisingEdge = TY_E_DI_MODE_LIST::TY_DI_PE_INT as u32,
+}
+
+/// Action taken on a digital input interrupt, `TY_E_DI_INT_ACTION`.
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Eq)]
+#[repr(u32)]
+pub enum DiAction {
+    None = TY_E_DI_INT_ACTION_LIST::TY_DI_INT_NO_OP as u32,
+    TriggerCapture = TY_E_DI_INT_ACTION_LIST::TY_DI_INT_TRIG_CAP as u32,
+    /// Report a device event.
+    Event = TY_E_DI_INT_ACTION_LIST::TY_DI_INT_EVENT as u32,
+}
+
+impl DiMode {
+    fn from_raw(raw: TY_E_DI_MODE) -> Option<Self> {
+        [DiMode::Poll, DiMode::FallingEdge, DiMode::RisingEdge].into_iter().find(|m| *m as u32 == raw)
+    }
+}
+
+impl DiAction {
+    fn from_raw(raw: TY_E_DI_INT_ACTION) -> Option<Self> {
+        [DiAction::None, DiAction::TriggerCapture, DiAction::Event].into_iter().find(|a| *a as u32 == raw)
+    }
+}
+
+fn supported(mask: u32, value: u32) -> bool {
+    value < 32 && mask & (1 << value) != 0
+}
+
+/// Current work mode of a digital output and what the hardware supports.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct DigitalOutput {
+    pub mode: DoMode,
+    pub voltage: DoVoltage,
+    modes_supported: u32,
+    volts_supported: u32,
+}
+
+impl DigitalOutput {
+    pub fn supports_mode(&self, mode: DoMode) -> bool {
+        supported(self.modes_supported, mode.raw())
+    }
+
+    pub fn supports_voltage(&self, voltage: DoVoltage) -> bool {
+        supported(self.volts_supported, voltage as u32)
+    }
+}
+
+/// Current work mode and level of a digital input, and what the hardware supports.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct DigitalInput {
+    pub mode: DiMode,
+    pub action: DiAction,
+    /// Input level, `TY_DI_WORKMODE::status`.
+    pub status: u32,
+    modes_supported: u32,
+    actions_supported: u32,
+}
+
+impl DigitalInput {
+    pub fn is_high(&self) -> bool {
+        self.status != 0
+    }
+
+    pub fn supports_mode(&self, mode: DiMode) -> bool {
+        supported(self.modes_supported, mode as u32)
+    }
+
+    pub fn supports_action(&self, action: DiAction) -> bool {
+        supported(self.actions_supported, action as u32)
+    }
+}
+
+fn do_feature(index: usize) -> Result<TY_FEATURE_ID_LIST> {
+    use TY_FEATURE_ID_LIST::*;
+    [TY_STRUCT_DO0_WORKMODE, TY_STRUCT_DO1_WORKMODE, TY_STRUCT_DO2_WORKMODE]
+        .get(index).copied().ok_or_else(|| ErrorCode::InvalidParameter.into())
+}
+
+fn di_feature(index: usize) -> Result<TY_FEATURE_ID_LIST> {
+    use TY_FEATURE_ID_LIST::*;
+    [TY_STRUCT_DI0_WORKMODE, TY_STRUCT_DI1_WORKMODE, TY_STRUCT_DI2_WORKMODE]
+        .get(index).copied().ok_or_else(|| ErrorCode::InvalidParameter.into())
+}
+
+/// PWM ranges documented for `TY_DO_WORKMODE`.
+const PWM_FREQ_HZ: RangeInclusive<u32> = 1..=1000;
+const PWM_DUTY_PERCENT: RangeInclusive<u32> = 1..=100;
+
+/// Work mode to write for `mode` and `voltage`, given the current one.
+///
+/// Unsupported modes or voltages fail with [`ErrorCode::NotPermitted`], a PWM frequency outside 1 to 1000 Hz or duty
+/// cycle outside 1 to 100% with [`ErrorCode::OutOfRange`].
+fn do_request(current: &TY_DO_WORKMODE, mode: DoMode, voltage: DoVoltage) -> Result<TY_DO_WORKMODE> {
+    let (modes, volts) = (current.mode_supported, current.volt_supported);
+    if !supported(modes, mode.raw()) || !supported(volts, voltage as u32) {
+        return Err(ErrorCode::NotPermitted.into());
+    }
+    let mut out = *current;
+    out.mode = mode.raw();
+    out.volt = voltage as u32;
+    if let DoMode::Pwm { freq, duty } = mode {
+        if !(PWM_FREQ_HZ.contains(&freq) && PWM_DUTY_PERCENT.contains(&duty)) {
+            return Err(ErrorCode::OutOfRange.into());
+        }
+        out.freq = freq;
+        out.duty = duty;
+    }
+    Ok(out)
+}
+
+fn di_request(current: &TY_DI_WORKMODE, mode: DiMode, action: DiAction) -> Result<TY_DI_WORKMODE> {
+    let (modes, actions) = (current.mode_supported, current.int_act_supported);
+    if !supported(modes, mode as u32) || !supported(actions, action as u32) {
+        return Err(ErrorCode::NotPermitted.into());
+    }
+    let mut out = *current;
+    out.mode = mode as u32;
+    out.int_act = action as u32;
+    Ok(out)
+}
+
+impl DeviceHandle {
+    /// Read `TY_STRUCT_DO<index>_WORKMODE`, `index` in `0..3`.
+    pub fn get_digital_output(&self, index: usize) -> Result<DigitalOutput> {
+        let raw: TY_DO_WORKMODE = self.get_struct(DEVICE, do_feature(index)?)?;
+        Ok(DigitalOutput {
+            mode: DoMode::from_raw(&raw).ok_or(ErrorCode::NotImplemented)?,
+            voltage: DoVoltage::from_raw(raw.volt).ok_or(ErrorCode::NotImplemented)?,
+            modes_supported: raw.mode_supported,
+            volts_supported: raw.volt_supported,
+        })
+    }
+
+    /// Set a digital output, checking the mode and voltage against what the hardware reports as supported.
+    pub fn set_digital_output(&self, index: usize, mode: DoMode, voltage: DoVoltage) -> Result<()> {
+        let feat = do_feature(index)?;
+        let current: TY_DO_WORKMODE = self.get_struct(DEVICE, feat)?;
+        self.set_struct(DEVICE, feat, &do_request(&current, mode, voltage)?)
+    }
+
+    /// Read `TY_STRUCT_DI<index>_WORKMODE`, including the input level, `index` in `0..3`.
+    pub fn get_digital_input(&self, index: usize) -> Result<DigitalInput> {
+        let raw: TY_DI_WORKMODE = self.get_struct(DEVICE, di_feature(index)?)?;
+        Ok(DigitalInput {
+            mode: DiMode::from_raw(raw.mode).ok_or(ErrorCode::NotImplemented)?,
+            action: DiAction::from_raw(raw.int_act).ok_or(ErrorCode::NotImplemented)?,
+            status: raw.status,
+            modes_supported: raw.mode_supported,
+            actions_supported: raw.int_act_supported,
+        })
+    }
+
+    /// Set a digital input, checking the mode and interrupt action against what the hardware supports.
+    pub fn set_digital_input(&self, index: usize, mode: DiMode, action: DiAction) -> Result<()> {
+        let feat = di_feature(index)?;
+        let current: TY_DI_WORKMODE = self.get_struct(DEVICE, feat)?;
+        self.set_struct(DEVICE, feat, &di_request(&current, mode, action)?)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_do_request() {
+        let current = TY_DO_WORKMODE {
+            mode: 0, volt: 0, freq: 0, duty: 0,
+            mode_supported: 0b0111, volt_supported: 0b010, reserved: [0; 3],
+        };
+        let pwm = DoMode::Pwm { freq: 1000, duty: 25 };
+        let req = do_request(&current, pwm, DoVoltage::V5).unwrap();
+        assert_eq!(DoMode::from_raw(&req), Some(pwm));
+        assert_eq!({ req.volt }, DoVoltage::V5 as u32);
+
+        let err = |r: Result<_>| r.unwrap_err().errcode;
+        assert_eq!(err(do_request(&current, DoMode::CameraTrigger, DoVoltage::V5)), ErrorCode::NotPermitted);
+        assert_eq!(err(do_request(&current, DoMode::High, DoVoltage::V12)), ErrorCode::NotPermitted);
+        let pwm = |freq, duty| do_request(&current, DoMode::Pwm { freq, duty }, DoVoltage::V5);
+        assert!(pwm(1, 1).is_ok() && pwm(1000, 100).is_ok());
+        for (freq, duty) in [(1, 101), (1, 0), (0, 50), (1001, 50)] {
+            assert_eq!(err(pwm(freq, duty)), ErrorCode::OutOfRange);
+        }
+    }
+
+    #[test]
+    fn test_di_request() {
+        let current = TY_DI_WORKMODE {
+            mode: 0, int_act: 0, mode_supported: 0b101, int_act_supported: 0b011, status: 1, reserved: [0; 3],
+        };
+        let req = di_request(&current, DiMode::RisingEdge, DiAction::TriggerCapture).unwrap();
+        assert_eq!(({ req.mode }, { req.int_act }), (DiMode::RisingEdge as u32, DiAction::TriggerCapture as u32));
+        assert!(di_request(&current, DiMode::FallingEdge, DiAction::None).is_err());
+        assert!(di_request(&current, DiMode::Poll, DiAction::Event).is_err());
+    }
+}
@@ -0,0 +1,97 @@
+use std::ffi::c_void;
+use std::fmt::Debug;
+use std::panic::{catch_unwind, AssertUnwindSafe};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{Mutex, PoisonError};
+use camport3_sys::*;
+
+use crate::ffi::*;
+use crate::utils::carr_to_str;
+
+/// Device event delivered by `TYRegisterEventCallback`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct DeviceEvent {
+    pub id: TY_EVENT,
+    pub message: String,
+}
+
+impl DeviceEvent {
+    pub fn kind(&self) -> Option<TY_EVENT_LIST> {
+        use TY_EVENT_LIST::*;
+        [TY_EVENT_DEVICE_OFFLINE, TY_EVENT_LICENSE_ERROR, TY_EVENT_FW_INIT_ERROR]
+            .into_iter()
+            .find(|kind| *kind as TY_EVENT == self.id)
+    }
+
+    pub fn is_offline(&self) -> bool {
+        self.kind() == Some(TY_EVENT_LIST::TY_EVENT_DEVICE_OFFLINE)
+    }
+}
+
+type EventListener = Box<dyn Fn(&DeviceEvent) + Send + Sync>;
+
+/// Receives the SDK event callback of one device and fans it out to the registered listeners.
+#[derive(Default)]
+pub(crate) struct EventDispatcher {
+    offline: AtomicBool,
+    listeners: Mutex<Vec<EventListener>>,
+}
+
+impl Debug for EventDispatcher {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("EventDispatcher")
+            .field("offline", &self.offline)
+            .finish_non_exhaustive()
+    }
+}
+
+impl EventDispatcher {
+    pub(crate) fn is_offline(&self) -> bool {
+        self.offline.load(Ordering::Acquire)
+    }
+
+    pub(crate) fn add_listener(&self, f: EventListener) {
+        self.listeners.lock().unwrap_or_else(PoisonError::into_inner).push(f);
+    }
+
+    fn dispatch(&self, event: DeviceEvent) {
+        if event.is_offline() {
+            self.offline.store(true, Ordering::Release);
+        }
+        for f in self.listeners.lock().unwrap_or_else(PoisonError::into_inner).iter() {
+            f(&event);
+        }
+    }
+}
+
+/// `TY_EVENT_CALLBACK` trampoline, `userdata` points to the device's [`EventDispatcher`].
+pub(crate) unsafe extern "C" fn event_callback(info: *mut TY_EVENT_INFO, userdata: *mut c_void) {
+    if info.is_null() || userdata.is_null() {
+        return;
+    }
+    let dispatcher = unsafe { &*(userdata as *const EventDispatcher) };
+    let info = unsafe { &*info };
+    let event = DeviceEvent {
+        id: info.eventId,
+        message: carr_to_str(&info.message).into_owned(),
+    };
+    if catch_unwind(AssertUnwindSafe(|| dispatcher.dispatch(event))).is_err() {
+        log::error!("device event listener panicked");
+    }
+}
+
+impl DeviceHandle {
+    /// Call `f` for every event the SDK reports for this device, from the SDK's event thread.
+    ///
+    /// `f` should not call into the device: the SDK may report the event while another thread holds the device lock.
+    pub fn on_event<F>(&self, f: F)
+    where F: Fn(&DeviceEvent) + Send + Sync + 'static
+    {
+        self.events().add_listener(Box::new(f));
+    }
+
+    /// Whether `TY_EVENT_DEVICE_OFFLINE` has been reported since the device was opened.
+    pub fn is_offline(&self) -> bool {
+        self.events().is_offline()
+    }
+}
@@ -0,0 +1,198 @@
+use serde::{Deserialize, Serialize};
+use camport3_sys::*;
+
+use crate::ffi::*;
+use crate::feature::*;
+
+/// Pixel rectangle, e.g. the auto exposure statistics region.
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Eq, Hash, Default)]
+pub struct Rect {
+    pub x: u32,
+    pub y: u32,
+    pub width: u32,
+    pub height: u32,
+}
+
+impl Rect {
+    pub fn new(x: u32, y: u32, width: u32, height: u32) -> Self {
+        Rect { x, y, width, height }
+    }
+
+    /// Whether the rectangle is non-empty and lies within a `width` x `height` image.
+    pub fn fits_in(&self, width: u32, height: u32) -> bool {
+        self.width > 0 && self.height > 0
+            && self.x.checked_add(self.width).is_some_and(|r| r <= width)
+            && self.y.checked_add(self.height).is_some_and(|b| b <= height)
+    }
+}
+
+impl From<TY_AEC_ROI_PARAM> for Rect {
+    fn from(roi: TY_AEC_ROI_PARAM) -> Self {
+        Rect { x: roi.x, y: roi.y, width: roi.w, height: roi.h }
+    }
+}
+
+impl From<Rect> for TY_AEC_ROI_PARAM {
+    fn from(r: Rect) -> Self {
+        TY_AEC_ROI_PARAM { x: r.x, y: r.y, w: r.width, h: r.height }
+    }
+}
+
+/// Exposure, gain and white balance controls of one camera component, see [`DeviceHandle::exposure`].
+///
+/// Integer setters clamp to the feature range and return the value written.
+#[derive(Debug, Clone, Copy)]
+pub struct Exposure<'a> {
+    dev: &'a DeviceHandle,
+    comp: TY_COMPONENT_ID,
+}
+
+impl DeviceHandle {
+    /// Controls of `comp`, typically `TY_COMPONENT_RGB_CAM` or one of the IR cameras.
+    pub fn exposure(&self, comp: impl ComponentId) -> Exposure<'_> {
+        Exposure { dev: self, comp: comp.component_id() }
+    }
+}
+
+impl Exposure<'_> {
+    fn get_bool(&self, feat: TY_FEATURE_ID_LIST) -> Result<bool> {
+        self.dev.get_bool(self.comp, feat)
+    }
+
+    fn set_bool(&self, feat: TY_FEATURE_ID_LIST, on: bool) -> Result<()> {
+        self.dev.set_bool(self.comp, feat, on)
+    }
+
+    fn get_int(&self, feat: TY_FEATURE_ID_LIST) -> Result<i32> {
+        self.dev.get_int(self.comp, feat)
+    }
+
+    fn set_int(&self, feat: TY_FEATURE_ID_LIST, value: i32) -> Result<i32> {
+        self.dev.set_int_clamped(self.comp, feat, value)
+    }
+
+    pub fn auto_exposure(&self) -> Result<bool> {
+        self.get_bool(TY_FEATURE_ID_LIST::TY_BOOL_AUTO_EXPOSURE)
+    }
+
+    pub fn set_auto_exposure(&self, on: bool) -> Result<()> {
+        self.set_bool(TY_FEATURE_ID_LIST::TY_BOOL_AUTO_EXPOSURE, on)
+    }
+
+    pub fn exposure_time(&self) -> Result<i32> {
+        self.get_int(TY_FEATURE_ID_LIST::TY_INT_EXPOSURE_TIME)
+    }
+
+    /// Only effective with auto exposure off.
+    pub fn set_exposure_time(&self, value: i32) -> Result<i32> {
+        self.set_int(TY_FEATURE_ID_LIST::TY_INT_EXPOSURE_TIME, value)
+    }
+
+    pub fn auto_gain(&self) -> Result<bool> {
+        self.get_bool(TY_FEATURE_ID_LIST::TY_BOOL_AUTO_GAIN)
+    }
+
+    pub fn set_auto_gain(&self, on: bool) -> Result<()> {
+        self.set_bool(TY_FEATURE_ID_LIST::TY_BOOL_AUTO_GAIN, on)
+    }
+
+    pub fn gain(&self) -> Result<i32> {
+        self.get_int(TY_FEATURE_ID_LIST::TY_INT_GAIN)
+    }
+
+    pub fn set_gain(&self, value: i32) -> Result<i32> {
+        self.set_int(TY_FEATURE_ID_LIST::TY_INT_GAIN, value)
+    }
+
+    pub fn analog_gain(&self) -> Result<i32> {
+        self.get_int(TY_FEATURE_ID_LIST::TY_INT_ANALOG_GAIN)
+    }
+
+    pub fn set_analog_gain(&self, value: i32) -> Result<i32> {
+        self.set_int(TY_FEATURE_ID_LIST::TY_INT_ANALOG_GAIN, value)
+    }
+
+    pub fn auto_white_balance(&self) -> Result<bool> {
+        self.get_bool(TY_FEATURE_ID_LIST::TY_BOOL_AUTO_AWB)
+    }
+
+    pub fn set_auto_white_balance(&self, on: bool) -> Result<()> {
+        self.set_bool(TY_FEATURE_ID_LIST::TY_BOOL_AUTO_AWB, on)
+    }
+
+    /// `TY_INT_R_GAIN`, `TY_INT_G_GAIN` and `TY_INT_B_GAIN`.
+    pub fn white_balance(&self) -> Result<(i32, i32, i32)> {
+        use TY_FEATURE_ID_LIST::*;
+        Ok((self.get_int(TY_INT_R_GAIN)?, self.get_int(TY_INT_G_GAIN)?, self.get_int(TY_INT_B_GAIN)?))
+    }
+
+    /// Only effective with auto white balance off.
+    pub fn set_white_balance(&self, r: i32, g: i32, b: i32) -> Result<(i32, i32, i32)> {
+        use TY_FEATURE_ID_LIST::*;
+        Ok((self.set_int(TY_INT_R_GAIN, r)?, self.set_int(TY_INT_G_GAIN, g)?, self.set_int(TY_INT_B_GAIN, b)?))
+    }
+
+    /// Target brightness of auto exposure.
+    pub fn ae_target(&self) -> Result<i32> {
+        self.get_int(TY_FEATURE_ID_LIST::TY_INT_AE_TARGET_Y)
+    }
+
+    pub fn set_ae_target(&self, value: i32) -> Result<i32> {
+        self.set_int(TY_FEATURE_ID_LIST::TY_INT_AE_TARGET_Y, value)
+    }
+
+    /// Region auto exposure statistics are computed on.
+    pub fn aec_roi(&self) -> Result<Rect> {
+        let roi: TY_AEC_ROI_PARAM = self.dev.get_struct(self.comp, TY_FEATURE_ID_LIST::TY_STRUCT_AEC_ROI)?;
+        Ok(roi.into())
+    }
+
+    /// Fails with [`ErrorCode::OutOfRange`] unless `roi` fits in the component's current image size.
+    pub fn set_aec_roi(&self, roi: Rect) -> Result<()> {
+        use TY_FEATURE_ID_LIST::*;
+        let (width, height) = (self.get_int(TY_INT_WIDTH)?, self.get_int(TY_INT_HEIGHT)?);
+        if !roi.fits_in(width.max(0) as u32, height.max(0) as u32) {
+            return Err(ErrorCode::OutOfRange.into());
+        }
+        self.dev.set_struct(self.comp, TY_STRUCT_AEC_ROI, &TY_AEC_ROI_PARAM::from(roi))
+    }
+
+    pub fn hdr(&self) -> Result<bool> {
+        self.get_bool(TY_FEATURE_ID_LIST::TY_BOOL_HDR)
+    }
+
+    pub fn set_hdr(&self, on: bool) -> Result<()> {
+        self.set_bool(TY_FEATURE_ID_LIST::TY_BOOL_HDR, on)
+    }
+
+    /// Device specific `TY_BYTEARRAY_HDR_PARAMETER` blob.
+    pub fn hdr_parameter(&self) -> Result<Vec<u8>> {
+        self.dev.get_byte_array(self.comp, TY_FEATURE_ID_LIST::TY_BYTEARRAY_HDR_PARAMETER)
+    }
+
+    /// Fails with [`ErrorCode::WrongSize`] unless `param` has the size the device reports.
+    pub fn set_hdr_parameter(&self, param: &[u8]) -> Result<()> {
+        let feat = TY_FEATURE_ID_LIST::TY_BYTEARRAY_HDR_PARAMETER;
+        if ty_get_byte_array_size(self.dev, self.comp, feat.feature_id())? != param.len() {
+            return Err(ErrorCode::WrongSize.into());
+        }
+        self.dev.set_byte_array(self.comp, feat, param)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_rect_fits_in() {
+        assert!(Rect::new(0, 0, 640, 480).fits_in(640, 480));
+        assert!(Rect::new(100, 100, 200, 200).fits_in(640, 480));
+        assert!(!Rect::new(500, 0, 200, 200).fits_in(640, 480));
+        assert!(!Rect::new(0, 0, 0, 10).fits_in(640, 480));
+        assert!(!Rect::new(u32::MAX, 0, 2, 2).fits_in(640, 480));
+
+        let roi = TY_AEC_ROI_PARAM::from(Rect::new(1, 2, 3, 4));
+        assert_eq!(Rect::from(roi), Rect::new(1, 2, 3, 4));
+    }
+}
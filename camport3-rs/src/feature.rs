@@ -0,0 +1,329 @@
+use std::borrow::Cow;
+use std::mem::MaybeUninit;
+use camport3_sys::*;
+
+use crate::utils::carr_to_str;
+use crate::ffi::*;
+
+pub(crate) const DEVICE: TY_DEVICE_COMPONENT_LIST = TY_DEVICE_COMPONENT_LIST::TY_COMPONENT_DEVICE;
+pub(crate) const DEPTH: TY_DEVICE_COMPONENT_LIST = TY_DEVICE_COMPONENT_LIST::TY_COMPONENT_DEPTH_CAM;
+
+/// Anything usable as a component ID: the `TY_DEVICE_COMPONENT_LIST` enum or a raw `TY_COMPONENT_ID`.
+pub trait ComponentId: Copy {
+    fn component_id(self) -> TY_COMPONENT_ID;
+}
+
+impl ComponentId for TY_DEVICE_COMPONENT_LIST {
+    fn component_id(self) -> TY_COMPONENT_ID {
+        self as TY_COMPONENT_ID
+    }
+}
+
+impl ComponentId for TY_COMPONENT_ID {
+    fn component_id(self) -> TY_COMPONENT_ID {
+        self
+    }
+}
+
+/// Anything usable as a feature ID: the `TY_FEATURE_ID_LIST` enum or a raw `TY_FEATURE_ID`.
+///
+/// Raw IDs are needed for features reported by `TYGetDeviceFeatureInfo` that the bindings do not know.
+pub trait FeatureId: Copy {
+    fn feature_id(self) -> TY_FEATURE_ID;
+
+    /// Value type encoded in the feature ID, one of `TY_FEATURE_TYPE_LIST`.
+    fn feature_type(self) -> TY_FEATURE_TYPE {
+        self.feature_id() & 0xf000
+    }
+}
+
+impl FeatureId for TY_FEATURE_ID_LIST {
+    fn feature_id(self) -> TY_FEATURE_ID {
+        self as TY_FEATURE_ID
+    }
+}
+
+impl FeatureId for TY_FEATURE_ID {
+    fn feature_id(self) -> TY_FEATURE_ID {
+        self
+    }
+}
+
+/// C structs that can be read and written with `TYGetStruct` / `TYSetStruct`.
+///
+/// # Safety
+/// Implementors must be plain C structs for which every bit pattern, including all zeros, is valid.
+pub unsafe trait FeatureStruct: Copy {}
+
+macro_rules! impl_feature_struct {
+    ($($t:ty),* $(,)?) => {
+        $(unsafe impl FeatureStruct for $t {})*
+    };
+}
+
+impl_feature_struct!(
+    TY_CAMERA_INTRINSIC, TY_CAMERA_EXTRINSIC, TY_CAMERA_DISTORTION, TY_CAMERA_CALIB_INFO,
+    TY_TRIGGER_PARAM, TY_TRIGGER_PARAM_EX, TY_TRIGGER_TIMER_LIST, TY_TRIGGER_TIMER_PERIOD,
+    TY_AEC_ROI_PARAM, TY_PHC_GROUP_ATTR, TY_LASER_PATTERN_PARAM, TY_CAMERA_STATISTICS,
+    TY_IMU_DATA, TY_ACC_BIAS, TY_ACC_MISALIGNMENT, TY_ACC_SCALE, TY_GYRO_BIAS,
+    TY_GYRO_MISALIGNMENT, TY_GYRO_SCALE, TY_CAMERA_TO_IMU, TY_TOF_FREQ, TY_LASER_PARAM,
+    TY_DO_WORKMODE, TY_DI_WORKMODE,
+);
+
+impl FeatureInfo {
+    pub fn is_valid(&self) -> bool {
+        self.0.isValid
+    }
+
+    pub fn is_readable(&self) -> bool {
+        self.0.accessMode & TY_ACCESS_MODE_LIST::TY_ACCESS_READABLE as TY_ACCESS_MODE != 0
+    }
+
+    pub fn is_writable(&self) -> bool {
+        self.0.accessMode & TY_ACCESS_MODE_LIST::TY_ACCESS_WRITABLE as TY_ACCESS_MODE != 0
+    }
+
+    /// Whether the feature can be written while capturing.
+    pub fn writable_at_run(&self) -> bool {
+        self.0.writableAtRun
+    }
+
+    pub fn component_id(&self) -> TY_COMPONENT_ID {
+        self.0.componentID
+    }
+
+    pub fn feature_id(&self) -> TY_FEATURE_ID {
+        self.0.featureID
+    }
+
+    pub fn name(&self) -> Cow<'_, str> {
+        carr_to_str(&self.0.name)
+    }
+
+    /// Component and feature this one is bound to, if any.
+    pub fn bound_to(&self) -> Option<(TY_COMPONENT_ID, TY_FEATURE_ID)> {
+        match (self.0.bindComponentID, self.0.bindFeatureID) {
+            (0, 0) => None,
+            ids => Some(ids),
+        }
+    }
+}
+
+impl IntRange {
+    pub fn min(&self) -> i32 {
+        self.0.min
+    }
+
+    pub fn max(&self) -> i32 {
+        self.0.max
+    }
+
+    pub fn inc(&self) -> i32 {
+        self.0.inc
+    }
+
+    /// Clamp `value` into the range and round it down onto the increment grid.
+    pub fn clamp(&self, value: i32) -> i32 {
+        let (min, max, inc) = (self.min(), self.max(), self.inc());
+        let value = value.clamp(min, max.max(min));
+        if inc > 1 { min + (value - min) / inc * inc } else { value }
+    }
+
+    /// Whether `value` is within the range and on its increment grid.
+    pub fn contains(&self, value: i32) -> bool {
+        let (min, inc) = (self.min(), self.inc());
+        (min..=self.max()).contains(&value) && (inc <= 0 || (i64::from(value) - i64::from(min)) % i64::from(inc) == 0)
+    }
+}
+
+impl FloatRange {
+    pub fn min(&self) -> f32 {
+        self.0.min
+    }
+
+    pub fn max(&self) -> f32 {
+        self.0.max
+    }
+
+    pub fn inc(&self) -> f32 {
+        self.0.inc
+    }
+
+    pub fn clamp(&self, value: f32) -> f32 {
+        value.clamp(self.min(), self.max().max(self.min()))
+    }
+
+    pub fn contains(&self, value: f32) -> bool {
+        (self.min()..=self.max()).contains(&value)
+    }
+}
+
+impl EnumEntry {
+    pub fn description(&self) -> Cow<'_, str> {
+        carr_to_str(&self.0.description)
+    }
+
+    pub fn value(&self) -> u32 {
+        self.0.value
+    }
+}
+
+impl DeviceHandle {
+    pub fn has_feature(&self, comp: impl ComponentId, feat: impl FeatureId) -> Result<bool> {
+        ty_has_feature(self, comp.component_id(), feat.feature_id())
+    }
+
+    pub fn get_feature_info(&self, comp: impl ComponentId, feat: impl FeatureId) -> Result<FeatureInfo> {
+        ty_get_feature_info(self, comp.component_id(), feat.feature_id())
+    }
+
+    /// All features of a component.
+    pub fn get_feature_list(&self, comp: impl ComponentId) -> Result<Vec<FeatureInfo>> {
+        ty_get_device_feature_info(self, comp.component_id())
+    }
+
+    pub fn get_int_range(&self, comp: impl ComponentId, feat: impl FeatureId) -> Result<IntRange> {
+        ty_get_int_range(self, comp.component_id(), feat.feature_id())
+    }
+
+    pub fn get_int(&self, comp: impl ComponentId, feat: impl FeatureId) -> Result<i32> {
+        ty_get_int(self, comp.component_id(), feat.feature_id())
+    }
+
+    pub fn set_int(&self, comp: impl ComponentId, feat: impl FeatureId, value: i32) -> Result<()> {
+        ty_set_int(self, comp.component_id(), feat.feature_id(), value)
+    }
+
+    /// Write `value`, failing with [`ErrorCode::OutOfRange`] outside the feature's range or off its increment grid.
+    pub fn set_int_checked(&self, comp: impl ComponentId, feat: impl FeatureId, value: i32) -> Result<()> {
+        if !self.get_int_range(comp, feat)?.contains(value) {
+            return Err(ErrorCode::OutOfRange.into());
+        }
+        self.set_int(comp, feat, value)
+    }
+
+    /// Clamp `value` with [`IntRange::clamp`] and write it, returning the value written.
+    pub fn set_int_clamped(&self, comp: impl ComponentId, feat: impl FeatureId, value: i32) -> Result<i32> {
+        let value = self.get_int_range(comp, feat)?.clamp(value);
+        self.set_int(comp, feat, value)?;
+        Ok(value)
+    }
+
+    /// `get(self, comp, feat)` if the component has the feature, `None` otherwise.
+    pub(crate) fn read_opt<C: ComponentId, F: FeatureId, T>(
+        &self, comp: C, feat: F, get: impl FnOnce(&Self, C, F) -> Result<T>,
+    ) -> Result<Option<T>> {
+        if self.has_feature(comp, feat)? { get(self, comp, feat).map(Some) } else { Ok(None) }
+    }
+
+    pub fn get_float_range(&self, comp: impl ComponentId, feat: impl FeatureId) -> Result<FloatRange> {
+        ty_get_float_range(self, comp.component_id(), feat.feature_id())
+    }
+
+    pub fn get_float(&self, comp: impl ComponentId, feat: impl FeatureId) -> Result<f32> {
+        ty_get_float(self, comp.component_id(), feat.feature_id())
+    }
+
+    pub fn set_float(&self, comp: impl ComponentId, feat: impl FeatureId, value: f32) -> Result<()> {
+        ty_set_float(self, comp.component_id(), feat.feature_id(), value)
+    }
+
+    pub fn get_enum_entries(&self, comp: impl ComponentId, feat: impl FeatureId) -> Result<Vec<EnumEntry>> {
+        ty_get_enum_entry_info(self, comp.component_id(), feat.feature_id())
+    }
+
+    pub fn get_enum(&self, comp: impl ComponentId, feat: impl FeatureId) -> Result<u32> {
+        ty_get_enum(self, comp.component_id(), feat.feature_id())
+    }
+
+    pub fn set_enum(&self, comp: impl ComponentId, feat: impl FeatureId, value: u32) -> Result<()> {
+        ty_set_enum(self, comp.component_id(), feat.feature_id(), value)
+    }
+
+    pub fn get_bool(&self, comp: impl ComponentId, feat: impl FeatureId) -> Result<bool> {
+        ty_get_bool(self, comp.component_id(), feat.feature_id())
+    }
+
+    pub fn set_bool(&self, comp: impl ComponentId, feat: impl FeatureId, value: bool) -> Result<()> {
+        ty_set_bool(self, comp.component_id(), feat.feature_id(), value)
+    }
+
+    pub fn get_string(&self, comp: impl ComponentId, feat: impl FeatureId) -> Result<String> {
+        ty_get_string(self, comp.component_id(), feat.feature_id())
+    }
+
+    pub fn set_string(&self, comp: impl ComponentId, feat: impl FeatureId, value: &str) -> Result<()> {
+        ty_set_string(self, comp.component_id(), feat.feature_id(), value)
+    }
+
+    pub fn get_struct<T: FeatureStruct>(&self, comp: impl ComponentId, feat: impl FeatureId) -> Result<T> {
+        // SAFETY: all-zero is a valid `FeatureStruct`.
+        let mut out = unsafe { MaybeUninit::<T>::zeroed().assume_init() };
+        self.read_struct(comp, feat, &mut out)?;
+        Ok(out)
+    }
+
+    /// Like [`DeviceHandle::get_struct`], for structs whose fields select what is read (e.g. `TY_LASER_PARAM::idx`).
+    pub fn read_struct<T: FeatureStruct>(&self, comp: impl ComponentId, feat: impl FeatureId, value: &mut T) -> Result<()> {
+        // SAFETY: the SDK checks the size, and any bit pattern it writes is a valid `FeatureStruct`.
+        unsafe { ty_get_struct(self, comp.component_id(), feat.feature_id(), value) }
+    }
+
+    pub fn set_struct<T: FeatureStruct>(&self, comp: impl ComponentId, feat: impl FeatureId, value: &T) -> Result<()> {
+        // SAFETY: the SDK checks the size against the feature's struct.
+        unsafe { ty_set_struct(self, comp.component_id(), feat.feature_id(), value) }
+    }
+
+    pub fn get_byte_array(&self, comp: impl ComponentId, feat: impl FeatureId) -> Result<Vec<u8>> {
+        ty_get_byte_array(self, comp.component_id(), feat.feature_id())
+    }
+
+    pub fn set_byte_array(&self, comp: impl ComponentId, feat: impl FeatureId, value: &[u8]) -> Result<()> {
+        ty_set_byte_array(self, comp.component_id(), feat.feature_id(), value)
+    }
+
+    /// Read `TY_STRUCT_TRIGGER_PARAM` of the device component as `(mode, fps)`.
+    pub fn get_trigger_param(&self) -> Result<(TY_TRIGGER_MODE, i8)> {
+        let param: TY_TRIGGER_PARAM = self.get_struct(
+            TY_DEVICE_COMPONENT_LIST::TY_COMPONENT_DEVICE,
+            TY_FEATURE_ID_LIST::TY_STRUCT_TRIGGER_PARAM,
+        )?;
+        Ok((param.mode, param.fps))
+    }
+
+    /// Write `TY_STRUCT_TRIGGER_PARAM`; `fps` only matters for `TY_TRIGGER_MODE_M_PER`.
+    pub fn set_trigger_param(&self, mode: TY_TRIGGER_MODE_LIST, fps: i8) -> Result<()> {
+        let param = TY_TRIGGER_PARAM { mode: mode as TY_TRIGGER_MODE, fps, rsvd: 0 };
+        self.set_struct(
+            TY_DEVICE_COMPONENT_LIST::TY_COMPONENT_DEVICE,
+            TY_FEATURE_ID_LIST::TY_STRUCT_TRIGGER_PARAM,
+            &param,
+        )
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use bytemuck::TransparentWrapper;
+    use super::*;
+
+    #[test]
+    fn test_ids() {
+        assert_eq!(TY_DEVICE_COMPONENT_LIST::TY_COMPONENT_DEPTH_CAM.component_id(), 0x00010000);
+        assert_eq!(TY_FEATURE_ID_LIST::TY_INT_EXPOSURE_TIME.feature_type(), TY_FEATURE_TYPE_LIST::TY_FEATURE_INT as u32);
+        assert_eq!(TY_FEATURE_ID_LIST::TY_STRUCT_TRIGGER_PARAM.feature_type(), TY_FEATURE_TYPE_LIST::TY_FEATURE_STRUCT as u32);
+        assert_eq!(0x4000u32.feature_type(), TY_FEATURE_TYPE_LIST::TY_FEATURE_BOOL as u32);
+    }
+
+    #[test]
+    fn test_int_range_clamp() {
+        let range = IntRange::wrap(TY_INT_RANGE { min: 10, max: 100, inc: 4, reserved: [0] });
+        assert_eq!(range.clamp(0), 10);
+        assert_eq!(range.clamp(200), 98);
+        assert_eq!(range.clamp(17), 14);
+        assert!(range.contains(98) && !range.contains(9));
+        // Off the increment grid.
+        assert!(!range.contains(100) && !range.contains(15));
+        assert!(range.contains(range.clamp(15)));
+    }
+}
@@ -1,12 +1,17 @@
-use serde::de::value::Error;
 use thiserror::Error;
+use bitflags::bitflags;
 use bytemuck::TransparentWrapper;
 use strum_macros::FromRepr;
 use serde::Serialize;
+use std::borrow::Cow;
 use std::fmt::Display;
-use std::{ffi::CString, marker::PhantomData, mem::transmute, ptr};
+use std::{ffi::{c_void, CString}, mem::{transmute, MaybeUninit}, ptr};
+use std::ops::Deref;
+use std::sync::{Arc, Mutex, MutexGuard, PoisonError};
+use std::time::{Duration, Instant};
 use camport3_sys::*;
-use crate::utils::cstr_to_str;
+use crate::event::{event_callback, EventDispatcher};
+use crate::utils::{carr_to_str, cstr_to_str};
 
 #[derive(Error, Serialize, Debug, Clone, Copy, PartialEq, Eq, FromRepr)]
 #[repr(i32)] // TY_STATUS
@@ -69,27 +74,115 @@ pub enum ErrorCode {
     DevEbusy = -16,
     #[error("dev error invalid")]
     DevEinval = -22,
+    #[error("device offline")]
+    DeviceOffline = -2001,
+    #[error("unknown status {0}")]
+    Unknown(i32) = i32::MIN,
+    #[error("incompatible library version {0}.{1}")]
+    IncompatibleVersion(u32, u32) = i32::MIN + 1,
+    #[error("SDK library could not be loaded")]
+    LibraryNotLoaded = i32::MIN + 2,
+}
+
+impl From<i32> for ErrorCode {
+    fn from(status: i32) -> Self {
+        match Self::from_repr(status) {
+            Some(Self::Unknown(_) | Self::IncompatibleVersion(..) | Self::LibraryNotLoaded) | None => Self::Unknown(status),
+            Some(code) => code,
+        }
+    }
+}
+
+bitflags! {
+    /// Decoded `TY_FW_ERRORCODE` bitmask reported by the firmware when a device is opened.
+    #[derive(Serialize, Debug, Clone, Copy, PartialEq, Eq, Hash, Default)]
+    pub struct FirmwareFaults: TY_FW_ERRORCODE {
+        const CAM0_NOT_DETECTED = TY_FW_ERRORCODE_CAM0_NOT_DETECTED;
+        const CAM1_NOT_DETECTED = TY_FW_ERRORCODE_CAM1_NOT_DETECTED;
+        const CAM2_NOT_DETECTED = TY_FW_ERRORCODE_CAM2_NOT_DETECTED;
+        const POE_NOT_INIT = TY_FW_ERRORCODE_POE_NOT_INIT;
+        const RECMAP_NOT_CORRECT = TY_FW_ERRORCODE_RECMAP_NOT_CORRECT;
+        const LOOKUPTABLE_NOT_CORRECT = TY_FW_ERRORCODE_LOOKUPTABLE_NOT_CORRECT;
+        const DRV8899_NOT_INIT = TY_FW_ERRORCODE_DRV8899_NOT_INIT;
+        const FOC_START_ERR = TY_FW_ERRORCODE_FOC_START_ERR;
+        const CONFIG_NOT_FOUND = TY_FW_ERRORCODE_CONFIG_NOT_FOUND;
+        const CONFIG_NOT_CORRECT = TY_FW_ERRORCODE_CONFIG_NOT_CORRECT;
+        const XML_NOT_FOUND = TY_FW_ERRORCODE_XML_NOT_FOUND;
+        const XML_NOT_CORRECT = TY_FW_ERRORCODE_XML_NOT_CORRECT;
+        const XML_OVERRIDE_FAILED = TY_FW_ERRORCODE_XML_OVERRIDE_FAILED;
+        const CAM_INIT_FAILED = TY_FW_ERRORCODE_CAM_INIT_FAILED;
+        const LASER_INIT_FAILED = TY_FW_ERRORCODE_LASER_INIT_FAILED;
+    }
+}
+
+impl FirmwareFaults {
+    pub fn description(&self) -> Option<&'static str> {
+        let desc = match *self {
+            Self::CAM0_NOT_DETECTED => "camera 0 not detected",
+            Self::CAM1_NOT_DETECTED => "camera 1 not detected",
+            Self::CAM2_NOT_DETECTED => "camera 2 not detected",
+            Self::POE_NOT_INIT => "POE not initialized",
+            Self::RECMAP_NOT_CORRECT => "rectification map not correct",
+            Self::LOOKUPTABLE_NOT_CORRECT => "lookup table not correct",
+            Self::DRV8899_NOT_INIT => "DRV8899 not initialized",
+            Self::FOC_START_ERR => "FOC start error",
+            Self::CONFIG_NOT_FOUND => "config not found",
+            Self::CONFIG_NOT_CORRECT => "config not correct",
+            Self::XML_NOT_FOUND => "XML not found",
+            Self::XML_NOT_CORRECT => "XML not correct",
+            Self::XML_OVERRIDE_FAILED => "XML override failed",
+            Self::CAM_INIT_FAILED => "camera init failed",
+            Self::LASER_INIT_FAILED => "laser init failed",
+            _ => return None,
+        };
+        Some(desc)
+    }
+}
+
+impl Display for FirmwareFaults {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        if self.is_empty() {
+            return write!(f, "none");
+        }
+        let mut names: Vec<String> = self.iter()
+            .filter_map(|flag| flag.description())
+            .map(str::to_owned)
+            .collect();
+        let unknown = self.bits() & !Self::all().bits();
+        if unknown != 0 {
+            names.push(format!("unknown faults {unknown:#x}"));
+        }
+        write!(f, "{}", names.join(", "))
+    }
 }
 
 #[derive(Error, Serialize, Debug, Clone, Copy, PartialEq, Eq)]
 pub struct DeviceError {
     pub errcode: ErrorCode,
-    pub firmware_errcode: Option<u32>,
+    pub firmware_errcode: Option<FirmwareFaults>,
 }
 
 impl Display for DeviceError {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
-        write!(f, "{}", self.errcode)
+        match self.firmware_errcode {
+            Some(faults) => write!(f, "{}: {}", self.errcode, faults),
+            None => write!(f, "{}", self.errcode),
+        }
     }
 }
 
-impl From<i32> for DeviceError {
-    fn from(value: i32) -> Self {
+impl From<ErrorCode> for DeviceError {
+    fn from(errcode: ErrorCode) -> Self {
         DeviceError {
-            errcode: ErrorCode::from_repr(value).unwrap(),
+            errcode,
             firmware_errcode: None,
         }
+    }
+}
 
+impl From<i32> for DeviceError {
+    fn from(value: i32) -> Self {
+        ErrorCode::from(value).into()
     }
 }
 
@@ -103,6 +196,10 @@ fn chkerr(status: TY_STATUS) -> Result<()> {
     }
 }
 
+fn to_cstring(s: &str) -> Result<CString> {
+    CString::new(s).map_err(|_| ErrorCode::InvalidParameter.into())
+}
+
 #[derive(Debug, Clone, Copy, TransparentWrapper)]
 #[repr(transparent)]
 pub struct Wrapper<T>(pub T);
@@ -112,76 +209,238 @@ pub type InterfaceInfo = Wrapper<TY_INTERFACE_INFO>;
 pub type NetInfo = Wrapper<TY_DEVICE_NET_INFO>;
 pub type UsbInfo = Wrapper<TY_DEVICE_USB_INFO>;
 pub type DeviceBaseInfo = Wrapper<TY_DEVICE_BASE_INFO>;
+pub type FeatureInfo = Wrapper<TY_FEATURE_INFO>;
+pub type IntRange = Wrapper<TY_INT_RANGE>;
+pub type FloatRange = Wrapper<TY_FLOAT_RANGE>;
+pub type EnumEntry = Wrapper<TY_ENUM_ENTRY>;
+
+/// Number of live contexts, guarding `_TYInitLib`/`TYDeinitLib`.
+static LIB_USERS: Mutex<usize> = Mutex::new(0);
 
+/// One user of the library, counted in [`LIB_USERS`].
 #[derive(Debug)]
-pub struct Context(pub(crate) PhantomData<()>);
+struct ContextInner(());
+
+impl ContextInner {
+    fn acquire() -> Result<Self> {
+        let mut users = LIB_USERS.lock().unwrap_or_else(PoisonError::into_inner);
+        if *users == 0 {
+            #[cfg(feature = "dlopen")]
+            camport3_sys::load().map_err(|e| {
+                log::error!("{e}");
+                ErrorCode::LibraryNotLoaded
+            })?;
+            check_lib_version()?;
+            ty_init_lib()?;
+        }
+        *users += 1;
+        Ok(Self(()))
+    }
+}
 
-impl Drop for Context {
+impl Drop for ContextInner {
     fn drop(&mut self) {
-        ty_deinit_lib().unwrap()
+        let mut users = LIB_USERS.lock().unwrap_or_else(PoisonError::into_inner);
+        *users -= 1;
+        if *users == 0 {
+            if let Err(e) = ty_deinit_lib() {
+                log::warn!("TYDeinitLib failed: {e}");
+            }
+        }
+    }
+}
+
+/// Accept the runtime like `TYInitLib` in TYApi.h: same major version, at least the minor version of the headers.
+fn check_lib_version() -> Result<VersionInfo> {
+    let ver = ty_lib_version()?;
+    if ver.major() != TY_LIB_VERSION_MAJOR || ver.minor() < TY_LIB_VERSION_MINOR {
+        return Err(ErrorCode::IncompatibleVersion(ver.major(), ver.minor()).into());
     }
+    Ok(ver)
+}
+
+/// Library context, cheap to clone.
+///
+/// The library is initialised by the first context in the process and deinitialised when the
+/// last one is dropped. Every interface and device keeps a clone, so `TYDeinitLib` only runs
+/// after the last of them is closed.
+#[derive(Debug, Clone)]
+pub struct Context {
+    _lib: Arc<ContextInner>,
 }
 
 impl Context {
-    pub fn new() -> Self {
-        ty_init_lib().unwrap();
-        Self(PhantomData)
+    pub fn new() -> Result<Self> {
+        Ok(Self { _lib: Arc::new(ContextInner::acquire()?) })
     }
 
-    pub fn error_string(&self, status: i32) -> &str {
+    pub fn error_string(&self, status: i32) -> Cow<'static, str> {
         ty_error_string(status)
     }
 
-    pub fn version(&self) -> VersionInfo {
-        ty_lib_version().unwrap()
+    pub fn version(&self) -> Result<VersionInfo> {
+        ty_lib_version()
     }
 
-    pub fn update_interface_list(&self) {
-        ty_update_interface_list().unwrap()
+    pub fn update_interface_list(&self) -> Result<()> {
+        ty_update_interface_list()
     }
 
-    pub fn get_interface_number(&self) -> usize {
-        ty_get_interface_number().unwrap()
+    /// Refresh the device lists of all interfaces at once.
+    pub fn update_all_device_list(&self) -> Result<()> {
+        ty_update_all_device_list()
     }
 
-    pub fn get_interface_list(&self, n: usize) -> Vec<InterfaceInfo> {
-        ty_get_interface_list(n).unwrap()
+    pub fn get_interface_number(&self) -> Result<usize> {
+        ty_get_interface_number()
+    }
+
+    pub fn get_interface_list(&self, n: usize) -> Result<Vec<InterfaceInfo>> {
+        ty_get_interface_list(n)
     }
 
     pub fn open_interface(&self, id: &str) -> Result<InterfaceHandle> {
         ty_open_interface(self, id)
     }
 
-    pub fn has_interface(&self, id: &str) -> bool {
-        ty_has_interface(id).unwrap()
+    pub fn has_interface(&self, id: &str) -> Result<bool> {
+        ty_has_interface(id)
+    }
+}
+
+/// An SDK handle, locked for the duration of one call.
+///
+/// TYApi.h documents no thread safety, so calls on the same handle are serialized here. The guard is a temporary
+/// of the statement making the call, e.g. `TYStartCapture(*h.raw())`, and must not be kept across calls.
+pub(crate) struct Locked<'a, H> {
+    raw: H,
+    _guard: MutexGuard<'a, ()>,
+}
+
+impl<'a, H> Locked<'a, H> {
+    fn new(raw: H, lock: &'a Mutex<()>) -> Self {
+        Locked { raw, _guard: lock.lock().unwrap_or_else(PoisonError::into_inner) }
+    }
+}
+
+impl<H> Deref for Locked<'_, H> {
+    type Target = H;
+
+    fn deref(&self) -> &H {
+        &self.raw
     }
 }
 
 #[derive(Debug)]
-pub struct InterfaceHandle<'ctx> {
+pub(crate) struct InterfaceInner {
     handle: TY_INTERFACE_HANDLE,
-    pub ctx: &'ctx Context,
+    id: String,
+    ctx: Context,
+    lock: Mutex<()>,
 }
 
-impl Drop for InterfaceHandle<'_> {
+// SAFETY: the interface handle is an opaque token the SDK does not bind to the opening thread,
+// and every call through it holds `lock` (see `Locked`).
+unsafe impl Send for InterfaceInner {}
+unsafe impl Sync for InterfaceInner {}
+
+impl Drop for InterfaceInner {
     fn drop(&mut self) {
-        ty_close_interface(self)
+        if self.handle.is_null() {
+            return;
+        }
+        if let Err(e) = ty_close_interface(self.handle) {
+            log::warn!("TYCloseInterface failed: {e}");
+        }
+    }
+}
+
+/// Open interface, cheap to clone.
+///
+/// Devices keep a clone, so the interface is closed after the last device opened on it.
+#[derive(Debug, Clone)]
+pub struct InterfaceHandle(pub(crate) Arc<InterfaceInner>);
+
+impl InterfaceHandle {
+    pub(crate) fn raw(&self) -> Locked<'_, TY_INTERFACE_HANDLE> {
+        Locked::new(self.0.handle, &self.0.lock)
+    }
+
+    /// ID the interface was opened with.
+    pub fn id(&self) -> &str {
+        &self.0.id
+    }
+
+    pub fn context(&self) -> &Context {
+        &self.0.ctx
+    }
+
+    /// Close the interface explicitly, reporting the failure instead of logging it on drop.
+    ///
+    /// Fails with [`ErrorCode::Busy`] while other clones or open devices still use the interface.
+    pub fn close(self) -> Result<()> {
+        let mut inner = Arc::try_unwrap(self.0).map_err(|_| ErrorCode::Busy)?;
+        let ret = ty_close_interface(inner.handle);
+        inner.handle = ptr::null_mut();
+        ret
     }
 }
 
 #[derive(Debug)]
-pub struct DeviceHandle<'iface, 'ctx> {
+pub struct DeviceHandle {
     handle: TY_DEV_HANDLE,
-    pub iface: &'iface InterfaceHandle<'ctx>,
+    iface: InterfaceHandle,
+    events: Box<EventDispatcher>,
+    lock: Mutex<()>,
 }
 
-impl Drop for DeviceHandle<'_, '_> {
+// SAFETY: as for `InterfaceInner`, nothing ties the handle to the opening thread and every call
+// through it holds `lock`.
+unsafe impl Send for DeviceHandle {}
+unsafe impl Sync for DeviceHandle {}
+
+impl Drop for DeviceHandle {
     fn drop(&mut self) {
-        ty_close_device(self, false)
+        if self.handle.is_null() {
+            return;
+        }
+        if let Err(e) = ty_close_device(self, false) {
+            log::warn!("TYCloseDevice failed: {e}");
+        }
     }
 }
 
-pub(crate) fn ty_error_string(status: TY_STATUS) -> &'static str {
+impl DeviceHandle {
+    fn new(iface: &InterfaceHandle) -> Self {
+        DeviceHandle {
+            handle: ptr::null_mut(),
+            iface: iface.clone(),
+            events: Box::default(),
+            lock: Mutex::new(()),
+        }
+    }
+
+    pub(crate) fn raw(&self) -> Locked<'_, TY_DEV_HANDLE> {
+        Locked::new(self.handle, &self.lock)
+    }
+
+    pub(crate) fn events(&self) -> &EventDispatcher {
+        &self.events
+    }
+
+    pub fn interface(&self) -> &InterfaceHandle {
+        &self.iface
+    }
+
+    /// Close the device explicitly, optionally rebooting it, and report the failure.
+    pub fn close(mut self, reboot: bool) -> Result<()> {
+        let ret = ty_close_device(&self, reboot);
+        self.handle = ptr::null_mut();
+        ret
+    }
+}
+
+pub(crate) fn ty_error_string(status: TY_STATUS) -> Cow<'static, str> {
     cstr_to_str(unsafe{TYErrorString(status)})
 }
 
@@ -194,7 +453,7 @@ pub(crate) fn ty_deinit_lib() -> Result<()> {
 }
 
 pub(crate) fn ty_lib_version() -> Result<VersionInfo> {
-    let mut out = std::mem::MaybeUninit::uninit();
+    let mut out = MaybeUninit::uninit();
     let out = unsafe {
         chkerr(TYLibVersion(out.as_mut_ptr()))?;
         out.assume_init()
@@ -206,6 +465,10 @@ pub(crate) fn ty_update_interface_list() -> Result<()> {
     chkerr(unsafe {TYUpdateInterfaceList() })
 }
 
+pub(crate) fn ty_update_all_device_list() -> Result<()> {
+    chkerr(unsafe { TYUpdateAllDeviceList() })
+}
+
 pub(crate) fn ty_get_interface_number() -> Result<usize> {
     let mut n: u32 = 0;
     chkerr(unsafe{TYGetInterfaceNumber(&mut n)})?;
@@ -214,7 +477,7 @@ pub(crate) fn ty_get_interface_number() -> Result<usize> {
 
 pub(crate) fn ty_get_interface_list(n: usize) -> Result<Vec<InterfaceInfo>> {
     let n = if n == 0 {
-        ty_get_interface_number().unwrap()
+        ty_get_interface_number()?
     } else {
         n
     };
@@ -227,55 +490,56 @@ pub(crate) fn ty_get_interface_list(n: usize) -> Result<Vec<InterfaceInfo>> {
     unsafe {
         chkerr(TYGetInterfaceList(out.as_mut_ptr(), n as u32, &mut filled_n))?;
         out.set_len(filled_n as usize);
-        Ok(transmute(out))
+        Ok(transmute::<Vec<TY_INTERFACE_INFO>, Vec<InterfaceInfo>>(out))
     }
 }
 
 pub(crate) fn ty_has_interface(id: &str) -> Result<bool> {
     let mut out = false;
-    let id = CString::new(id).unwrap();
+    let id = to_cstring(id)?;
     let id = id.as_ptr();
     chkerr(unsafe{TYHasInterface(id, &mut out)})?;
     Ok(out)
 }
 
-pub(crate) fn ty_open_interface<'ctx>(ctx: &'ctx Context, id: &str) -> Result<InterfaceHandle<'ctx>> {
+pub(crate) fn ty_open_interface(ctx: &Context, id: &str) -> Result<InterfaceHandle> {
     let mut out = ptr::null_mut();
-    let id = CString::new(id).unwrap();
-    let id = id.as_ptr();
+    let c_id = to_cstring(id)?;
     chkerr(unsafe{
-        TYOpenInterface(id, &mut out)
+        TYOpenInterface(c_id.as_ptr(), &mut out)
     })?;
     if out.is_null() {
-        panic!("handle cannot be NULL!");
+        return Err(ErrorCode::InvalidHandle.into())
     }
-    Ok(InterfaceHandle{
+    Ok(InterfaceHandle(Arc::new(InterfaceInner{
         handle: out,
-        ctx,
-    })
+        id: id.to_owned(),
+        ctx: ctx.clone(),
+        lock: Mutex::new(()),
+    })))
 }
 
-pub(crate) fn ty_close_interface(h: &InterfaceHandle) {
+pub(crate) fn ty_close_interface(h: TY_INTERFACE_HANDLE) -> Result<()> {
     chkerr(unsafe{
-        TYCloseInterface(h.handle)
-    }).unwrap()
+        TYCloseInterface(h)
+    })
 }
 
 pub(crate) fn ty_update_device_list(h: &InterfaceHandle) -> Result<()> {
     chkerr(unsafe{
-        TYUpdateDeviceList(h.handle)
+        TYUpdateDeviceList(*h.raw())
     })
 }
 
 pub(crate) fn ty_get_device_number(h: &InterfaceHandle) -> Result<usize> {
     let mut n: u32 = 0;
-    chkerr(unsafe{TYGetDeviceNumber(h.handle, &mut n)})?;
+    chkerr(unsafe{TYGetDeviceNumber(*h.raw(), &mut n)})?;
     Ok(n as usize)
 }
 
 pub(crate) fn ty_get_device_list(h: &InterfaceHandle, mut n: usize) -> Result<Vec<DeviceBaseInfo>> {
     if n == 0 {
-        n = ty_get_device_number(h).unwrap();
+        n = ty_get_device_number(h)?;
     }
     if n == 0 {
         return Ok(Vec::new())
@@ -284,73 +548,366 @@ pub(crate) fn ty_get_device_list(h: &InterfaceHandle, mut n: usize) -> Result<Ve
     let mut out = Vec::<TY_DEVICE_BASE_INFO>::with_capacity(n);
     let mut filled_n = 0;
     unsafe {
-        chkerr(TYGetDeviceList(h.handle, out.as_mut_ptr(), n as u32, &mut filled_n))?;
+        chkerr(TYGetDeviceList(*h.raw(), out.as_mut_ptr(), n as u32, &mut filled_n))?;
         out.set_len(filled_n as usize);
-        Ok(transmute(out))
+        Ok(transmute::<Vec<TY_DEVICE_BASE_INFO>, Vec<DeviceBaseInfo>>(out))
     }
 }
 
 pub(crate) fn ty_has_device(h: &InterfaceHandle, id: &str) -> Result<bool> {
     let mut out = false;
-    let id = CString::new(id).unwrap();
+    let id = to_cstring(id)?;
     let id = id.as_ptr();
-    chkerr(unsafe{TYHasDevice(h.handle, id, &mut out)})?;
+    chkerr(unsafe{TYHasDevice(*h.raw(), id, &mut out)})?;
     Ok(out)
 }
 
-pub(crate) fn ty_close_device(h: &DeviceHandle, reboot: bool) {
+pub(crate) fn ty_close_device(h: &DeviceHandle, reboot: bool) -> Result<()> {
     chkerr(unsafe{
-        TYCloseDevice(h.handle, reboot)
-    }).unwrap()
+        TYCloseDevice(*h.raw(), reboot)
+    })
 }
 
-pub(crate) fn ty_open_device<'iface, 'ctx>(h: &'iface InterfaceHandle<'ctx>, id: &str) -> Result<DeviceHandle<'iface, 'ctx>> {
-    let mut out = DeviceHandle{
-        handle: ptr::null_mut(),
-        iface: h,
-    };
+pub(crate) fn ty_open_device(h: &InterfaceHandle, id: &str) -> Result<DeviceHandle> {
+    let (out, faults) = ty_open_device_with_faults(h, id)?;
+    if !faults.is_empty() {
+        Err(DeviceError {
+            errcode: ErrorCode::DeviceError,
+            firmware_errcode: Some(faults),
+        })
+    } else {
+        Ok(out)
+    }
+}
+
+pub(crate) fn ty_open_device_with_faults(h: &InterfaceHandle, id: &str) -> Result<(DeviceHandle, FirmwareFaults)> {
+    let mut out = DeviceHandle::new(h);
     let mut err_code: TY_FW_ERRORCODE = 0;
-    let id = CString::new(id).unwrap();
+    let id = to_cstring(id)?;
     let id = id.as_ptr();
     chkerr(unsafe{
-        TYOpenDevice(h.handle, id, &mut out.handle, &mut err_code)
+        TYOpenDevice(*h.raw(), id, &mut out.handle, &mut err_code)
     })?;
     if out.handle.is_null() {
         return Err(DeviceError { errcode: ErrorCode::DevEinval, firmware_errcode: None })
     }
+    ty_register_event_callback(&out, Some(event_callback), out.events() as *const _ as *mut c_void)?;
 
-    if err_code != 0 {
-        return Err(DeviceError {
-            errcode: ErrorCode::DeviceError,
-            firmware_errcode: Some(err_code),
-        })
-    } else {
-        Ok(out)
-    }
-
+    Ok((out, FirmwareFaults::from_bits_retain(err_code)))
 }
 
-pub(crate) fn ty_open_device_with_ip<'iface, 'ctx>(h: &'iface InterfaceHandle<'ctx>, ip: &str) -> Result<DeviceHandle<'iface, 'ctx>> {
-    let mut out = DeviceHandle{
-        handle: ptr::null_mut(),
-        iface: h,
-    };
-    let ip = CString::new(ip).unwrap();
+pub(crate) fn ty_open_device_with_ip(h: &InterfaceHandle, ip: &str) -> Result<DeviceHandle> {
+    let mut out = DeviceHandle::new(h);
+    let ip = to_cstring(ip)?;
     let ip: *const i8 = ip.as_ptr();
     chkerr(unsafe{
-        TYOpenDeviceWithIP(h.handle, ip, &mut out.handle)
+        TYOpenDeviceWithIP(*h.raw(), ip, &mut out.handle)
     })?;
     if out.handle.is_null() {
         return Err(DeviceError { errcode: ErrorCode::DevEinval, firmware_errcode: None })
     }
+    ty_register_event_callback(&out, Some(event_callback), out.events() as *const _ as *mut c_void)?;
     Ok(out)
 }
 
 // TYGetDeviceInterface, already implemented struct DeviceHandle
 
+pub(crate) fn ty_get_device_info(h: &DeviceHandle) -> Result<DeviceBaseInfo> {
+    let mut out = MaybeUninit::uninit();
+    let out = unsafe {
+        chkerr(TYGetDeviceInfo(*h.raw(), out.as_mut_ptr()))?;
+        out.assume_init()
+    };
+    Ok(TransparentWrapper::wrap(out))
+}
+
+pub(crate) fn ty_get_component_ids(h: &DeviceHandle) -> Result<TY_COMPONENT_ID> {
+    let mut out = 0;
+    chkerr(unsafe{TYGetComponentIDs(*h.raw(), &mut out)})?;
+    Ok(out)
+}
+
+pub(crate) fn ty_get_enabled_components(h: &DeviceHandle) -> Result<TY_COMPONENT_ID> {
+    let mut out = 0;
+    chkerr(unsafe{TYGetEnabledComponents(*h.raw(), &mut out)})?;
+    Ok(out)
+}
+
+pub(crate) fn ty_enable_components(h: &DeviceHandle, ids: TY_COMPONENT_ID) -> Result<()> {
+    chkerr(unsafe{TYEnableComponents(*h.raw(), ids)})
+}
+
+pub(crate) fn ty_disable_components(h: &DeviceHandle, ids: TY_COMPONENT_ID) -> Result<()> {
+    chkerr(unsafe{TYDisableComponents(*h.raw(), ids)})
+}
+
+pub(crate) fn ty_get_frame_buffer_size(h: &DeviceHandle) -> Result<usize> {
+    let mut out: u32 = 0;
+    chkerr(unsafe{TYGetFrameBufferSize(*h.raw(), &mut out)})?;
+    Ok(out as usize)
+}
+
+/// # Safety
+/// `buffer` must stay valid for `size` bytes until it is fetched back or the queue is cleared.
+pub(crate) unsafe fn ty_enqueue_buffer(h: &DeviceHandle, buffer: *mut c_void, size: usize) -> Result<()> {
+    chkerr(unsafe{TYEnqueueBuffer(*h.raw(), buffer, size as u32)})
+}
+
+pub(crate) fn ty_clear_buffer_queue(h: &DeviceHandle) -> Result<()> {
+    chkerr(unsafe{TYClearBufferQueue(*h.raw())})
+}
+
+pub(crate) fn ty_start_capture(h: &DeviceHandle) -> Result<()> {
+    chkerr(unsafe{TYStartCapture(*h.raw())})
+}
+
+pub(crate) fn ty_stop_capture(h: &DeviceHandle) -> Result<()> {
+    chkerr(unsafe{TYStopCapture(*h.raw())})
+}
+
+pub(crate) fn ty_send_soft_trigger(h: &DeviceHandle) -> Result<()> {
+    chkerr(unsafe{TYSendSoftTrigger(*h.raw())})
+}
+
+pub(crate) fn ty_register_event_callback(h: &DeviceHandle, callback: TY_EVENT_CALLBACK, userdata: *mut c_void) -> Result<()> {
+    chkerr(unsafe{TYRegisterEventCallback(*h.raw(), callback, userdata)})
+}
+
+/// Longest wait of a single `TYFetchFrame` call, which holds the device lock while waiting.
+const FETCH_SLICE_MS: i32 = 20;
+
+/// Waits in slices of [`FETCH_SLICE_MS`], so other calls on the device, e.g. enqueueing buffers, are not held up by a
+/// long fetch. Fails with [`ErrorCode::DeviceOffline`] once the device went offline, also while waiting.
+pub(crate) fn ty_fetch_frame(h: &DeviceHandle, timeout_ms: i32) -> Result<TY_FRAME_DATA> {
+    fetch_in_slices(timeout_ms, || h.is_offline(), |slice| {
+        let mut out = MaybeUninit::uninit();
+        chkerr(unsafe { TYFetchFrame(*h.raw(), out.as_mut_ptr(), slice) })?;
+        Ok(unsafe { out.assume_init() })
+    })
+}
+
+/// Call `fetch` with timeouts of at most [`FETCH_SLICE_MS`] until it returns anything but [`ErrorCode::TIMEOUT`]
+/// or `timeout_ms` (-1 waits forever) passed, checking `offline` before every call.
+fn fetch_in_slices<T>(timeout_ms: i32, offline: impl Fn() -> bool, mut fetch: impl FnMut(i32) -> Result<T>) -> Result<T> {
+    let deadline = u64::try_from(timeout_ms).ok().map(|ms| Instant::now() + Duration::from_millis(ms));
+    loop {
+        if offline() {
+            return Err(ErrorCode::DeviceOffline.into());
+        }
+        let slice = match deadline {
+            Some(d) => d.saturating_duration_since(Instant::now()).as_millis().min(FETCH_SLICE_MS as u128) as i32,
+            None => FETCH_SLICE_MS,
+        };
+        match fetch(slice) {
+            Err(e) if e.errcode == ErrorCode::TIMEOUT && deadline.is_none_or(|d| Instant::now() < d) => {}
+            ret => return ret,
+        }
+    }
+}
+
+pub(crate) fn ty_has_feature(h: &DeviceHandle, comp: TY_COMPONENT_ID, feat: TY_FEATURE_ID) -> Result<bool> {
+    let mut out = false;
+    chkerr(unsafe{TYHasFeature(*h.raw(), comp, feat, &mut out)})?;
+    Ok(out)
+}
+
+pub(crate) fn ty_get_feature_info(h: &DeviceHandle, comp: TY_COMPONENT_ID, feat: TY_FEATURE_ID) -> Result<FeatureInfo> {
+    let mut out = MaybeUninit::uninit();
+    let out = unsafe {
+        chkerr(TYGetFeatureInfo(*h.raw(), comp, feat, out.as_mut_ptr()))?;
+        out.assume_init()
+    };
+    Ok(TransparentWrapper::wrap(out))
+}
+
+pub(crate) fn ty_get_int_range(h: &DeviceHandle, comp: TY_COMPONENT_ID, feat: TY_FEATURE_ID) -> Result<IntRange> {
+    let mut out = MaybeUninit::uninit();
+    let out = unsafe {
+        chkerr(TYGetIntRange(*h.raw(), comp, feat, out.as_mut_ptr()))?;
+        out.assume_init()
+    };
+    Ok(TransparentWrapper::wrap(out))
+}
+
+pub(crate) fn ty_get_int(h: &DeviceHandle, comp: TY_COMPONENT_ID, feat: TY_FEATURE_ID) -> Result<i32> {
+    let mut out = 0;
+    chkerr(unsafe{TYGetInt(*h.raw(), comp, feat, &mut out)})?;
+    Ok(out)
+}
+
+pub(crate) fn ty_set_int(h: &DeviceHandle, comp: TY_COMPONENT_ID, feat: TY_FEATURE_ID, value: i32) -> Result<()> {
+    chkerr(unsafe{TYSetInt(*h.raw(), comp, feat, value)})
+}
+
+pub(crate) fn ty_get_float_range(h: &DeviceHandle, comp: TY_COMPONENT_ID, feat: TY_FEATURE_ID) -> Result<FloatRange> {
+    let mut out = MaybeUninit::uninit();
+    let out = unsafe {
+        chkerr(TYGetFloatRange(*h.raw(), comp, feat, out.as_mut_ptr()))?;
+        out.assume_init()
+    };
+    Ok(TransparentWrapper::wrap(out))
+}
+
+pub(crate) fn ty_get_float(h: &DeviceHandle, comp: TY_COMPONENT_ID, feat: TY_FEATURE_ID) -> Result<f32> {
+    let mut out = 0.0;
+    chkerr(unsafe{TYGetFloat(*h.raw(), comp, feat, &mut out)})?;
+    Ok(out)
+}
+
+pub(crate) fn ty_set_float(h: &DeviceHandle, comp: TY_COMPONENT_ID, feat: TY_FEATURE_ID, value: f32) -> Result<()> {
+    chkerr(unsafe{TYSetFloat(*h.raw(), comp, feat, value)})
+}
+
+pub(crate) fn ty_get_enum_entry_count(h: &DeviceHandle, comp: TY_COMPONENT_ID, feat: TY_FEATURE_ID) -> Result<usize> {
+    let mut out: u32 = 0;
+    chkerr(unsafe{TYGetEnumEntryCount(*h.raw(), comp, feat, &mut out)})?;
+    Ok(out as usize)
+}
+
+pub(crate) fn ty_get_enum_entry_info(h: &DeviceHandle, comp: TY_COMPONENT_ID, feat: TY_FEATURE_ID) -> Result<Vec<EnumEntry>> {
+    let n = ty_get_enum_entry_count(h, comp, feat)?;
+    if n == 0 {
+        return Ok(Vec::new())
+    }
+
+    let mut out = Vec::<TY_ENUM_ENTRY>::with_capacity(n);
+    let mut filled_n = 0;
+    unsafe {
+        chkerr(TYGetEnumEntryInfo(*h.raw(), comp, feat, out.as_mut_ptr(), n as u32, &mut filled_n))?;
+        out.set_len((filled_n as usize).min(n));
+        Ok(transmute::<Vec<TY_ENUM_ENTRY>, Vec<EnumEntry>>(out))
+    }
+}
+
+pub(crate) fn ty_get_enum(h: &DeviceHandle, comp: TY_COMPONENT_ID, feat: TY_FEATURE_ID) -> Result<u32> {
+    let mut out = 0;
+    chkerr(unsafe{TYGetEnum(*h.raw(), comp, feat, &mut out)})?;
+    Ok(out)
+}
+
+pub(crate) fn ty_set_enum(h: &DeviceHandle, comp: TY_COMPONENT_ID, feat: TY_FEATURE_ID, value: u32) -> Result<()> {
+    chkerr(unsafe{TYSetEnum(*h.raw(), comp, feat, value)})
+}
+
+pub(crate) fn ty_get_bool(h: &DeviceHandle, comp: TY_COMPONENT_ID, feat: TY_FEATURE_ID) -> Result<bool> {
+    let mut out = false;
+    chkerr(unsafe{TYGetBool(*h.raw(), comp, feat, &mut out)})?;
+    Ok(out)
+}
+
+pub(crate) fn ty_set_bool(h: &DeviceHandle, comp: TY_COMPONENT_ID, feat: TY_FEATURE_ID, value: bool) -> Result<()> {
+    chkerr(unsafe{TYSetBool(*h.raw(), comp, feat, value)})
+}
+
+pub(crate) fn ty_get_string(h: &DeviceHandle, comp: TY_COMPONENT_ID, feat: TY_FEATURE_ID) -> Result<String> {
+    let mut n: u32 = 0;
+    chkerr(unsafe{TYGetStringLength(*h.raw(), comp, feat, &mut n)})?;
+    let mut out = vec![0 as std::ffi::c_char; n as usize + 1];
+    chkerr(unsafe{TYGetString(*h.raw(), comp, feat, out.as_mut_ptr(), out.len() as u32)})?;
+    Ok(carr_to_str(&out).into_owned())
+}
+
+pub(crate) fn ty_set_string(h: &DeviceHandle, comp: TY_COMPONENT_ID, feat: TY_FEATURE_ID, value: &str) -> Result<()> {
+    let value = to_cstring(value)?;
+    chkerr(unsafe{TYSetString(*h.raw(), comp, feat, value.as_ptr())})
+}
+
+/// # Safety
+/// `T` must be the C struct the feature is declared with.
+pub(crate) unsafe fn ty_get_struct<T>(h: &DeviceHandle, comp: TY_COMPONENT_ID, feat: TY_FEATURE_ID, value: &mut T) -> Result<()> {
+    chkerr(unsafe{
+        TYGetStruct(*h.raw(), comp, feat, (value as *mut T).cast(), size_of::<T>() as u32)
+    })
+}
+
+/// # Safety
+/// `T` must be the C struct the feature is declared with.
+pub(crate) unsafe fn ty_set_struct<T: Copy>(h: &DeviceHandle, comp: TY_COMPONENT_ID, feat: TY_FEATURE_ID, value: &T) -> Result<()> {
+    let mut value = *value;
+    chkerr(unsafe{
+        TYSetStruct(*h.raw(), comp, feat, (&mut value as *mut T).cast(), size_of::<T>() as u32)
+    })
+}
+
+pub(crate) fn ty_get_struct_bytes(h: &DeviceHandle, comp: TY_COMPONENT_ID, feat: TY_FEATURE_ID, value: &mut [u8]) -> Result<()> {
+    chkerr(unsafe{
+        TYGetStruct(*h.raw(), comp, feat, value.as_mut_ptr().cast(), value.len() as u32)
+    })
+}
+
+pub(crate) fn ty_set_struct_bytes(h: &DeviceHandle, comp: TY_COMPONENT_ID, feat: TY_FEATURE_ID, value: &[u8]) -> Result<()> {
+    let mut value = value.to_vec();
+    chkerr(unsafe{
+        TYSetStruct(*h.raw(), comp, feat, value.as_mut_ptr().cast(), value.len() as u32)
+    })
+}
+
+pub(crate) fn ty_get_byte_array_size(h: &DeviceHandle, comp: TY_COMPONENT_ID, feat: TY_FEATURE_ID) -> Result<usize> {
+    let mut out: u32 = 0;
+    chkerr(unsafe{TYGetByteArraySize(*h.raw(), comp, feat, &mut out)})?;
+    Ok(out as usize)
+}
+
+pub(crate) fn ty_get_byte_array(h: &DeviceHandle, comp: TY_COMPONENT_ID, feat: TY_FEATURE_ID) -> Result<Vec<u8>> {
+    let n = ty_get_byte_array_size(h, comp, feat)?;
+    let mut out = vec![0u8; n];
+    chkerr(unsafe{TYGetByteArray(*h.raw(), comp, feat, out.as_mut_ptr(), n as u32)})?;
+    Ok(out)
+}
+
+pub(crate) fn ty_set_byte_array(h: &DeviceHandle, comp: TY_COMPONENT_ID, feat: TY_FEATURE_ID, value: &[u8]) -> Result<()> {
+    chkerr(unsafe{TYSetByteArray(*h.raw(), comp, feat, value.as_ptr(), value.len() as u32)})
+}
+
+pub(crate) fn ty_get_device_feature_info(h: &DeviceHandle, comp: TY_COMPONENT_ID) -> Result<Vec<FeatureInfo>> {
+    let mut n: u32 = 0;
+    chkerr(unsafe{TYGetDeviceFeatureNumber(*h.raw(), comp, &mut n)})?;
+    if n == 0 {
+        return Ok(Vec::new())
+    }
+
+    let mut out = Vec::<TY_FEATURE_INFO>::with_capacity(n as usize);
+    let mut filled_n = 0;
+    unsafe {
+        chkerr(TYGetDeviceFeatureInfo(*h.raw(), comp, out.as_mut_ptr(), n, &mut filled_n))?;
+        out.set_len(filled_n.min(n) as usize);
+        Ok(transmute::<Vec<TY_FEATURE_INFO>, Vec<FeatureInfo>>(out))
+    }
+}
+
+pub(crate) fn ty_map_depth_image_to_point3d(
+    calib: &TY_CAMERA_CALIB_INFO, width: i32, height: i32, depth: &[u16], out: &mut [TY_VECT_3F], scale_unit: f32,
+) -> Result<()> {
+    let n = width.max(0) as usize * height.max(0) as usize;
+    if depth.len() < n || out.len() < n {
+        return Err(ErrorCode::InvalidParameter.into());
+    }
+    chkerr(unsafe {
+        TYMapDepthImageToPoint3d(calib, width, height, depth.as_ptr(), out.as_mut_ptr(), scale_unit)
+    })
+}
+
+pub(crate) fn ty_invert_extrinsic(extrinsic: &TY_CAMERA_EXTRINSIC) -> Result<TY_CAMERA_EXTRINSIC> {
+    let mut out = MaybeUninit::uninit();
+    unsafe {
+        chkerr(TYInvertExtrinsic(extrinsic, out.as_mut_ptr()))?;
+        Ok(out.assume_init())
+    }
+}
+
+pub(crate) fn ty_map_point3d_to_point3d(
+    extrinsic: &TY_CAMERA_EXTRINSIC, points: &[TY_VECT_3F], out: &mut [TY_VECT_3F],
+) -> Result<()> {
+    let count = i32::try_from(points.len()).map_err(|_| ErrorCode::InvalidParameter)?;
+    if out.len() < points.len() {
+        return Err(ErrorCode::InvalidParameter.into());
+    }
+    chkerr(unsafe { TYMapPoint3dToPoint3d(extrinsic, points.as_ptr(), count, out.as_mut_ptr()) })
+}
 
 #[cfg(test)]
 mod tests {
+    use std::sync::atomic::{AtomicBool, Ordering};
     use super::*;
 
     const VALID_ID: &str = "eth-30:0e:d5:57:c2:ea9b04a8c0";
@@ -358,27 +915,73 @@ mod tests {
     #[test]
     fn test_basics() {
         assert_eq!(ErrorCode::from_repr(-1002).unwrap(), ErrorCode::NotInited);
+        assert_eq!(ErrorCode::from(-1002), ErrorCode::NotInited);
+        assert_eq!(ErrorCode::from(-4242), ErrorCode::Unknown(-4242));
 
         const DEV_NR: usize = 4;
 
-        let ty_ver =  ty_lib_version().unwrap();
-        let ver: (u32, u32, u32) = ty_ver.clone().into();
+        let ctx = Context::new().unwrap();
+        let ty_ver = ctx.version().unwrap();
+        let ver: (u32, u32, u32) = ty_ver.into();
         assert_eq!(ver, (3, 6, 66));
-        // dbg!(ty_error_string(-1002));
-        ty_init_lib().unwrap();
-        assert_eq!(ty_error_string(-1002), "not initialized");
+        assert_eq!(ctx.error_string(-1002), "not initialized");
 
-        ty_update_interface_list().unwrap();
-        let n = ty_get_interface_number().unwrap();
+        ctx.update_interface_list().unwrap();
+        let n = ctx.get_interface_number().unwrap();
         assert_eq!(n, DEV_NR);
 
-        let dev_list = ty_get_interface_list(n).unwrap();
+        let dev_list = ctx.get_interface_list(n).unwrap();
         assert_eq!(dev_list.len(), DEV_NR);
 
-        assert_eq!(ty_has_interface("not exists id").unwrap(), false);
-        assert_eq!(ty_has_interface(VALID_ID).unwrap(), true);
+        assert!(!ctx.has_interface("not exists id").unwrap());
+        assert!(ctx.has_interface(VALID_ID).unwrap());
 
         let s = serde_yaml::to_string(&ty_ver).unwrap();
         assert_eq!(s, "major: 3\nminor: 6\npatch: 66\n");
     }
-}
\ No newline at end of file
+
+    #[test]
+    fn test_context_refcount() {
+        let ctx1 = Context::new().unwrap();
+        let ctx2 = Context::new().unwrap();
+        drop(ctx1);
+        ctx2.update_interface_list().unwrap();
+    }
+
+    #[test]
+    fn test_fetch_offline() {
+        let offline = AtomicBool::new(true);
+        let ret = fetch_in_slices(-1, || offline.load(Ordering::Acquire), |_| -> Result<()> { unreachable!() });
+        assert_eq!(ret.unwrap_err().errcode, ErrorCode::DeviceOffline);
+
+        // Going offline during a blocking fetch ends it after the current slice.
+        let offline = AtomicBool::new(false);
+        let mut calls = 0;
+        let ret = fetch_in_slices(-1, || offline.load(Ordering::Acquire), |slice| -> Result<()> {
+            assert_eq!(slice, FETCH_SLICE_MS);
+            calls += 1;
+            if calls == 3 {
+                offline.store(true, Ordering::Release);
+            }
+            Err(ErrorCode::TIMEOUT.into())
+        });
+        assert_eq!(ret.unwrap_err().errcode, ErrorCode::DeviceOffline);
+        assert_eq!(calls, 3);
+
+        let ret = fetch_in_slices(0, || false, |_| -> Result<()> { Err(ErrorCode::TIMEOUT.into()) });
+        assert_eq!(ret.unwrap_err().errcode, ErrorCode::TIMEOUT);
+    }
+
+    #[test]
+    fn test_firmware_faults() {
+        assert_eq!(FirmwareFaults::empty().to_string(), "none");
+
+        let faults = FirmwareFaults::from_bits_retain(
+            TY_FW_ERRORCODE_CAM1_NOT_DETECTED | TY_FW_ERRORCODE_XML_NOT_FOUND | 0x100);
+        assert!(faults.contains(FirmwareFaults::CAM1_NOT_DETECTED));
+        assert_eq!(faults.to_string(), "camera 1 not detected, XML not found, unknown faults 0x100");
+
+        let err = DeviceError { errcode: ErrorCode::DeviceError, firmware_errcode: Some(faults) };
+        assert!(err.to_string().starts_with("device error: camera 1 not detected"));
+    }
+}
@@ -0,0 +1,269 @@
+use std::collections::VecDeque;
+use std::sync::Arc;
+use camport3_sys::*;
+
+use crate::ffi::*;
+use crate::capture::*;
+
+/// How the master camera emits trigger signals to the slaves.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum MasterTrigger {
+    /// `TY_TRIGGER_MODE_M_SIG`: one signal per soft or hardware trigger, see [`CameraGroup::trigger`].
+    Signal,
+    /// `TY_TRIGGER_MODE_M_PER`: periodic signals at the given fps.
+    Periodic(i8),
+}
+
+/// What frames of different cameras are matched on.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum MatchKey {
+    /// Image index, i.e. the trigger count. Tolerance is in triggers.
+    ImageIndex,
+    /// Image timestamp. Tolerance is in microseconds; the device clocks must be synchronised.
+    Timestamp,
+}
+
+#[derive(Debug, Clone)]
+pub struct GroupConfig {
+    pub trigger: MasterTrigger,
+    /// Components enabled on every camera.
+    pub components: TY_COMPONENT_ID,
+    pub match_key: MatchKey,
+    pub tolerance: u64,
+    pub buffer_count: usize,
+}
+
+impl Default for GroupConfig {
+    fn default() -> Self {
+        GroupConfig {
+            trigger: MasterTrigger::Periodic(10),
+            components: TY_DEVICE_COMPONENT_LIST::TY_COMPONENT_DEPTH_CAM as TY_COMPONENT_ID,
+            match_key: MatchKey::ImageIndex,
+            tolerance: 0,
+            buffer_count: DEFAULT_BUFFER_COUNT,
+        }
+    }
+}
+
+/// Frames of one trigger, one slot per camera in [`CameraGroup::device_ids`] order.
+#[derive(Debug, Clone)]
+pub struct FrameSet {
+    /// Match key of the master frame.
+    pub reference: i64,
+    frames: Vec<(String, Option<Frame>)>,
+}
+
+impl FrameSet {
+    pub fn master(&self) -> &Frame {
+        self.frames[0].1.as_ref().expect("a frame set always holds the master frame")
+    }
+
+    pub fn get(&self, device_id: &str) -> Option<&Frame> {
+        self.frames.iter()
+            .find(|(id, _)| id == device_id)
+            .and_then(|(_, frame)| frame.as_ref())
+    }
+
+    pub fn frames(&self) -> impl Iterator<Item = (&str, Option<&Frame>)> {
+        self.frames.iter().map(|(id, frame)| (id.as_str(), frame.as_ref()))
+    }
+
+    /// IDs of the cameras without a frame matching the master's.
+    pub fn missing(&self) -> Vec<&str> {
+        self.frames().filter(|(_, frame)| frame.is_none()).map(|(id, _)| id).collect()
+    }
+
+    pub fn is_complete(&self) -> bool {
+        self.frames.iter().all(|(_, frame)| frame.is_some())
+    }
+}
+
+#[derive(Debug)]
+struct Member {
+    id: String,
+    session: CaptureSession,
+    pending: VecDeque<(i64, Frame)>,
+}
+
+/// Cameras sharing a trigger line: one master driving the trigger, any number of slaves.
+///
+/// The master is configured with `TY_TRIGGER_MODE_M_SIG` or `TY_TRIGGER_MODE_M_PER` and every slave with
+/// `TY_TRIGGER_MODE_SLAVE`. Slaves start capturing before the master so that none misses the first trigger.
+#[derive(Debug)]
+pub struct CameraGroup {
+    // `members[0]` is the master.
+    members: Vec<Member>,
+    config: GroupConfig,
+}
+
+impl CameraGroup {
+    /// Open the cameras by ID, on whatever interface each is attached to.
+    ///
+    /// Fails with [`ErrorCode::DeviceOffline`] if a camera is found on no interface.
+    pub fn open(ctx: &Context, master_id: &str, slave_ids: &[&str], config: GroupConfig) -> Result<Self> {
+        let open = |id: &str| -> Result<Arc<DeviceHandle>> {
+            let iface = ctx.find_device(id)?.ok_or(ErrorCode::DeviceOffline)?;
+            Ok(Arc::new(iface.open_device(id)?))
+        };
+        let master = open(master_id)?;
+        let slaves = slave_ids.iter().map(|id| open(id)).collect::<Result<Vec<_>>>()?;
+        Self::new(master, slaves, config)
+    }
+
+    /// Configure already opened cameras and queue their frame buffers.
+    pub fn new(master: Arc<DeviceHandle>, slaves: Vec<Arc<DeviceHandle>>, config: GroupConfig) -> Result<Self> {
+        let mut members = Vec::with_capacity(slaves.len() + 1);
+        let roles = std::iter::once((master, true)).chain(slaves.into_iter().map(|dev| (dev, false)));
+        for (dev, is_master) in roles {
+            match (is_master, config.trigger) {
+                (false, _) => dev.set_trigger_param(TY_TRIGGER_MODE_LIST::TY_TRIGGER_MODE_SLAVE, 0)?,
+                (true, MasterTrigger::Signal) => dev.set_trigger_param(TY_TRIGGER_MODE_LIST::TY_TRIGGER_MODE_M_SIG, 0)?,
+                (true, MasterTrigger::Periodic(fps)) => dev.set_trigger_param(TY_TRIGGER_MODE_LIST::TY_TRIGGER_MODE_M_PER, fps)?,
+            }
+            dev.enable_components(config.components)?;
+            let id = dev.get_device_info()?.id().into_owned();
+            members.push(Member {
+                id,
+                session: CaptureSession::new(dev, config.buffer_count)?,
+                pending: VecDeque::new(),
+            });
+        }
+        Ok(CameraGroup { members, config })
+    }
+
+    /// Device IDs, master first.
+    pub fn device_ids(&self) -> impl Iterator<Item = &str> {
+        self.members.iter().map(|m| m.id.as_str())
+    }
+
+    pub fn master(&self) -> &Arc<DeviceHandle> {
+        self.members[0].session.device()
+    }
+
+    pub fn config(&self) -> &GroupConfig {
+        &self.config
+    }
+
+    pub fn is_capturing(&self) -> bool {
+        self.members[0].session.is_capturing()
+    }
+
+    /// Start the slaves, then the master.
+    pub fn start(&mut self) -> Result<()> {
+        for m in self.members.iter_mut().skip(1) {
+            m.session.start()?;
+        }
+        self.members[0].session.start()
+    }
+
+    /// Stop the master, then the slaves, and drop unmatched frames.
+    ///
+    /// Every camera is stopped even if some fail; the first error is returned.
+    pub fn stop(&mut self) -> Result<()> {
+        let mut result = Ok(());
+        for m in self.members.iter_mut() {
+            if let Err(e) = m.session.stop() {
+                log::warn!("stopping {} failed: {e}", m.id);
+                result = result.and(Err(e));
+            }
+            m.pending.clear();
+        }
+        result
+    }
+
+    /// Send a soft trigger to the master, for [`MasterTrigger::Signal`].
+    pub fn trigger(&self) -> Result<()> {
+        self.master().send_soft_trigger()
+    }
+
+    /// Fetch the next master frame and the slave frames matching it.
+    ///
+    /// Each camera is waited for at most `timeout_ms`. A slave that times out, or whose next frame is already
+    /// past the tolerance, has no frame in the set; see [`FrameSet::missing`].
+    pub fn fetch_set(&mut self, timeout_ms: i32) -> Result<FrameSet> {
+        let key = self.config.match_key;
+        let tolerance = self.config.tolerance;
+
+        let (reference, master_frame) = loop {
+            let frame = self.members[0].session.fetch_frame(timeout_ms)?;
+            if let Some(k) = frame_key(&frame, key) {
+                break (k, frame);
+            }
+        };
+
+        let mut frames = Vec::with_capacity(self.members.len());
+        frames.push((self.members[0].id.clone(), Some(master_frame)));
+        for m in self.members.iter_mut().skip(1) {
+            let frame = loop {
+                match take_match(&mut m.pending, reference, tolerance) {
+                    Match::Found(frame) => break Some(frame),
+                    Match::Missing => break None,
+                    Match::NeedMore => match m.session.fetch_frame(timeout_ms) {
+                        Ok(frame) => if let Some(k) = frame_key(&frame, key) {
+                            m.pending.push_back((k, frame));
+                        },
+                        Err(e) if e.errcode == ErrorCode::TIMEOUT => break None,
+                        Err(e) => return Err(e),
+                    },
+                }
+            };
+            frames.push((m.id.clone(), frame));
+        }
+        Ok(FrameSet { reference, frames })
+    }
+}
+
+fn frame_key(frame: &Frame, key: MatchKey) -> Option<i64> {
+    frame.images().first().map(|img| image_key(img, key))
+}
+
+pub(crate) fn image_key(img: &Image, key: MatchKey) -> i64 {
+    match key {
+        MatchKey::ImageIndex => i64::from(img.image_index),
+        MatchKey::Timestamp => img.timestamp as i64,
+    }
+}
+
+#[derive(Debug, PartialEq)]
+enum Match<T> {
+    Found(T),
+    /// The oldest pending frame is already newer than the reference.
+    Missing,
+    /// All pending frames were older than the reference and have been dropped.
+    NeedMore,
+}
+
+fn take_match<T>(pending: &mut VecDeque<(i64, T)>, reference: i64, tolerance: u64) -> Match<T> {
+    let tolerance = i64::try_from(tolerance).unwrap_or(i64::MAX);
+    let (lo, hi) = (reference.saturating_sub(tolerance), reference.saturating_add(tolerance));
+    while let Some(&(k, _)) = pending.front() {
+        if k < lo {
+            pending.pop_front();
+        } else if k <= hi {
+            return Match::Found(pending.pop_front().unwrap().1);
+        } else {
+            return Match::Missing;
+        }
+    }
+    Match::NeedMore
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_take_match() {
+        let mut pending: VecDeque<(i64, i64)> = [(1, 1), (2, 2), (4, 4)].into_iter().collect();
+        assert_eq!(take_match(&mut pending, 2, 0), Match::Found(2));
+        assert_eq!(pending.len(), 1);
+        assert_eq!(take_match(&mut pending, 3, 0), Match::Missing);
+        assert_eq!(take_match(&mut pending, 5, 1), Match::Found(4));
+        assert_eq!(take_match(&mut pending, 6, 0), Match::NeedMore);
+
+        let mut pending: VecDeque<(i64, i64)> = [(990, 0), (2005, 1)].into_iter().collect();
+        assert_eq!(take_match(&mut pending, 2000, 10), Match::Found(1));
+        assert!(pending.is_empty());
+        assert_eq!(take_match(&mut pending, 0, u64::MAX), Match::NeedMore);
+    }
+}
@@ -0,0 +1,235 @@
+use std::fmt::Display;
+use serde::{Deserialize, Serialize};
+use strum_macros::FromRepr;
+use camport3_sys::*;
+
+use crate::ffi::*;
+use crate::feature::ComponentId;
+
+/// Pixel format part of an image mode, `TY_PIXEL_FORMAT_LIST`.
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Eq, Hash, FromRepr)]
+#[repr(u32)]
+pub enum PixelFormat {
+    Mono = TY_PIXEL_FORMAT_LIST::TY_PIXEL_FORMAT_MONO as u32,
+    Bayer8Gb = TY_PIXEL_FORMAT_LIST::TY_PIXEL_FORMAT_BAYER8GB as u32,
+    Bayer8Bg = TY_PIXEL_FORMAT_LIST::TY_PIXEL_FORMAT_BAYER8BG as u32,
+    Bayer8Gr = TY_PIXEL_FORMAT_LIST::TY_PIXEL_FORMAT_BAYER8GR as u32,
+    Bayer8Rg = TY_PIXEL_FORMAT_LIST::TY_PIXEL_FORMAT_BAYER8RG as u32,
+    CsiMono10 = TY_PIXEL_FORMAT_LIST::TY_PIXEL_FORMAT_CSI_MONO10 as u32,
+    CsiBayer10Grbg = TY_PIXEL_FORMAT_LIST::TY_PIXEL_FORMAT_CSI_BAYER10GRBG as u32,
+    CsiBayer10Rggb = TY_PIXEL_FORMAT_LIST::TY_PIXEL_FORMAT_CSI_BAYER10RGGB as u32,
+    CsiBayer10Gbrg = TY_PIXEL_FORMAT_LIST::TY_PIXEL_FORMAT_CSI_BAYER10GBRG as u32,
+    CsiBayer10Bggr = TY_PIXEL_FORMAT_LIST::TY_PIXEL_FORMAT_CSI_BAYER10BGGR as u32,
+    CsiMono12 = TY_PIXEL_FORMAT_LIST::TY_PIXEL_FORMAT_CSI_MONO12 as u32,
+    CsiBayer12Grbg = TY_PIXEL_FORMAT_LIST::TY_PIXEL_FORMAT_CSI_BAYER12GRBG as u32,
+    CsiBayer12Rggb = TY_PIXEL_FORMAT_LIST::TY_PIXEL_FORMAT_CSI_BAYER12RGGB as u32,
+    CsiBayer12Gbrg = TY_PIXEL_FORMAT_LIST::TY_PIXEL_FORMAT_CSI_BAYER12GBRG as u32,
+    CsiBayer12Bggr = TY_PIXEL_FORMAT_LIST::TY_PIXEL_FORMAT_CSI_BAYER12BGGR as u32,
+    Depth16 = TY_PIXEL_FORMAT_LIST::TY_PIXEL_FORMAT_DEPTH16 as u32,
+    Yvyu = TY_PIXEL_FORMAT_LIST::TY_PIXEL_FORMAT_YVYU as u32,
+    Yuyv = TY_PIXEL_FORMAT_LIST::TY_PIXEL_FORMAT_YUYV as u32,
+    Mono16 = TY_PIXEL_FORMAT_LIST::TY_PIXEL_FORMAT_MONO16 as u32,
+    TofIrMono16 = TY_PIXEL_FORMAT_LIST::TY_PIXEL_FORMAT_TOF_IR_MONO16 as u32,
+    Rgb = TY_PIXEL_FORMAT_LIST::TY_PIXEL_FORMAT_RGB as u32,
+    Bgr = TY_PIXEL_FORMAT_LIST::TY_PIXEL_FORMAT_BGR as u32,
+    Jpeg = TY_PIXEL_FORMAT_LIST::TY_PIXEL_FORMAT_JPEG as u32,
+    Mjpg = TY_PIXEL_FORMAT_LIST::TY_PIXEL_FORMAT_MJPG as u32,
+    Rgb48 = TY_PIXEL_FORMAT_LIST::TY_PIXEL_FORMAT_RGB48 as u32,
+    Bgr48 = TY_PIXEL_FORMAT_LIST::TY_PIXEL_FORMAT_BGR48 as u32,
+    Xyz48 = TY_PIXEL_FORMAT_LIST::TY_PIXEL_FORMAT_XYZ48 as u32,
+}
+
+/// A `TY_IMAGE_MODE`: pixel format in the top byte, width and height in the low 24 bits.
+///
+/// The accessors port the `static inline` helpers of `TYApi.h`.
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Eq, Hash)]
+#[serde(transparent)]
+pub struct ImageMode(pub TY_IMAGE_MODE);
+
+impl ImageMode {
+    /// Largest width or height a mode can encode, in 12 bits.
+    pub const MAX_SIZE: u32 = 0xfff;
+
+    /// `TYImageMode2`; `None` if `width` or `height` exceeds [`MAX_SIZE`](Self::MAX_SIZE).
+    pub fn new(format: PixelFormat, width: u32, height: u32) -> Option<Self> {
+        if width > Self::MAX_SIZE || height > Self::MAX_SIZE {
+            return None;
+        }
+        Some(ImageMode(format as u32 | (width << 12) | height))
+    }
+
+    /// `TYPixelFormat`, the raw format bits.
+    pub fn raw_pixel_format(&self) -> TY_PIXEL_FORMAT {
+        self.0 & 0xff00_0000
+    }
+
+    /// `None` for formats this crate does not know.
+    pub fn pixel_format(&self) -> Option<PixelFormat> {
+        PixelFormat::from_repr(self.raw_pixel_format())
+    }
+
+    /// `TYResolutionMode`.
+    pub fn resolution_mode(&self) -> TY_RESOLUTION_MODE {
+        (self.0 & 0x00ff_ffff) as TY_RESOLUTION_MODE
+    }
+
+    /// `TYImageWidth`.
+    pub fn width(&self) -> u32 {
+        (self.0 & 0x00ff_ffff) >> 12
+    }
+
+    /// `TYImageHeight`.
+    pub fn height(&self) -> u32 {
+        self.0 & 0x0fff
+    }
+
+    /// `TYBitsPerPixel`, 8 for bit depths the SDK does not list.
+    pub fn bits_per_pixel(&self) -> u32 {
+        use TY_PIXEL_BITS_LIST::*;
+        match self.0 & (0xf << 28) {
+            b if b == TY_PIXEL_16BIT as u32 => 16,
+            b if b == TY_PIXEL_24BIT as u32 => 24,
+            b if b == TY_PIXEL_32BIT as u32 => 32,
+            b if b == TY_PIXEL_48BIT as u32 => 48,
+            b if b == TY_PIXEL_64BIT as u32 => 64,
+            b if b == TY_PIXEL_10BIT as u32 => 10,
+            b if b == TY_PIXEL_12BIT as u32 => 12,
+            _ => 8,
+        }
+    }
+
+    /// `TYPixelLineSize`, bytes per line of `width` pixels.
+    pub fn line_size(&self, width: u32) -> u32 {
+        (width * self.bits_per_pixel()) >> 3
+    }
+}
+
+impl Display for ImageMode {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self.pixel_format() {
+            Some(format) => write!(f, "{:?} {}x{}", format, self.width(), self.height()),
+            None => write!(f, "{:#010x} {}x{}", self.raw_pixel_format(), self.width(), self.height()),
+        }
+    }
+}
+
+/// One alternative of [`select_image_mode`]: any of `formats` (any format if empty), closest to `size` if set.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct ModeQuery {
+    formats: Vec<PixelFormat>,
+    size: Option<(u32, u32)>,
+}
+
+impl ModeQuery {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn format(mut self, format: PixelFormat) -> Self {
+        self.formats.push(format);
+        self
+    }
+
+    pub fn closest_to(mut self, width: u32, height: u32) -> Self {
+        self.size = Some((width, height));
+        self
+    }
+
+    fn matches(&self, mode: &ImageMode) -> bool {
+        self.formats.is_empty() || mode.pixel_format().is_some_and(|f| self.formats.contains(&f))
+    }
+
+    /// Closest by pixel count, ties broken by aspect ratio then format order; the largest mode without a size.
+    fn pick(&self, modes: &[ImageMode]) -> Option<ImageMode> {
+        let format_rank = |m: &ImageMode| self.formats.iter().position(|f| Some(*f) == m.pixel_format()).unwrap_or(0);
+        let candidates = modes.iter().filter(|m| self.matches(m));
+        match self.size {
+            Some((w, h)) => {
+                let target = w as u64 * h as u64;
+                let aspect = w as f64 / h.max(1) as f64;
+                candidates.min_by(|a, b| {
+                    let dist = |m: &ImageMode| (m.width() as u64 * m.height() as u64).abs_diff(target);
+                    let skew = |m: &ImageMode| (m.width() as f64 / m.height().max(1) as f64 - aspect).abs();
+                    dist(a).cmp(&dist(b))
+                        .then(skew(a).total_cmp(&skew(b)))
+                        .then(format_rank(a).cmp(&format_rank(b)))
+                }).copied()
+            }
+            None => candidates
+                .min_by_key(|m| (std::cmp::Reverse(m.width() as u64 * m.height() as u64), format_rank(m)))
+                .copied(),
+        }
+    }
+}
+
+/// The mode picked by the first query that matches any of `modes`.
+///
+/// For "closest to 1280x960 in YUYV, else any RGB":
+/// `[ModeQuery::new().format(PixelFormat::Yuyv).closest_to(1280, 960), ModeQuery::new().format(PixelFormat::Rgb)]`.
+pub fn select_image_mode(modes: &[ImageMode], queries: &[ModeQuery]) -> Option<ImageMode> {
+    queries.iter().find_map(|q| q.pick(modes))
+}
+
+impl DeviceHandle {
+    /// Modes `comp` supports, from the entries of `TY_ENUM_IMAGE_MODE`.
+    pub fn image_modes(&self, comp: impl ComponentId) -> Result<Vec<ImageMode>> {
+        let entries = self.get_enum_entries(comp, TY_FEATURE_ID_LIST::TY_ENUM_IMAGE_MODE)?;
+        Ok(entries.iter().map(|e| ImageMode(e.value())).collect())
+    }
+
+    pub fn get_image_mode(&self, comp: impl ComponentId) -> Result<ImageMode> {
+        self.get_enum(comp, TY_FEATURE_ID_LIST::TY_ENUM_IMAGE_MODE).map(ImageMode)
+    }
+
+    pub fn set_image_mode(&self, comp: impl ComponentId, mode: ImageMode) -> Result<()> {
+        self.set_enum(comp, TY_FEATURE_ID_LIST::TY_ENUM_IMAGE_MODE, mode.0)
+    }
+
+    /// [`select_image_mode`] among the modes of `comp` and set it; `None`, writing nothing, if no query matched.
+    pub fn select_image_mode(&self, comp: impl ComponentId, queries: &[ModeQuery]) -> Result<Option<ImageMode>> {
+        let comp = comp.component_id();
+        let Some(mode) = select_image_mode(&self.image_modes(comp)?, queries) else {
+            return Ok(None);
+        };
+        self.set_image_mode(comp, mode)?;
+        Ok(Some(mode))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_image_mode() {
+        let mode = ImageMode(TY_IMAGE_MODE_LIST::TY_IMAGE_MODE_YUYV_1280x960 as u32);
+        assert_eq!(mode.pixel_format(), Some(PixelFormat::Yuyv));
+        assert_eq!((mode.width(), mode.height()), (1280, 960));
+        assert_eq!(mode.bits_per_pixel(), 16);
+        assert_eq!(mode.line_size(1280), 2560);
+        assert_eq!(ImageMode::new(PixelFormat::Yuyv, 1280, 960), Some(mode));
+        assert_eq!(ImageMode::new(PixelFormat::Yuyv, 4095, 4095).map(|m| (m.width(), m.height())), Some((4095, 4095)));
+        assert_eq!(ImageMode::new(PixelFormat::Yuyv, 4096, 960), None);
+        assert_eq!(ImageMode::new(PixelFormat::Yuyv, 1280, 4096), None);
+        assert_eq!(mode.to_string(), "Yuyv 1280x960");
+
+        let mono10 = ImageMode::new(PixelFormat::CsiMono10, 640, 480).unwrap();
+        assert_eq!(mono10.bits_per_pixel(), 10);
+        assert_eq!(mono10.line_size(640), 800);
+    }
+
+    #[test]
+    fn test_select_image_mode() {
+        use PixelFormat::*;
+        let modes = [(Yuyv, 640, 480), (Yuyv, 2560, 1920), (Bgr, 1280, 960), (Rgb, 640, 480), (Rgb, 1920, 1080)]
+            .map(|(f, w, h)| ImageMode::new(f, w, h).unwrap());
+        let yuyv = ModeQuery::new().format(Yuyv).closest_to(1280, 960);
+        let rgb = ModeQuery::new().format(Rgb);
+        assert_eq!(select_image_mode(&modes, &[yuyv.clone(), rgb.clone()]), Some(modes[0]));
+        assert_eq!(select_image_mode(&modes[2..], &[yuyv.clone(), rgb.clone()]), Some(modes[4]));
+        assert_eq!(select_image_mode(&modes[2..3], &[yuyv, rgb]), None);
+
+        let any = ModeQuery::new().closest_to(1280, 960);
+        assert_eq!(select_image_mode(&modes, &[any]), Some(modes[2]));
+    }
+}
@@ -0,0 +1,241 @@
+use serde::{Deserialize, Serialize};
+use camport3_sys::*;
+
+use crate::ffi::*;
+
+/// Structured light pattern of `TY_STRUCT_LASER_PATTERN`, `TY_LASER_PATTERN_PARAM`.
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+pub enum LaserPattern {
+    Sine { phase_num: u32, period: f32 },
+    Gray { phase_num: u32, param1: u32, param2: u32, param3: u32 },
+    /// Raw pattern bits, at most 512 bytes.
+    Binary { offset: u32, data: Vec<u8> },
+    Empty,
+}
+
+/// Maximum length of [`LaserPattern::Binary`] data.
+pub const LASER_PATTERN_DATA_LEN: usize = 512;
+
+impl LaserPattern {
+    fn from_raw(raw: &TY_LASER_PATTERN_PARAM) -> Option<Self> {
+        let t = raw.type_;
+        let payload = raw.__bindgen_anon_1;
+        // SAFETY: the union members are plain integers and floats, valid for any content.
+        Some(match t {
+            t if t == TY_PATTERN_SINE_TYPE as u32 => {
+                let p = unsafe { payload.sine_param };
+                LaserPattern::Sine { phase_num: p.phase_num, period: p.period }
+            }
+            t if t == TY_PATTERN_GRAY_TYPE as u32 => {
+                let p = unsafe { payload.gray_param };
+                LaserPattern::Gray { phase_num: p.phase_num, param1: p.param1, param2: p.param2, param3: p.param3 }
+            }
+            t if t == TY_PATTERN_BIN_TYPE as u32 => {
+                let p = unsafe { payload.bin };
+                LaserPattern::Binary { offset: p.offset, data: p.data.to_vec() }
+            }
+            t if t == TY_PATTERN_EMPTY_TYPE as u32 => LaserPattern::Empty,
+            _ => return None,
+        })
+    }
+
+    fn to_raw(&self, img_index: u32) -> Result<TY_LASER_PATTERN_PARAM> {
+        let mut payload = TY_LASER_PATTERN_PARAM__bindgen_ty_1 { payload: [0; 528] };
+        let type_ = match self {
+            LaserPattern::Sine { phase_num, period } => {
+                payload.sine_param = pattern_sine_param { phase_num: *phase_num, period: *period };
+                TY_PATTERN_SINE_TYPE
+            }
+            LaserPattern::Gray { phase_num, param1, param2, param3 } => {
+                payload.gray_param = pattern_gray_param {
+                    phase_num: *phase_num,
+                    param1: *param1,
+                    param2: *param2,
+                    param3: *param3,
+                };
+                TY_PATTERN_GRAY_TYPE
+            }
+            LaserPattern::Binary { offset, data } => {
+                if data.len() > LASER_PATTERN_DATA_LEN {
+                    return Err(ErrorCode::WrongSize.into());
+                }
+                let mut bin = pattern_bin_param { offset: *offset, data: [0; LASER_PATTERN_DATA_LEN] };
+                bin.data[..data.len()].copy_from_slice(data);
+                payload.bin = bin;
+                TY_PATTERN_BIN_TYPE
+            }
+            LaserPattern::Empty => TY_PATTERN_EMPTY_TYPE,
+        };
+        Ok(TY_LASER_PATTERN_PARAM { img_index, type_: type_ as u32, __bindgen_anon_1: payload })
+    }
+}
+
+/// Enable state and power of one laser or flood emitter.
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Emitter {
+    pub enabled: bool,
+    pub power: u32,
+}
+
+/// Laser, flood and flashlight controls of a device, see [`DeviceHandle::laser`].
+///
+/// Setters of `TY_INT_*` features clamp to the feature range and return the value written, like
+/// [`Exposure`](crate::Exposure). The SDK reports no range for the per-emitter powers, they are written as given.
+#[derive(Debug, Clone, Copy)]
+pub struct Laser<'a> {
+    dev: &'a DeviceHandle,
+}
+
+const LASER: TY_DEVICE_COMPONENT_LIST = TY_DEVICE_COMPONENT_LIST::TY_COMPONENT_LASER;
+const IR: TY_DEVICE_COMPONENT_LIST = TY_DEVICE_COMPONENT_LIST::TY_COMPONENT_IR_CAM_LEFT;
+const RGB: TY_DEVICE_COMPONENT_LIST = TY_DEVICE_COMPONENT_LIST::TY_COMPONENT_RGB_CAM;
+
+impl DeviceHandle {
+    pub fn laser(&self) -> Laser<'_> {
+        Laser { dev: self }
+    }
+}
+
+impl Laser<'_> {
+    fn read_param(&self, feat: TY_FEATURE_ID_LIST, idx: u32) -> Result<TY_LASER_PARAM> {
+        let mut param = TY_LASER_PARAM { idx, en: 0, power: 0 };
+        self.dev.read_struct(LASER, feat, &mut param)?;
+        Ok(param)
+    }
+
+    fn write_param(&self, feat: TY_FEATURE_ID_LIST, param: TY_LASER_PARAM) -> Result<()> {
+        self.dev.set_struct(LASER, feat, &param)
+    }
+
+    /// `TY_INT_LASER_POWER`, shared by all emitters.
+    pub fn power(&self) -> Result<i32> {
+        self.dev.get_int(LASER, TY_FEATURE_ID_LIST::TY_INT_LASER_POWER)
+    }
+
+    pub fn set_power(&self, power: i32) -> Result<i32> {
+        self.dev.set_int_clamped(LASER, TY_FEATURE_ID_LIST::TY_INT_LASER_POWER, power)
+    }
+
+    /// `TY_BOOL_LASER_AUTO_CTRL`: the laser is only on while the sensors expose.
+    pub fn auto_ctrl(&self) -> Result<bool> {
+        self.dev.get_bool(LASER, TY_FEATURE_ID_LIST::TY_BOOL_LASER_AUTO_CTRL)
+    }
+
+    pub fn set_auto_ctrl(&self, on: bool) -> Result<()> {
+        self.dev.set_bool(LASER, TY_FEATURE_ID_LIST::TY_BOOL_LASER_AUTO_CTRL, on)
+    }
+
+    /// Laser emitter `idx`, from `TY_STRUCT_LASER_ENABLE_BY_IDX` and `TY_STRUCT_LASER_POWER_BY_IDX`.
+    pub fn emitter(&self, idx: u32) -> Result<Emitter> {
+        use TY_FEATURE_ID_LIST::*;
+        Ok(Emitter {
+            enabled: self.read_param(TY_STRUCT_LASER_ENABLE_BY_IDX, idx)?.en != 0,
+            power: self.read_param(TY_STRUCT_LASER_POWER_BY_IDX, idx)?.power,
+        })
+    }
+
+    pub fn set_emitter_enabled(&self, idx: u32, enabled: bool) -> Result<()> {
+        let param = TY_LASER_PARAM { idx, en: enabled as u32, power: 0 };
+        self.write_param(TY_FEATURE_ID_LIST::TY_STRUCT_LASER_ENABLE_BY_IDX, param)
+    }
+
+    /// Leaves the emitter enabled or disabled as it is. `power` is not clamped, the device rejects invalid values.
+    pub fn set_emitter_power(&self, idx: u32, power: u32) -> Result<()> {
+        use TY_FEATURE_ID_LIST::*;
+        let en = self.read_param(TY_STRUCT_LASER_ENABLE_BY_IDX, idx)?.en;
+        self.write_param(TY_STRUCT_LASER_POWER_BY_IDX, TY_LASER_PARAM { idx, en, power })
+    }
+
+    /// Flood emitter `idx`, from `TY_STRUCT_FLOOD_ENABLE_BY_IDX` and `TY_STRUCT_FLOOD_POWER_BY_IDX`.
+    pub fn flood(&self, idx: u32) -> Result<Emitter> {
+        use TY_FEATURE_ID_LIST::*;
+        Ok(Emitter {
+            enabled: self.read_param(TY_STRUCT_FLOOD_ENABLE_BY_IDX, idx)?.en != 0,
+            power: self.read_param(TY_STRUCT_FLOOD_POWER_BY_IDX, idx)?.power,
+        })
+    }
+
+    pub fn set_flood_enabled(&self, idx: u32, enabled: bool) -> Result<()> {
+        let param = TY_LASER_PARAM { idx, en: enabled as u32, power: 0 };
+        self.write_param(TY_FEATURE_ID_LIST::TY_STRUCT_FLOOD_ENABLE_BY_IDX, param)
+    }
+
+    /// Leaves the emitter enabled or disabled as it is. `power` is not clamped, the device rejects invalid values.
+    pub fn set_flood_power(&self, idx: u32, power: u32) -> Result<()> {
+        use TY_FEATURE_ID_LIST::*;
+        let en = self.read_param(TY_STRUCT_FLOOD_ENABLE_BY_IDX, idx)?.en;
+        self.write_param(TY_STRUCT_FLOOD_POWER_BY_IDX, TY_LASER_PARAM { idx, en, power })
+    }
+
+    pub fn ir_flashlight(&self) -> Result<bool> {
+        self.dev.get_bool(IR, TY_FEATURE_ID_LIST::TY_BOOL_IR_FLASHLIGHT)
+    }
+
+    pub fn set_ir_flashlight(&self, on: bool) -> Result<()> {
+        self.dev.set_bool(IR, TY_FEATURE_ID_LIST::TY_BOOL_IR_FLASHLIGHT, on)
+    }
+
+    pub fn ir_flashlight_intensity(&self) -> Result<i32> {
+        self.dev.get_int(IR, TY_FEATURE_ID_LIST::TY_INT_IR_FLASHLIGHT_INTENSITY)
+    }
+
+    pub fn set_ir_flashlight_intensity(&self, level: i32) -> Result<i32> {
+        self.dev.set_int_clamped(IR, TY_FEATURE_ID_LIST::TY_INT_IR_FLASHLIGHT_INTENSITY, level)
+    }
+
+    pub fn rgb_flashlight(&self) -> Result<bool> {
+        self.dev.get_bool(RGB, TY_FEATURE_ID_LIST::TY_BOOL_RGB_FLASHLIGHT)
+    }
+
+    pub fn set_rgb_flashlight(&self, on: bool) -> Result<()> {
+        self.dev.set_bool(RGB, TY_FEATURE_ID_LIST::TY_BOOL_RGB_FLASHLIGHT, on)
+    }
+
+    pub fn rgb_flashlight_intensity(&self) -> Result<i32> {
+        self.dev.get_int(RGB, TY_FEATURE_ID_LIST::TY_INT_RGB_FLASHLIGHT_INTENSITY)
+    }
+
+    pub fn set_rgb_flashlight_intensity(&self, level: i32) -> Result<i32> {
+        self.dev.set_int_clamped(RGB, TY_FEATURE_ID_LIST::TY_INT_RGB_FLASHLIGHT_INTENSITY, level)
+    }
+
+    /// Pattern projected for image `img_index` of a pattern sequence.
+    pub fn pattern(&self, img_index: u32) -> Result<LaserPattern> {
+        let mut raw = LaserPattern::Empty.to_raw(img_index)?;
+        self.dev.read_struct(LASER, TY_FEATURE_ID_LIST::TY_STRUCT_LASER_PATTERN, &mut raw)?;
+        LaserPattern::from_raw(&raw).ok_or_else(|| ErrorCode::NotImplemented.into())
+    }
+
+    pub fn set_pattern(&self, img_index: u32, pattern: &LaserPattern) -> Result<()> {
+        self.dev.set_struct(LASER, TY_FEATURE_ID_LIST::TY_STRUCT_LASER_PATTERN, &pattern.to_raw(img_index)?)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_pattern_raw() {
+        let patterns = [
+            LaserPattern::Sine { phase_num: 4, period: 12.5 },
+            LaserPattern::Gray { phase_num: 7, param1: 1, param2: 2, param3: 3 },
+            LaserPattern::Binary { offset: 8, data: vec![0xaa; LASER_PATTERN_DATA_LEN] },
+            LaserPattern::Empty,
+        ];
+        for pattern in patterns {
+            let raw = pattern.to_raw(3).unwrap();
+            assert_eq!({ raw.img_index }, 3);
+            assert_eq!(LaserPattern::from_raw(&raw), Some(pattern));
+        }
+
+        let binary = LaserPattern::Binary { offset: 0, data: vec![1, 2] };
+        let LaserPattern::Binary { data, .. } = LaserPattern::from_raw(&binary.to_raw(0).unwrap()).unwrap() else {
+            panic!("not a binary pattern");
+        };
+        assert_eq!(&data[..3], [1, 2, 0]);
+
+        let too_long = LaserPattern::Binary { offset: 0, data: vec![0; LASER_PATTERN_DATA_LEN + 1] };
+        assert_eq!(too_long.to_raw(0).unwrap_err().errcode, ErrorCode::WrongSize);
+    }
+}
@@ -2,6 +2,54 @@ mod utils;
 mod ffi_macros;
 mod ffi;
 mod types;
+mod feature;
+mod profile;
+mod event;
+mod buffer;
+mod capture;
+mod group;
+mod pairing;
+mod stats;
+mod network;
+mod timesync;
+mod dio;
+mod laser;
+mod exposure;
+mod sgbm;
+mod phase;
+mod tof;
+mod depth;
+mod image_mode;
+mod supervisor;
+mod mapper;
+mod transform;
+mod watcher;
+#[cfg(feature = "async")]
+mod stream;
 
 pub use ffi::*;
-pub use types::*;
\ No newline at end of file
+pub use types::*;
+pub use feature::*;
+pub use profile::*;
+pub use event::*;
+pub use buffer::*;
+pub use capture::*;
+pub use group::*;
+pub use pairing::*;
+pub use stats::*;
+pub use network::*;
+pub use timesync::*;
+pub use dio::*;
+pub use laser::*;
+pub use exposure::*;
+pub use sgbm::*;
+pub use phase::*;
+pub use tof::*;
+pub use depth::*;
+pub use image_mode::*;
+pub use supervisor::*;
+pub use mapper::*;
+pub use transform::*;
+pub use watcher::*;
+#[cfg(feature = "async")]
+pub use stream::*;
@@ -0,0 +1,52 @@
+use camport3_sys::*;
+
+use crate::ffi::*;
+use crate::feature::ComponentId;
+
+/// A 3D point, in millimetres unless scaled.
+pub type Point3 = TY_VECT_3F;
+
+impl DeviceHandle {
+    /// `TY_STRUCT_CAM_CALIB_DATA` of a component.
+    pub fn calib_info(&self, comp: impl ComponentId) -> Result<TY_CAMERA_CALIB_INFO> {
+        self.get_struct(comp, TY_FEATURE_ID_LIST::TY_STRUCT_CAM_CALIB_DATA)
+    }
+}
+
+/// Map a `width` x `height` depth image to one point per pixel, row-major, with `TYMapDepthImageToPoint3d`.
+///
+/// Zero depth maps to NaN. `scale_unit` is `TY_FLOAT_SCALE_UNIT` of the depth component, 1.0 for millimetres.
+/// Fails with [`ErrorCode::InvalidParameter`] when `depth` holds fewer than `width * height` pixels.
+pub fn depth_to_points(
+    calib: &TY_CAMERA_CALIB_INFO, width: u32, height: u32, depth: &[u16], scale_unit: f32,
+) -> Result<Vec<Point3>> {
+    let mut out = vec![Point3 { x: 0.0, y: 0.0, z: 0.0 }; width as usize * height as usize];
+    depth_to_points_into(calib, width, height, depth, scale_unit, &mut out)?;
+    Ok(out)
+}
+
+/// Like [`depth_to_points`], writing into a caller-provided buffer of at least `width * height` points.
+pub fn depth_to_points_into(
+    calib: &TY_CAMERA_CALIB_INFO, width: u32, height: u32, depth: &[u16], scale_unit: f32, out: &mut [Point3],
+) -> Result<()> {
+    let (w, h) = (i32::try_from(width), i32::try_from(height));
+    let (Ok(w), Ok(h)) = (w, h) else {
+        return Err(ErrorCode::InvalidParameter.into());
+    };
+    ty_map_depth_image_to_point3d(calib, w, h, depth, out, scale_unit)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_size_check() {
+        let calib: TY_CAMERA_CALIB_INFO = unsafe { std::mem::zeroed() };
+        let err = depth_to_points(&calib, 4, 3, &[0; 11], 1.0).unwrap_err();
+        assert_eq!(err.errcode, ErrorCode::InvalidParameter);
+        let mut out = [Point3 { x: 0.0, y: 0.0, z: 0.0 }; 2];
+        let err = depth_to_points_into(&calib, 2, 2, &[0; 4], 1.0, &mut out).unwrap_err();
+        assert_eq!(err.errcode, ErrorCode::InvalidParameter);
+    }
+}
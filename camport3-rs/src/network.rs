@@ -0,0 +1,278 @@
+use std::cmp::Ordering;
+use std::time::{Duration, Instant};
+use serde::{Deserialize, Serialize};
+use camport3_sys::*;
+
+use crate::ffi::*;
+use crate::feature::DEVICE;
+use crate::capture::CaptureSession;
+
+/// GigE streaming parameters of the device component. `None` fields are absent on read and left alone on apply.
+#[derive(Serialize, Deserialize, Debug, Clone, Default, PartialEq, Eq)]
+pub struct NetworkTuning {
+    /// `TY_INT_PACKET_SIZE`, bytes.
+    pub packet_size: Option<i32>,
+    /// `TY_INT_PACKET_DELAY`, microseconds between packets.
+    pub packet_delay: Option<i32>,
+    /// `TY_BOOL_GVSP_RESEND`.
+    pub gvsp_resend: Option<bool>,
+    /// `TY_INT_ACCEPTABLE_PERCENT`, share of an image that must arrive for it to be delivered.
+    pub acceptable_percent: Option<i32>,
+    /// `TY_INT_LINK_CMD_TIMEOUT`, milliseconds.
+    pub link_cmd_timeout: Option<i32>,
+    /// `TY_BOOL_KEEP_ALIVE_ONOFF`.
+    pub keep_alive: Option<bool>,
+    /// `TY_INT_KEEP_ALIVE_TIMEOUT`, milliseconds.
+    pub keep_alive_timeout: Option<i32>,
+}
+
+impl NetworkTuning {
+    pub fn read(dev: &DeviceHandle) -> Result<Self> {
+        use TY_FEATURE_ID_LIST::*;
+        Ok(NetworkTuning {
+            packet_size: dev.read_opt(DEVICE, TY_INT_PACKET_SIZE, DeviceHandle::get_int)?,
+            packet_delay: dev.read_opt(DEVICE, TY_INT_PACKET_DELAY, DeviceHandle::get_int)?,
+            gvsp_resend: dev.read_opt(DEVICE, TY_BOOL_GVSP_RESEND, DeviceHandle::get_bool)?,
+            acceptable_percent: dev.read_opt(DEVICE, TY_INT_ACCEPTABLE_PERCENT, DeviceHandle::get_int)?,
+            link_cmd_timeout: dev.read_opt(DEVICE, TY_INT_LINK_CMD_TIMEOUT, DeviceHandle::get_int)?,
+            keep_alive: dev.read_opt(DEVICE, TY_BOOL_KEEP_ALIVE_ONOFF, DeviceHandle::get_bool)?,
+            keep_alive_timeout: dev.read_opt(DEVICE, TY_INT_KEEP_ALIVE_TIMEOUT, DeviceHandle::get_int)?,
+        })
+    }
+
+    /// Write the `Some` fields. Integers outside the feature's range fail with [`ErrorCode::OutOfRange`].
+    pub fn apply(&self, dev: &DeviceHandle) -> Result<()> {
+        use TY_FEATURE_ID_LIST::*;
+        let ints = [
+            (TY_INT_PACKET_SIZE, self.packet_size),
+            (TY_INT_PACKET_DELAY, self.packet_delay),
+            (TY_INT_ACCEPTABLE_PERCENT, self.acceptable_percent),
+            (TY_INT_LINK_CMD_TIMEOUT, self.link_cmd_timeout),
+        ];
+        for (feat, value) in ints {
+            if let Some(value) = value {
+                dev.set_int_checked(DEVICE, feat, value)?;
+            }
+        }
+        if let Some(on) = self.gvsp_resend {
+            dev.set_bool(DEVICE, TY_BOOL_GVSP_RESEND, on)?;
+        }
+        // The timeout is set first so the device is never kept alive with a stale one.
+        if let Some(timeout) = self.keep_alive_timeout {
+            dev.set_int_checked(DEVICE, TY_INT_KEEP_ALIVE_TIMEOUT, timeout)?;
+        }
+        if let Some(on) = self.keep_alive {
+            dev.set_bool(DEVICE, TY_BOOL_KEEP_ALIVE_ONOFF, on)?;
+        }
+        Ok(())
+    }
+}
+
+/// Candidates and measurement length of [`auto_tune`].
+#[derive(Debug, Clone)]
+pub struct AutoTuneConfig {
+    /// Packet sizes to try; clamped to the device's range.
+    pub packet_sizes: Vec<i32>,
+    /// Packet delays to try, in microseconds; clamped to the device's range.
+    pub packet_delays: Vec<i32>,
+    pub frames_per_trial: usize,
+    pub fetch_timeout_ms: i32,
+}
+
+impl Default for AutoTuneConfig {
+    fn default() -> Self {
+        AutoTuneConfig {
+            packet_sizes: vec![1500, 4000, 8000],
+            packet_delays: vec![0, 20, 100, 500],
+            frames_per_trial: 30,
+            fetch_timeout_ms: 1000,
+        }
+    }
+}
+
+/// Measurement of one packet size / delay combination.
+#[derive(Debug, Clone, PartialEq)]
+pub struct TuneTrial {
+    pub packet_size: i32,
+    pub packet_delay: i32,
+    pub packets_received: u64,
+    pub packets_lost: u64,
+    pub frames: usize,
+    pub timeouts: usize,
+    pub elapsed: Duration,
+}
+
+impl TuneTrial {
+    pub fn loss_ratio(&self) -> f64 {
+        let total = self.packets_received + self.packets_lost;
+        if total == 0 { 0.0 } else { self.packets_lost as f64 / total as f64 }
+    }
+
+    pub fn frames_per_sec(&self) -> f64 {
+        let secs = self.elapsed.as_secs_f64();
+        if secs > 0.0 { self.frames as f64 / secs } else { 0.0 }
+    }
+}
+
+/// Trials without frames last and trials with timeouts after those without, then lowest loss, then fewest timeouts,
+/// then highest frame rate, then shortest delay.
+///
+/// A trial that timed out counted fewer packets, so a low loss ratio alone does not make it better.
+fn compare_trials(a: &TuneTrial, b: &TuneTrial) -> Ordering {
+    let stalled = |t: &TuneTrial| (t.frames == 0, t.timeouts > 0);
+    stalled(a).cmp(&stalled(b))
+        .then(a.loss_ratio().total_cmp(&b.loss_ratio()))
+        .then(a.timeouts.cmp(&b.timeouts))
+        .then(b.frames_per_sec().total_cmp(&a.frames_per_sec()))
+        .then(a.packet_delay.cmp(&b.packet_delay))
+}
+
+fn statistics(dev: &DeviceHandle) -> Result<TY_CAMERA_STATISTICS> {
+    dev.get_struct(DEVICE, TY_FEATURE_ID_LIST::TY_STRUCT_CAM_STATISTICS)
+}
+
+fn candidates(range: IntRange, values: &[i32]) -> Vec<i32> {
+    let mut out: Vec<_> = values.iter().map(|v| range.clamp(*v)).collect();
+    out.sort_unstable();
+    out.dedup();
+    out
+}
+
+/// Try every packet size and delay while capturing, and apply the combination with the least packet loss.
+///
+/// Packet size and delay are changed with capture stopped; afterwards the session is capturing again only if it
+/// was before. Returns the applied setting and all trials. On failure the original packet size and delay are
+/// restored.
+pub fn auto_tune(session: &mut CaptureSession, config: &AutoTuneConfig) -> Result<(NetworkTuning, Vec<TuneTrial>)> {
+    use TY_FEATURE_ID_LIST::*;
+    let dev = session.device().clone();
+    let original = NetworkTuning {
+        packet_size: Some(dev.get_int(DEVICE, TY_INT_PACKET_SIZE)?),
+        packet_delay: Some(dev.get_int(DEVICE, TY_INT_PACKET_DELAY)?),
+        ..Default::default()
+    };
+    let was_capturing = session.is_capturing();
+
+    match run_trials(session, config) {
+        Ok(tuned) => {
+            if was_capturing {
+                session.start()?;
+            }
+            Ok(tuned)
+        }
+        Err(e) => {
+            if let Err(e) = restore(session, &original, was_capturing) {
+                log::warn!("restoring {original:?} after failed auto-tune failed: {e}");
+            }
+            Err(e)
+        }
+    }
+}
+
+fn restore(session: &mut CaptureSession, original: &NetworkTuning, capturing: bool) -> Result<()> {
+    if session.is_capturing() {
+        session.stop()?;
+    }
+    original.apply(session.device())?;
+    if capturing {
+        session.start()?;
+    }
+    Ok(())
+}
+
+fn run_trials(session: &mut CaptureSession, config: &AutoTuneConfig) -> Result<(NetworkTuning, Vec<TuneTrial>)> {
+    use TY_FEATURE_ID_LIST::*;
+    let dev = session.device().clone();
+    let sizes = candidates(dev.get_int_range(DEVICE, TY_INT_PACKET_SIZE)?, &config.packet_sizes);
+    let delays = candidates(dev.get_int_range(DEVICE, TY_INT_PACKET_DELAY)?, &config.packet_delays);
+
+    let mut trials = Vec::with_capacity(sizes.len() * delays.len());
+    for &packet_size in &sizes {
+        for &packet_delay in &delays {
+            if session.is_capturing() {
+                session.stop()?;
+            }
+            dev.set_int(DEVICE, TY_INT_PACKET_SIZE, packet_size)?;
+            dev.set_int(DEVICE, TY_INT_PACKET_DELAY, packet_delay)?;
+            session.start()?;
+
+            let before = statistics(&dev)?;
+            let start = Instant::now();
+            let (mut frames, mut timeouts) = (0, 0);
+            for _ in 0..config.frames_per_trial {
+                match session.fetch_frame(config.fetch_timeout_ms) {
+                    Ok(_) => frames += 1,
+                    Err(e) if e.errcode == ErrorCode::TIMEOUT => timeouts += 1,
+                    Err(e) => return Err(e),
+                }
+            }
+            let elapsed = start.elapsed();
+            let after = statistics(&dev)?;
+            let trial = TuneTrial {
+                packet_size,
+                packet_delay,
+                packets_received: after.packetReceived.saturating_sub(before.packetReceived),
+                packets_lost: after.packetLost.saturating_sub(before.packetLost),
+                frames,
+                timeouts,
+                elapsed,
+            };
+            log::debug!("auto-tune {trial:?}: loss {:.4}", trial.loss_ratio());
+            trials.push(trial);
+        }
+    }
+
+    let best = trials.iter().min_by(|a, b| compare_trials(a, b)).ok_or(ErrorCode::InvalidParameter)?;
+    let tuning = NetworkTuning {
+        packet_size: Some(best.packet_size),
+        packet_delay: Some(best.packet_delay),
+        ..Default::default()
+    };
+    if session.is_capturing() {
+        session.stop()?;
+    }
+    tuning.apply(&dev)?;
+    Ok((tuning, trials))
+}
+
+#[cfg(test)]
+mod tests {
+    use bytemuck::TransparentWrapper;
+    use super::*;
+
+    fn trial(size: i32, delay: i32, lost: u64, frames: usize) -> TuneTrial {
+        TuneTrial {
+            packet_size: size,
+            packet_delay: delay,
+            packets_received: 1000 - lost,
+            packets_lost: lost,
+            frames,
+            timeouts: 0,
+            elapsed: Duration::from_secs(1),
+        }
+    }
+
+    #[test]
+    fn test_best_trial() {
+        let trials = [trial(1500, 0, 10, 30), trial(8000, 100, 0, 25), trial(8000, 20, 0, 29), trial(4000, 0, 0, 29)];
+        let best = trials.iter().min_by(|a, b| compare_trials(a, b)).unwrap();
+        assert_eq!((best.packet_size, best.packet_delay), (4000, 0));
+        assert_eq!(trials[0].loss_ratio(), 0.01);
+
+        // A setting that only timed out counted no packets, it must not win on its zero loss ratio.
+        let dead = TuneTrial { packets_received: 0, packets_lost: 0, timeouts: 30, ..trial(9000, 0, 0, 0) };
+        assert_eq!(dead.loss_ratio(), 0.0);
+        let flaky = TuneTrial { timeouts: 2, ..trial(4000, 0, 0, 28) };
+        let trials = [dead, trial(1500, 0, 10, 30), flaky];
+        let best = trials.iter().min_by(|a, b| compare_trials(a, b)).unwrap();
+        assert_eq!((best.packet_size, best.packet_delay), (1500, 0));
+        let worst = trials.iter().max_by(|a, b| compare_trials(a, b)).unwrap();
+        assert_eq!(worst.packet_size, 9000);
+    }
+
+    #[test]
+    fn test_candidates() {
+        let range = IntRange::wrap(TY_INT_RANGE { min: 576, max: 7000, inc: 1, reserved: [0] });
+        assert_eq!(candidates(range, &[1500, 8000, 9000, 100]), [576, 1500, 7000]);
+    }
+}
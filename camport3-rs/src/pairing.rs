@@ -0,0 +1,240 @@
+use std::collections::VecDeque;
+use std::time::{Duration, Instant};
+use serde::{Deserialize, Serialize};
+use strum_macros::FromRepr;
+use camport3_sys::*;
+
+use crate::ffi::*;
+use crate::feature::DEVICE;
+use crate::capture::{CaptureSession, Frame, Image};
+use crate::group::{image_key, MatchKey};
+
+/// `TY_ENUM_STREAM_ASYNC`: which components are delivered in their own frames instead of together.
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Eq, Hash, FromRepr)]
+#[repr(u32)]
+pub enum StreamAsyncMode {
+    Off = TY_STREAM_ASYNC_MODE_LIST::TY_STREAM_ASYNC_OFF as u32,
+    Depth = TY_STREAM_ASYNC_MODE_LIST::TY_STREAM_ASYNC_DEPTH as u32,
+    Rgb = TY_STREAM_ASYNC_MODE_LIST::TY_STREAM_ASYNC_RGB as u32,
+    DepthRgb = TY_STREAM_ASYNC_MODE_LIST::TY_STREAM_ASYNC_DEPTH_RGB as u32,
+    All = TY_STREAM_ASYNC_MODE_LIST::TY_STREAM_ASYNC_ALL as u32,
+}
+
+impl DeviceHandle {
+    pub fn get_stream_async(&self) -> Result<StreamAsyncMode> {
+        let raw = self.get_enum(DEVICE, TY_FEATURE_ID_LIST::TY_ENUM_STREAM_ASYNC)?;
+        StreamAsyncMode::from_repr(raw).ok_or_else(|| ErrorCode::NotImplemented.into())
+    }
+
+    /// Must be set before capture starts.
+    pub fn set_stream_async(&self, mode: StreamAsyncMode) -> Result<()> {
+        self.set_enum(DEVICE, TY_FEATURE_ID_LIST::TY_ENUM_STREAM_ASYNC, mode as u32)
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct PairerConfig {
+    /// Component paired with depth: `TY_COMPONENT_RGB_CAM` for RGB-D, an IR camera for IR-depth.
+    pub partner: TY_DEVICE_COMPONENT_LIST,
+    pub match_key: MatchKey,
+    /// Largest key difference of a pair, in images or microseconds depending on `match_key`.
+    pub tolerance: u64,
+    /// Images waiting longer than this for their partner are dropped.
+    pub max_latency: Duration,
+}
+
+impl Default for PairerConfig {
+    fn default() -> Self {
+        PairerConfig {
+            partner: TY_DEVICE_COMPONENT_LIST::TY_COMPONENT_RGB_CAM,
+            match_key: MatchKey::Timestamp,
+            tolerance: 10_000,
+            max_latency: Duration::from_millis(500),
+        }
+    }
+}
+
+/// A depth image and the partner image matched with it.
+#[derive(Debug, Clone)]
+pub struct ImagePair {
+    pub depth: Image,
+    pub partner: Image,
+}
+
+#[derive(Debug)]
+struct Pending {
+    key: i64,
+    received: Instant,
+    image: Image,
+}
+
+/// Matches depth images with partner images delivered in separate frames, as with [`StreamAsyncMode`].
+///
+/// Images of each component must arrive in key order. An image is dropped once the other stream has moved past
+/// it by more than the tolerance, or after waiting `max_latency`.
+#[derive(Debug)]
+pub struct FramePairer {
+    config: PairerConfig,
+    depth: VecDeque<Pending>,
+    partner: VecDeque<Pending>,
+    ready: VecDeque<ImagePair>,
+    dropped: u64,
+}
+
+impl FramePairer {
+    pub fn new(config: PairerConfig) -> Self {
+        FramePairer {
+            config,
+            depth: VecDeque::new(),
+            partner: VecDeque::new(),
+            ready: VecDeque::new(),
+            dropped: 0,
+        }
+    }
+
+    pub fn config(&self) -> &PairerConfig {
+        &self.config
+    }
+
+    /// Images dropped without a partner so far.
+    pub fn dropped(&self) -> u64 {
+        self.dropped
+    }
+
+    /// Queue the depth and partner images of `frame`; other components are ignored.
+    pub fn push(&mut self, frame: Frame) {
+        self.push_at(frame.into_images(), Instant::now());
+    }
+
+    fn push_at(&mut self, images: Vec<Image>, now: Instant) {
+        for image in images {
+            let queue = if image.is_component(TY_DEVICE_COMPONENT_LIST::TY_COMPONENT_DEPTH_CAM) {
+                &mut self.depth
+            } else if image.is_component(self.config.partner) {
+                &mut self.partner
+            } else {
+                continue;
+            };
+            let key = image_key(&image, self.config.match_key);
+            queue.push_back(Pending { key, received: now, image });
+        }
+        // Expire first, so an image waiting longer than `max_latency` cannot pair with a partner arriving now.
+        self.expire(now);
+        self.pair();
+    }
+
+    fn pair(&mut self) {
+        let tolerance = i64::try_from(self.config.tolerance).unwrap_or(i64::MAX);
+        while let (Some(d), Some(p)) = (self.depth.front(), self.partner.front()) {
+            if d.key.abs_diff(p.key) <= tolerance as u64 {
+                let depth = self.depth.pop_front().unwrap().image;
+                let partner = self.partner.pop_front().unwrap().image;
+                self.ready.push_back(ImagePair { depth, partner });
+            } else if d.key < p.key {
+                // Later partners only have larger keys, so this depth image can no longer be matched.
+                self.depth.pop_front();
+                self.dropped += 1;
+            } else {
+                self.partner.pop_front();
+                self.dropped += 1;
+            }
+        }
+    }
+
+    fn expire(&mut self, now: Instant) {
+        let max_latency = self.config.max_latency;
+        for queue in [&mut self.depth, &mut self.partner] {
+            while queue.front().is_some_and(|p| now.duration_since(p.received) > max_latency) {
+                queue.pop_front();
+                self.dropped += 1;
+            }
+        }
+    }
+
+    /// Next matched pair, if any.
+    pub fn pop(&mut self) -> Option<ImagePair> {
+        self.ready.pop_front()
+    }
+
+    /// Fetch frames from `session` until a pair is matched. Each fetch waits at most `timeout_ms`.
+    pub fn fetch_pair(&mut self, session: &CaptureSession, timeout_ms: i32) -> Result<ImagePair> {
+        loop {
+            if let Some(pair) = self.pop() {
+                return Ok(pair);
+            }
+            self.push(session.fetch_frame(timeout_ms)?);
+        }
+    }
+
+    /// Drop everything queued, e.g. after restarting capture.
+    pub fn clear(&mut self) {
+        self.depth.clear();
+        self.partner.clear();
+        self.ready.clear();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use TY_DEVICE_COMPONENT_LIST::*;
+
+    fn pairer(match_key: MatchKey, tolerance: u64) -> FramePairer {
+        FramePairer::new(PairerConfig { match_key, tolerance, ..Default::default() })
+    }
+
+    fn pairs(p: &mut FramePairer) -> Vec<(u64, u64)> {
+        std::iter::from_fn(|| p.pop()).map(|pair| (pair.depth.timestamp, pair.partner.timestamp)).collect()
+    }
+
+    #[test]
+    fn test_pair_by_timestamp() {
+        let now = Instant::now();
+        let mut p = pairer(MatchKey::Timestamp, 5);
+        let depth = |ts| Image::from_parts(TY_COMPONENT_DEPTH_CAM, 0, ts);
+        let rgb = |ts| Image::from_parts(TY_DEVICE_COMPONENT_LIST::TY_COMPONENT_RGB_CAM, 0, ts);
+
+        p.push_at(vec![depth(100), depth(200)], now);
+        p.push_at(vec![Image::from_parts(TY_COMPONENT_IR_CAM_LEFT, 0, 100)], now);
+        assert!(pairs(&mut p).is_empty());
+        p.push_at(vec![rgb(103)], now);
+        assert_eq!(pairs(&mut p), [(100, 103)]);
+        // rgb 150 has no depth within 5 us; depth 200 is still waiting.
+        p.push_at(vec![rgb(150), rgb(198)], now);
+        assert_eq!(pairs(&mut p), [(200, 198)]);
+        assert_eq!(p.dropped(), 1);
+        // Synchronous frames carry both images.
+        p.push_at(vec![depth(300), rgb(300)], now);
+        assert_eq!(pairs(&mut p), [(300, 300)]);
+    }
+
+    #[test]
+    fn test_pair_by_index_and_latency() {
+        let now = Instant::now();
+        let mut p = pairer(MatchKey::ImageIndex, 0);
+        let depth = |i| Image::from_parts(TY_COMPONENT_DEPTH_CAM, i, i as u64);
+        let rgb = |i| Image::from_parts(TY_DEVICE_COMPONENT_LIST::TY_COMPONENT_RGB_CAM, i, 1000 + i as u64);
+
+        p.push_at(vec![depth(1), depth(2), depth(3)], now);
+        p.push_at(vec![rgb(2)], now);
+        assert_eq!(pairs(&mut p), [(2, 1002)]);
+        assert_eq!(p.dropped(), 1);
+
+        // Depth 3 waits past max_latency and is dropped, so rgb 3 finds nothing.
+        p.push_at(vec![], now + Duration::from_secs(1));
+        assert_eq!(p.dropped(), 2);
+        p.push_at(vec![rgb(3)], now + Duration::from_secs(1));
+        assert!(pairs(&mut p).is_empty());
+    }
+
+    #[test]
+    fn test_late_partner_in_same_push() {
+        let now = Instant::now();
+        let mut p = pairer(MatchKey::ImageIndex, 0);
+        p.push_at(vec![Image::from_parts(TY_COMPONENT_DEPTH_CAM, 7, 0)], now);
+        // The partner arrives after max_latency: the waiting depth image expires instead of pairing.
+        p.push_at(vec![Image::from_parts(TY_DEVICE_COMPONENT_LIST::TY_COMPONENT_RGB_CAM, 7, 0)], now + Duration::from_secs(1));
+        assert!(pairs(&mut p).is_empty());
+        assert_eq!(p.dropped(), 1);
+    }
+}
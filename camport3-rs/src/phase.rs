@@ -0,0 +1,268 @@
+use serde::{Deserialize, Serialize};
+use camport3_sys::*;
+
+use crate::ffi::*;
+use crate::feature::DEPTH;
+
+/// Phase of a `TY_STRUCT_PHC_GROUP_ATTR` group.
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Eq, Hash)]
+#[serde(rename_all = "snake_case")]
+#[repr(u8)]
+pub enum PhaseType {
+    Normal = TY_NORMAL_PHASE_TYPE as u8,
+    Reference = TY_REFER_PHASE_TYPE as u8,
+}
+
+impl PhaseType {
+    fn from_raw(raw: u8) -> Option<Self> {
+        [PhaseType::Normal, PhaseType::Reference].into_iter().find(|t| *t as u8 == raw)
+    }
+}
+
+/// One phase compute group of `TY_STRUCT_PHC_GROUP_ATTR`.
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Eq)]
+pub struct PhaseGroup {
+    pub phase_type: PhaseType,
+    /// Minimum modulation amplitude for a pixel to be decoded.
+    pub amp_thresh: u8,
+    pub channel: u16,
+    pub channel_type: u8,
+}
+
+/// Maximum number of [`PhaseGroup`]s a device holds.
+pub const PHASE_GROUP_COUNT: usize = 16;
+
+/// Phase-shift structured light parameters of the depth component, the `TY_*_SGPM_*` features and
+/// `TY_STRUCT_PHC_GROUP_ATTR`.
+///
+/// `None` fields are absent on read and left alone on apply.
+#[derive(Serialize, Deserialize, Debug, Clone, Default, PartialEq)]
+#[serde(default)]
+pub struct PhaseConfig {
+    /// `TY_INT_SGPM_PHASE_NUM`, phases used per depth frame.
+    pub phase_num: Option<i32>,
+    /// `TY_INT_SGPM_NORMAL_PHASE_SCALE`.
+    pub normal_phase_scale: Option<i32>,
+    /// `TY_INT_SGPM_NORMAL_PHASE_OFFSET`.
+    pub normal_phase_offset: Option<i32>,
+    /// `TY_INT_SGPM_REF_PHASE_SCALE`.
+    pub ref_phase_scale: Option<i32>,
+    /// `TY_INT_SGPM_REF_PHASE_OFFSET`.
+    pub ref_phase_offset: Option<i32>,
+    /// `TY_FLOAT_SGPM_EPI_HS`, epipolar constraint pattern scale.
+    pub epi_hs: Option<f32>,
+    /// `TY_INT_SGPM_EPI_HF`, epipolar constraint pattern offset.
+    pub epi_hf: Option<i32>,
+    /// `TY_BOOL_SGPM_EPI_EN`.
+    pub epi_enable: Option<bool>,
+    /// `TY_INT_SGPM_EPI_CH0`.
+    pub epi_ch0: Option<i32>,
+    /// `TY_INT_SGPM_EPI_CH1`.
+    pub epi_ch1: Option<i32>,
+    /// `TY_INT_SGPM_EPI_THRESH`.
+    pub epi_thresh: Option<i32>,
+    /// `TY_BOOL_SGPM_ORDER_FILTER_EN`.
+    pub order_filter_enable: Option<bool>,
+    /// `TY_INT_SGPM_ORDER_FILTER_CHN`.
+    pub order_filter_channel: Option<i32>,
+    /// `TY_STRUCT_PHC_GROUP_ATTR`, at most [`PHASE_GROUP_COUNT`] groups.
+    pub groups: Option<Vec<PhaseGroup>>,
+}
+
+fn groups_from_raw(raw: &TY_PHC_GROUP_ATTR) -> Option<Vec<PhaseGroup>> {
+    let attrs = raw.phc_attr;
+    let size = (raw.size as usize).min(PHASE_GROUP_COUNT);
+    attrs[..size].iter().map(|a| Some(PhaseGroup {
+        phase_type: PhaseType::from_raw(a.type_)?,
+        amp_thresh: a.amp_thresh,
+        channel: a.ch,
+        channel_type: a.chn_type,
+    })).collect()
+}
+
+fn groups_to_raw(groups: &[PhaseGroup]) -> Result<TY_PHC_GROUP_ATTR> {
+    if groups.len() > PHASE_GROUP_COUNT {
+        return Err(ErrorCode::WrongSize.into());
+    }
+    let empty = TY_PHC_GROUP_ATTR_phc_group_attr { type_: 0, amp_thresh: 0, ch: 0, chn_type: 0, rsvd: [0; 27] };
+    let mut raw = TY_PHC_GROUP_ATTR { offset: 0, size: groups.len() as u32, phc_attr: [empty; PHASE_GROUP_COUNT] };
+    for (attr, g) in raw.phc_attr.iter_mut().zip(groups) {
+        *attr = TY_PHC_GROUP_ATTR_phc_group_attr {
+            type_: g.phase_type as u8,
+            amp_thresh: g.amp_thresh,
+            ch: g.channel,
+            chn_type: g.channel_type,
+            rsvd: [0; 27],
+        };
+    }
+    Ok(raw)
+}
+
+impl PhaseConfig {
+    fn ints(&self) -> [(TY_FEATURE_ID_LIST, Option<i32>); 10] {
+        use TY_FEATURE_ID_LIST::*;
+        [
+            (TY_INT_SGPM_PHASE_NUM, self.phase_num),
+            (TY_INT_SGPM_NORMAL_PHASE_SCALE, self.normal_phase_scale),
+            (TY_INT_SGPM_NORMAL_PHASE_OFFSET, self.normal_phase_offset),
+            (TY_INT_SGPM_REF_PHASE_SCALE, self.ref_phase_scale),
+            (TY_INT_SGPM_REF_PHASE_OFFSET, self.ref_phase_offset),
+            (TY_INT_SGPM_EPI_HF, self.epi_hf),
+            (TY_INT_SGPM_EPI_CH0, self.epi_ch0),
+            (TY_INT_SGPM_EPI_CH1, self.epi_ch1),
+            (TY_INT_SGPM_EPI_THRESH, self.epi_thresh),
+            (TY_INT_SGPM_ORDER_FILTER_CHN, self.order_filter_channel),
+        ]
+    }
+
+    fn bools(&self) -> [(TY_FEATURE_ID_LIST, Option<bool>); 2] {
+        use TY_FEATURE_ID_LIST::*;
+        [(TY_BOOL_SGPM_EPI_EN, self.epi_enable), (TY_BOOL_SGPM_ORDER_FILTER_EN, self.order_filter_enable)]
+    }
+
+    /// `self` with only the fields that are set in `mask`.
+    fn masked(&self, mask: &PhaseConfig) -> PhaseConfig {
+        macro_rules! pick {
+            ($($f:ident),*) => { PhaseConfig { $($f: mask.$f.as_ref().and(self.$f.clone())),* } };
+        }
+        pick!(phase_num, normal_phase_scale, normal_phase_offset, ref_phase_scale, ref_phase_offset, epi_hs, epi_hf,
+              epi_enable, epi_ch0, epi_ch1, epi_thresh, order_filter_enable, order_filter_channel, groups)
+    }
+
+    pub fn read(dev: &DeviceHandle) -> Result<Self> {
+        let int = |feat| dev.read_opt(DEPTH, feat, DeviceHandle::get_int);
+        let bool_ = |feat| dev.read_opt(DEPTH, feat, DeviceHandle::get_bool);
+        use TY_FEATURE_ID_LIST::*;
+        let epi_hs = dev.read_opt(DEPTH, TY_FLOAT_SGPM_EPI_HS, DeviceHandle::get_float)?;
+        let groups = dev.read_opt(DEPTH, TY_STRUCT_PHC_GROUP_ATTR, |dev, comp, feat| {
+            let mut raw = groups_to_raw(&[])?;
+            raw.size = PHASE_GROUP_COUNT as u32;
+            dev.read_struct(comp, feat, &mut raw)?;
+            groups_from_raw(&raw).ok_or_else(|| ErrorCode::NotImplemented.into())
+        })?;
+        Ok(PhaseConfig {
+            phase_num: int(TY_INT_SGPM_PHASE_NUM)?,
+            normal_phase_scale: int(TY_INT_SGPM_NORMAL_PHASE_SCALE)?,
+            normal_phase_offset: int(TY_INT_SGPM_NORMAL_PHASE_OFFSET)?,
+            ref_phase_scale: int(TY_INT_SGPM_REF_PHASE_SCALE)?,
+            ref_phase_offset: int(TY_INT_SGPM_REF_PHASE_OFFSET)?,
+            epi_hs,
+            epi_hf: int(TY_INT_SGPM_EPI_HF)?,
+            epi_enable: bool_(TY_BOOL_SGPM_EPI_EN)?,
+            epi_ch0: int(TY_INT_SGPM_EPI_CH0)?,
+            epi_ch1: int(TY_INT_SGPM_EPI_CH1)?,
+            epi_thresh: int(TY_INT_SGPM_EPI_THRESH)?,
+            order_filter_enable: bool_(TY_BOOL_SGPM_ORDER_FILTER_EN)?,
+            order_filter_channel: int(TY_INT_SGPM_ORDER_FILTER_CHN)?,
+            groups,
+        })
+    }
+
+    /// Check the `Some` fields against the device without writing anything.
+    ///
+    /// Features the device lacks fail with [`ErrorCode::NotImplemented`], numbers outside their range with
+    /// [`ErrorCode::OutOfRange`] and more than [`PHASE_GROUP_COUNT`] groups with [`ErrorCode::WrongSize`].
+    pub fn validate(&self, dev: &DeviceHandle) -> Result<()> {
+        use TY_FEATURE_ID_LIST::*;
+        let require = |feat| -> Result<()> {
+            if dev.has_feature(DEPTH, feat)? { Ok(()) } else { Err(ErrorCode::NotImplemented.into()) }
+        };
+        for (feat, _) in self.bools().into_iter().filter(|(_, v)| v.is_some()) {
+            require(feat)?;
+        }
+        for (feat, value) in self.ints() {
+            let Some(value) = value else { continue };
+            require(feat)?;
+            if !dev.get_int_range(DEPTH, feat)?.contains(value) {
+                return Err(ErrorCode::OutOfRange.into());
+            }
+        }
+        if let Some(hs) = self.epi_hs {
+            require(TY_FLOAT_SGPM_EPI_HS)?;
+            if !dev.get_float_range(DEPTH, TY_FLOAT_SGPM_EPI_HS)?.contains(hs) {
+                return Err(ErrorCode::OutOfRange.into());
+            }
+        }
+        if let Some(groups) = &self.groups {
+            require(TY_STRUCT_PHC_GROUP_ATTR)?;
+            groups_to_raw(groups)?;
+        }
+        Ok(())
+    }
+
+    fn write(&self, dev: &DeviceHandle) -> Result<()> {
+        use TY_FEATURE_ID_LIST::*;
+        // The groups and phase count go first, the scales and filters are interpreted against them.
+        if let Some(groups) = &self.groups {
+            dev.set_struct(DEPTH, TY_STRUCT_PHC_GROUP_ATTR, &groups_to_raw(groups)?)?;
+        }
+        for (feat, value) in self.ints() {
+            if let Some(value) = value {
+                dev.set_int(DEPTH, feat, value)?;
+            }
+        }
+        if let Some(hs) = self.epi_hs {
+            dev.set_float(DEPTH, TY_FLOAT_SGPM_EPI_HS, hs)?;
+        }
+        for (feat, on) in self.bools() {
+            if let Some(on) = on {
+                dev.set_bool(DEPTH, feat, on)?;
+            }
+        }
+        Ok(())
+    }
+
+    /// [`validate`](Self::validate), then write the `Some` fields.
+    ///
+    /// If a write fails part way, the fields are restored to the values read before applying, so the device is left
+    /// either fully configured or as it was. A failed restore is logged; the write error is returned.
+    pub fn apply(&self, dev: &DeviceHandle) -> Result<()> {
+        self.validate(dev)?;
+        let previous = PhaseConfig::read(dev)?.masked(self);
+        self.write(dev).inspect_err(|e| {
+            if let Err(rollback) = previous.write(dev) {
+                log::warn!("restoring phase config after failed apply ({e}) failed, device left partly configured: {rollback}");
+            }
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_groups_raw() {
+        let groups = vec![
+            PhaseGroup { phase_type: PhaseType::Normal, amp_thresh: 10, channel: 0, channel_type: 1 },
+            PhaseGroup { phase_type: PhaseType::Reference, amp_thresh: 20, channel: 3, channel_type: 0 },
+        ];
+        let raw = groups_to_raw(&groups).unwrap();
+        assert_eq!(({ raw.offset }, { raw.size }), (0, 2));
+        assert_eq!(groups_from_raw(&raw), Some(groups));
+
+        let mut bad = raw;
+        bad.phc_attr[1].type_ = 7;
+        assert_eq!(groups_from_raw(&bad), None);
+
+        let too_many = vec![PhaseGroup { phase_type: PhaseType::Normal, amp_thresh: 0, channel: 0, channel_type: 0 }; 17];
+        assert_eq!(groups_to_raw(&too_many).unwrap_err().errcode, ErrorCode::WrongSize);
+    }
+
+    #[test]
+    fn test_masked_and_serde() {
+        let current = PhaseConfig {
+            phase_num: Some(4),
+            epi_hs: Some(1.5),
+            epi_enable: Some(false),
+            groups: Some(vec![]),
+            ..Default::default()
+        };
+        let request: PhaseConfig = serde_yaml::from_str("phase_num: 8\nepi_hs: 2.0\n").unwrap();
+        let restore = current.masked(&request);
+        assert_eq!(restore, PhaseConfig { phase_num: Some(4), epi_hs: Some(1.5), ..Default::default() });
+
+        let yaml = serde_yaml::to_string(&current).unwrap();
+        assert_eq!(serde_yaml::from_str::<PhaseConfig>(&yaml).unwrap(), current);
+    }
+}
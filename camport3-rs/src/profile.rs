@@ -0,0 +1,467 @@
+use std::collections::BTreeMap;
+use serde::{Deserialize, Serialize};
+use camport3_sys::*;
+
+use crate::ffi::*;
+use crate::feature::*;
+
+/// Value of one feature, tagged with its type.
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+#[serde(rename_all = "snake_case")]
+pub enum FeatureValue {
+    Int(i32),
+    Float(f32),
+    Enum(u32),
+    Bool(bool),
+    String(String),
+    ByteArray(Vec<u8>),
+    /// Raw bytes of the feature's C struct.
+    Struct(Vec<u8>),
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+pub struct FeatureSetting {
+    /// Name reported by the SDK, informational only.
+    pub name: String,
+    pub value: FeatureValue,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, Default, PartialEq)]
+pub struct ComponentProfile {
+    pub enabled: bool,
+    pub features: BTreeMap<TY_FEATURE_ID, FeatureSetting>,
+}
+
+/// Values of all readable features of a device, by component and feature ID.
+#[derive(Serialize, Deserialize, Debug, Clone, Default, PartialEq)]
+pub struct Profile {
+    /// Model of the snapshotted device, informational only.
+    pub model: String,
+    pub components: BTreeMap<TY_COMPONENT_ID, ComponentProfile>,
+}
+
+/// One difference between two profiles, see [`Profile::diff`].
+#[derive(Debug, Clone, PartialEq)]
+pub enum ProfileDiff {
+    Enabled {
+        component: TY_COMPONENT_ID,
+        old: Option<bool>,
+        new: Option<bool>,
+    },
+    Feature {
+        component: TY_COMPONENT_ID,
+        feature: TY_FEATURE_ID,
+        name: String,
+        old: Option<FeatureValue>,
+        new: Option<FeatureValue>,
+    },
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SkipReason {
+    /// Missing or read-only on the target device.
+    NotWritable,
+    /// Cannot be written while capturing.
+    NotWritableAtRun,
+    /// Overridden by an automatic mode the profile enables, e.g. `TY_BOOL_AUTO_EXPOSURE`.
+    AutoControlled,
+}
+
+#[derive(Debug, Clone)]
+pub struct SkippedFeature {
+    pub component: TY_COMPONENT_ID,
+    pub feature: TY_FEATURE_ID,
+    pub name: String,
+    pub reason: SkipReason,
+}
+
+#[derive(Debug, Clone)]
+pub struct FailedFeature {
+    pub component: TY_COMPONENT_ID,
+    /// `None` when enabling or disabling the component failed.
+    pub feature: Option<TY_FEATURE_ID>,
+    pub name: String,
+    pub error: DeviceError,
+}
+
+/// Outcome of [`Profile::apply`].
+#[derive(Debug, Clone, Default)]
+pub struct ApplyReport {
+    pub applied: usize,
+    pub skipped: Vec<SkippedFeature>,
+    pub failed: Vec<FailedFeature>,
+}
+
+impl ApplyReport {
+    pub fn is_ok(&self) -> bool {
+        self.failed.is_empty()
+    }
+}
+
+/// Manual values ignored by the device while the automatic mode is on.
+const AUTO_CONTROLLED: &[(TY_FEATURE_ID_LIST, &[TY_FEATURE_ID_LIST])] = {
+    use TY_FEATURE_ID_LIST::*;
+    &[
+        (TY_BOOL_AUTO_EXPOSURE, &[TY_INT_EXPOSURE_TIME]),
+        (TY_BOOL_AUTO_GAIN, &[TY_INT_GAIN, TY_INT_ANALOG_GAIN]),
+        (TY_BOOL_AUTO_AWB, &[TY_INT_R_GAIN, TY_INT_G_GAIN, TY_INT_B_GAIN]),
+    ]
+};
+
+/// Size of the C struct of struct features that can be snapshotted.
+///
+/// The `*_BY_IDX` features are left out, their struct selects what is read.
+fn struct_size(feat: TY_FEATURE_ID) -> Option<usize> {
+    use std::mem::size_of;
+    use TY_FEATURE_ID_LIST::*;
+    let sizes = [
+        (TY_STRUCT_CAM_INTRINSIC, size_of::<TY_CAMERA_INTRINSIC>()),
+        (TY_STRUCT_EXTRINSIC_TO_DEPTH, size_of::<TY_CAMERA_EXTRINSIC>()),
+        (TY_STRUCT_EXTRINSIC_TO_IR_LEFT, size_of::<TY_CAMERA_EXTRINSIC>()),
+        (TY_STRUCT_CAM_DISTORTION, size_of::<TY_CAMERA_DISTORTION>()),
+        (TY_STRUCT_CAM_CALIB_DATA, size_of::<TY_CAMERA_CALIB_INFO>()),
+        (TY_STRUCT_CAM_RECTIFIED_INTRI, size_of::<TY_CAMERA_INTRINSIC>()),
+        (TY_STRUCT_TRIGGER_PARAM, size_of::<TY_TRIGGER_PARAM>()),
+        (TY_STRUCT_TRIGGER_PARAM_EX, size_of::<TY_TRIGGER_PARAM_EX>()),
+        (TY_STRUCT_TRIGGER_TIMER_LIST, size_of::<TY_TRIGGER_TIMER_LIST>()),
+        (TY_STRUCT_TRIGGER_TIMER_PERIOD, size_of::<TY_TRIGGER_TIMER_PERIOD>()),
+        (TY_STRUCT_DO0_WORKMODE, size_of::<TY_DO_WORKMODE>()),
+        (TY_STRUCT_DO1_WORKMODE, size_of::<TY_DO_WORKMODE>()),
+        (TY_STRUCT_DO2_WORKMODE, size_of::<TY_DO_WORKMODE>()),
+        (TY_STRUCT_DI0_WORKMODE, size_of::<TY_DI_WORKMODE>()),
+        (TY_STRUCT_DI1_WORKMODE, size_of::<TY_DI_WORKMODE>()),
+        (TY_STRUCT_DI2_WORKMODE, size_of::<TY_DI_WORKMODE>()),
+        (TY_STRUCT_AEC_ROI, size_of::<TY_AEC_ROI_PARAM>()),
+        (TY_STRUCT_LASER_PATTERN, size_of::<TY_LASER_PATTERN_PARAM>()),
+        (TY_STRUCT_PHC_GROUP_ATTR, size_of::<TY_PHC_GROUP_ATTR>()),
+        (TY_STRUCT_TOF_FREQ, size_of::<TY_TOF_FREQ>()),
+    ];
+    sizes.into_iter().find(|(id, _)| id.feature_id() == feat).map(|(_, size)| size)
+}
+
+fn read_value(dev: &DeviceHandle, comp: TY_COMPONENT_ID, feat: TY_FEATURE_ID) -> Option<Result<FeatureValue>> {
+    use TY_FEATURE_TYPE_LIST::*;
+    let t = feat.feature_type();
+    let value = if t == TY_FEATURE_INT as u32 {
+        dev.get_int(comp, feat).map(FeatureValue::Int)
+    } else if t == TY_FEATURE_FLOAT as u32 {
+        dev.get_float(comp, feat).map(FeatureValue::Float)
+    } else if t == TY_FEATURE_ENUM as u32 {
+        dev.get_enum(comp, feat).map(FeatureValue::Enum)
+    } else if t == TY_FEATURE_BOOL as u32 {
+        dev.get_bool(comp, feat).map(FeatureValue::Bool)
+    } else if t == TY_FEATURE_STRING as u32 {
+        dev.get_string(comp, feat).map(FeatureValue::String)
+    } else if t == TY_FEATURE_BYTEARRAY as u32 {
+        dev.get_byte_array(comp, feat).map(FeatureValue::ByteArray)
+    } else if t == TY_FEATURE_STRUCT as u32 {
+        let mut buf = vec![0u8; struct_size(feat)?];
+        ty_get_struct_bytes(dev, comp, feat, &mut buf).map(|_| FeatureValue::Struct(buf))
+    } else {
+        return None;
+    };
+    Some(value)
+}
+
+fn write_value(dev: &DeviceHandle, comp: TY_COMPONENT_ID, feat: TY_FEATURE_ID, value: &FeatureValue) -> Result<()> {
+    match value {
+        FeatureValue::Int(v) => dev.set_int(comp, feat, *v),
+        FeatureValue::Float(v) => dev.set_float(comp, feat, *v),
+        FeatureValue::Enum(v) => dev.set_enum(comp, feat, *v),
+        FeatureValue::Bool(v) => dev.set_bool(comp, feat, *v),
+        FeatureValue::String(v) => dev.set_string(comp, feat, v),
+        FeatureValue::ByteArray(v) => dev.set_byte_array(comp, feat, v),
+        FeatureValue::Struct(v) => ty_set_struct_bytes(dev, comp, feat, v),
+    }
+}
+
+/// Mode-selecting enums and switches go first, so that e.g. `TY_BOOL_AUTO_EXPOSURE` is off before
+/// `TY_INT_EXPOSURE_TIME` is written. Bindings reported by the device override this, see [`Profile::apply_order`].
+fn apply_rank(feat: TY_FEATURE_ID) -> u8 {
+    use TY_FEATURE_TYPE_LIST::*;
+    match feat.feature_type() {
+        t if t == TY_FEATURE_ENUM as u32 => 0,
+        t if t == TY_FEATURE_BOOL as u32 => 1,
+        t if t == TY_FEATURE_INT as u32 || t == TY_FEATURE_FLOAT as u32 => 2,
+        _ => 3,
+    }
+}
+
+fn component_bits(ids: TY_COMPONENT_ID) -> impl Iterator<Item = TY_COMPONENT_ID> {
+    (0..32).map(|bit| 1 << bit).filter(move |c| ids & c != 0)
+}
+
+impl Profile {
+    /// Read every readable feature of every component.
+    ///
+    /// Features that fail to read (some are only readable in certain modes) are left out and logged.
+    pub fn snapshot(dev: &DeviceHandle) -> Result<Self> {
+        let device = TY_DEVICE_COMPONENT_LIST::TY_COMPONENT_DEVICE.component_id();
+        let enabled = dev.get_enabled_components()? | device;
+        let mut profile = Profile {
+            model: dev.get_device_info()?.model_name().into_owned(),
+            components: BTreeMap::new(),
+        };
+
+        for comp in component_bits(dev.get_component_ids()? | device) {
+            let mut features = BTreeMap::new();
+            for info in dev.get_feature_list(comp)? {
+                if !info.is_valid() || !info.is_readable() {
+                    continue;
+                }
+                let feat = info.feature_id();
+                match read_value(dev, comp, feat) {
+                    Some(Ok(value)) => {
+                        features.insert(feat, FeatureSetting { name: info.name().into_owned(), value });
+                    }
+                    Some(Err(e)) => log::debug!("skipping {} of component {comp:#x}: {e}", info.name()),
+                    None => log::debug!("skipping {} of component {comp:#x}: unsupported type", info.name()),
+                }
+            }
+            profile.components.insert(comp, ComponentProfile { enabled: enabled & comp != 0, features });
+        }
+        Ok(profile)
+    }
+
+    /// Differences turning `self` into `other`.
+    pub fn diff(&self, other: &Profile) -> Vec<ProfileDiff> {
+        let mut out = Vec::new();
+        let empty = ComponentProfile::default();
+        let comps = self.components.keys().chain(other.components.keys().filter(|c| !self.components.contains_key(c)));
+        for &component in comps {
+            let old = self.components.get(&component);
+            let new = other.components.get(&component);
+            if old.map(|c| c.enabled) != new.map(|c| c.enabled) {
+                out.push(ProfileDiff::Enabled {
+                    component,
+                    old: old.map(|c| c.enabled),
+                    new: new.map(|c| c.enabled),
+                });
+            }
+
+            let (old, new) = (old.unwrap_or(&empty), new.unwrap_or(&empty));
+            let feats = old.features.keys().chain(new.features.keys().filter(|f| !old.features.contains_key(f)));
+            for &feature in feats {
+                let (a, b) = (old.features.get(&feature), new.features.get(&feature));
+                if a.map(|s| &s.value) != b.map(|s| &s.value) {
+                    out.push(ProfileDiff::Feature {
+                        component,
+                        feature,
+                        name: a.or(b).map(|s| s.name.clone()).unwrap_or_default(),
+                        old: a.map(|s| s.value.clone()),
+                        new: b.map(|s| s.value.clone()),
+                    });
+                }
+            }
+        }
+        out
+    }
+
+    /// Write the profile to a device, continuing past failures.
+    ///
+    /// Components are enabled and disabled as recorded unless `capturing`, in which case features that are not
+    /// `writableAtRun` are skipped too. Read-only features and manual values overridden by an enabled automatic
+    /// mode are skipped.
+    pub fn apply(&self, dev: &DeviceHandle, capturing: bool) -> Result<ApplyReport> {
+        let mut report = ApplyReport::default();
+        let device = TY_DEVICE_COMPONENT_LIST::TY_COMPONENT_DEVICE.component_id();
+
+        let current = dev.get_enabled_components()? | device;
+        for (&comp, cp) in self.components.iter().filter(|(c, _)| **c != device) {
+            if (current & comp != 0) == cp.enabled {
+                continue;
+            }
+            let res = if capturing {
+                Err(ErrorCode::Busy.into())
+            } else if cp.enabled {
+                dev.enable_components(comp)
+            } else {
+                dev.disable_components(comp)
+            };
+            if let Err(error) = res {
+                report.failed.push(FailedFeature { component: comp, feature: None, name: String::new(), error });
+            }
+        }
+
+        let infos: BTreeMap<_, _> = self.features()
+            .map(|(comp, feat, _)| ((comp, feat), dev.get_feature_info(comp, feat).ok()))
+            .collect();
+        let bound_to = |comp, feat| infos.get(&(comp, feat)).copied().flatten().and_then(|info| info.bound_to());
+        for (comp, feat, setting) in self.apply_order(bound_to) {
+            let skip = |reason| SkippedFeature { component: comp, feature: feat, name: setting.name.clone(), reason };
+            if self.auto_controlled(comp, feat) {
+                report.skipped.push(skip(SkipReason::AutoControlled));
+                continue;
+            }
+            match infos[&(comp, feat)] {
+                Some(info) if info.is_valid() && info.is_writable() => {
+                    if capturing && !info.writable_at_run() {
+                        report.skipped.push(skip(SkipReason::NotWritableAtRun));
+                        continue;
+                    }
+                }
+                _ => {
+                    report.skipped.push(skip(SkipReason::NotWritable));
+                    continue;
+                }
+            }
+            match write_value(dev, comp, feat, &setting.value) {
+                Ok(()) => report.applied += 1,
+                Err(error) => report.failed.push(FailedFeature {
+                    component: comp,
+                    feature: Some(feat),
+                    name: setting.name.clone(),
+                    error,
+                }),
+            }
+        }
+        Ok(report)
+    }
+
+    fn features(&self) -> impl Iterator<Item = (TY_COMPONENT_ID, TY_FEATURE_ID, &FeatureSetting)> {
+        self.components.iter().flat_map(|(&comp, cp)| cp.features.iter().map(move |(&feat, s)| (comp, feat, s)))
+    }
+
+    /// Features by [`apply_rank`], except that a feature bound to another one of the profile, as reported by
+    /// `bound_to`, is written after it.
+    fn apply_order(
+        &self, bound_to: impl Fn(TY_COMPONENT_ID, TY_FEATURE_ID) -> Option<(TY_COMPONENT_ID, TY_FEATURE_ID)>,
+    ) -> Vec<(TY_COMPONENT_ID, TY_FEATURE_ID, &FeatureSetting)> {
+        let mut ranked: Vec<_> = self.features().collect();
+        ranked.sort_by_key(|&(comp, feat, _)| (apply_rank(feat), comp, feat));
+        let index: BTreeMap<_, _> = ranked.iter().enumerate().map(|(i, &(comp, feat, _))| ((comp, feat), i)).collect();
+
+        let mut placed = vec![false; ranked.len()];
+        let mut out = Vec::with_capacity(ranked.len());
+        for i in 0..ranked.len() {
+            // Follow the bindings down to a placed or unbound feature, then place the chain from its end.
+            let mut chain = Vec::new();
+            let mut j = i;
+            while !placed[j] && !chain.contains(&j) {
+                chain.push(j);
+                let (comp, feat, _) = ranked[j];
+                match bound_to(comp, feat).and_then(|target| index.get(&target)) {
+                    Some(&target) => j = target,
+                    None => break,
+                }
+            }
+            for &j in chain.iter().rev() {
+                placed[j] = true;
+                out.push(ranked[j]);
+            }
+        }
+        out
+    }
+
+    fn auto_controlled(&self, comp: TY_COMPONENT_ID, feat: TY_FEATURE_ID) -> bool {
+        let Some(cp) = self.components.get(&comp) else {
+            return false;
+        };
+        AUTO_CONTROLLED.iter()
+            .filter(|(_, manual)| manual.iter().any(|m| m.feature_id() == feat))
+            .any(|(auto, _)| {
+                cp.features.get(&auto.feature_id()).map(|s| &s.value) == Some(&FeatureValue::Bool(true))
+            })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use TY_FEATURE_ID_LIST::*;
+
+    fn setting(name: &str, value: FeatureValue) -> FeatureSetting {
+        FeatureSetting { name: name.into(), value }
+    }
+
+    fn sample() -> Profile {
+        let rgb = TY_DEVICE_COMPONENT_LIST::TY_COMPONENT_RGB_CAM.component_id();
+        let features = BTreeMap::from([
+            (TY_INT_EXPOSURE_TIME.feature_id(), setting("ExposureTime", FeatureValue::Int(1000))),
+            (TY_BOOL_AUTO_EXPOSURE.feature_id(), setting("AutoExposure", FeatureValue::Bool(true))),
+            (TY_ENUM_IMAGE_MODE.feature_id(), setting("ImageMode", FeatureValue::Enum(0x10))),
+            (TY_STRUCT_AEC_ROI.feature_id(), setting("AecROI", FeatureValue::Struct(vec![0; 16]))),
+        ]);
+        Profile {
+            model: "FM851-E2".into(),
+            components: BTreeMap::from([(rgb, ComponentProfile { enabled: true, features })]),
+        }
+    }
+
+    #[test]
+    fn test_apply_order() {
+        let profile = sample();
+        let order: Vec<_> = profile.apply_order(|_, _| None).into_iter().map(|(_, f, _)| f).collect();
+        assert_eq!(order, [
+            TY_ENUM_IMAGE_MODE.feature_id(),
+            TY_BOOL_AUTO_EXPOSURE.feature_id(),
+            TY_INT_EXPOSURE_TIME.feature_id(),
+            TY_STRUCT_AEC_ROI.feature_id(),
+        ]);
+
+        // Bound features follow their target whatever the types, also across chains and cycles.
+        let rgb = TY_DEVICE_COMPONENT_LIST::TY_COMPONENT_RGB_CAM.component_id();
+        let bound_to = |comp, feat| {
+            if feat == TY_BOOL_AUTO_EXPOSURE.feature_id() {
+                Some((comp, TY_INT_EXPOSURE_TIME.feature_id()))
+            } else if feat == TY_INT_EXPOSURE_TIME.feature_id() {
+                Some((comp, TY_STRUCT_AEC_ROI.feature_id()))
+            } else if feat == TY_STRUCT_AEC_ROI.feature_id() {
+                Some((comp, TY_BOOL_AUTO_EXPOSURE.feature_id()))
+            } else {
+                Some((rgb, TY_INT_GAIN.feature_id()))
+            }
+        };
+        let order: Vec<_> = profile.apply_order(bound_to).into_iter().map(|(_, f, _)| f).collect();
+        assert_eq!(order, [
+            TY_ENUM_IMAGE_MODE.feature_id(),
+            TY_STRUCT_AEC_ROI.feature_id(),
+            TY_INT_EXPOSURE_TIME.feature_id(),
+            TY_BOOL_AUTO_EXPOSURE.feature_id(),
+        ]);
+
+        // Only the bound feature moves, the rest keeps its rank.
+        let mode_bound = |comp, feat| {
+            (feat == TY_ENUM_IMAGE_MODE.feature_id()).then_some((comp, TY_INT_EXPOSURE_TIME.feature_id()))
+        };
+        let order: Vec<_> = profile.apply_order(mode_bound).into_iter().map(|(_, f, _)| f).collect();
+        assert_eq!(order, [
+            TY_INT_EXPOSURE_TIME.feature_id(),
+            TY_ENUM_IMAGE_MODE.feature_id(),
+            TY_BOOL_AUTO_EXPOSURE.feature_id(),
+            TY_STRUCT_AEC_ROI.feature_id(),
+        ]);
+
+        let rgb = TY_DEVICE_COMPONENT_LIST::TY_COMPONENT_RGB_CAM.component_id();
+        assert!(profile.auto_controlled(rgb, TY_INT_EXPOSURE_TIME.feature_id()));
+        assert!(!profile.auto_controlled(rgb, TY_INT_GAIN.feature_id()));
+    }
+
+    #[test]
+    fn test_diff_and_serde() {
+        let a = sample();
+        let yaml = serde_yaml::to_string(&a).unwrap();
+        let b: Profile = serde_yaml::from_str(&yaml).unwrap();
+        assert_eq!(a, b);
+        assert!(a.diff(&b).is_empty());
+
+        let rgb = TY_DEVICE_COMPONENT_LIST::TY_COMPONENT_RGB_CAM.component_id();
+        let mut c = b.clone();
+        let cp = c.components.get_mut(&rgb).unwrap();
+        cp.enabled = false;
+        cp.features.remove(&TY_ENUM_IMAGE_MODE.feature_id());
+        cp.features.get_mut(&TY_INT_EXPOSURE_TIME.feature_id()).unwrap().value = FeatureValue::Int(2000);
+
+        let diff = a.diff(&c);
+        assert_eq!(diff.len(), 3);
+        assert_eq!(diff[0], ProfileDiff::Enabled { component: rgb, old: Some(true), new: Some(false) });
+        assert!(diff.contains(&ProfileDiff::Feature {
+            component: rgb,
+            feature: TY_INT_EXPOSURE_TIME.feature_id(),
+            name: "ExposureTime".into(),
+            old: Some(FeatureValue::Int(1000)),
+            new: Some(FeatureValue::Int(2000)),
+        }));
+        assert!(diff.iter().any(|d| matches!(d, ProfileDiff::Feature { new: None, .. })));
+    }
+}
@@ -0,0 +1,232 @@
+use serde::{Deserialize, Serialize};
+use camport3_sys::*;
+
+use crate::ffi::*;
+use crate::feature::DEPTH;
+
+/// Stereo matching parameters of the depth component, the `TY_INT_SGBM_*` and `TY_BOOL_SGBM_*` features.
+///
+/// `None` fields are absent on read and left alone on apply.
+#[derive(Serialize, Deserialize, Debug, Clone, Default, PartialEq, Eq)]
+#[serde(default)]
+pub struct SgbmParams {
+    /// `TY_INT_SGBM_IMAGE_NUM`, images matched per depth frame.
+    pub image_num: Option<i32>,
+    /// `TY_INT_SGBM_DISPARITY_NUM`.
+    pub disparity_num: Option<i32>,
+    /// `TY_INT_SGBM_DISPARITY_OFFSET`.
+    pub disparity_offset: Option<i32>,
+    /// `TY_INT_SGBM_MATCH_WIN_WIDTH`.
+    pub match_win_width: Option<i32>,
+    /// `TY_INT_SGBM_MATCH_WIN_HEIGHT`.
+    pub match_win_height: Option<i32>,
+    /// `TY_INT_SGBM_SEMI_PARAM_P1`, penalty for disparity changes of one.
+    pub p1: Option<i32>,
+    /// `TY_INT_SGBM_SEMI_PARAM_P2`, penalty for larger disparity changes.
+    pub p2: Option<i32>,
+    /// `TY_INT_SGBM_SEMI_PARAM_P1_SCALE`.
+    pub p1_scale: Option<i32>,
+    /// `TY_INT_SGBM_UNIQUE_FACTOR`, percent margin the best match must win by.
+    pub unique_factor: Option<i32>,
+    /// `TY_INT_SGBM_UNIQUE_ABSDIFF`.
+    pub unique_absdiff: Option<i32>,
+    /// `TY_INT_SGBM_UNIQUE_MAX_COST`.
+    pub unique_max_cost: Option<i32>,
+    /// `TY_BOOL_SGBM_HFILTER_HALF_WIN`.
+    pub hfilter_half_win: Option<bool>,
+    /// `TY_BOOL_SGBM_MEDFILTER`.
+    pub median_filter: Option<bool>,
+    /// `TY_INT_SGBM_MEDFILTER_THRESH`.
+    pub median_filter_thresh: Option<i32>,
+    /// `TY_BOOL_SGBM_LRC`, left-right consistency check.
+    pub lrc: Option<bool>,
+    /// `TY_INT_SGBM_LRC_DIFF`, maximum disparity difference of the consistency check.
+    pub lrc_diff: Option<i32>,
+}
+
+/// Named starting points for [`SgbmParams::preset`].
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Eq, Hash)]
+#[serde(rename_all = "snake_case")]
+pub enum SgbmPreset {
+    /// Fewer images and a small window, for frame rate.
+    Fast,
+    /// Larger window and median filtering, for fewer holes on matte surfaces.
+    Smooth,
+    /// Strict uniqueness and consistency checks, dropping the spurious matches specular surfaces produce.
+    Reflective,
+}
+
+impl SgbmParams {
+    fn ints(&self) -> [(TY_FEATURE_ID_LIST, Option<i32>); 13] {
+        use TY_FEATURE_ID_LIST::*;
+        [
+            (TY_INT_SGBM_IMAGE_NUM, self.image_num),
+            (TY_INT_SGBM_DISPARITY_NUM, self.disparity_num),
+            (TY_INT_SGBM_DISPARITY_OFFSET, self.disparity_offset),
+            (TY_INT_SGBM_MATCH_WIN_WIDTH, self.match_win_width),
+            (TY_INT_SGBM_MATCH_WIN_HEIGHT, self.match_win_height),
+            (TY_INT_SGBM_SEMI_PARAM_P1, self.p1),
+            (TY_INT_SGBM_SEMI_PARAM_P2, self.p2),
+            (TY_INT_SGBM_SEMI_PARAM_P1_SCALE, self.p1_scale),
+            (TY_INT_SGBM_UNIQUE_FACTOR, self.unique_factor),
+            (TY_INT_SGBM_UNIQUE_ABSDIFF, self.unique_absdiff),
+            (TY_INT_SGBM_UNIQUE_MAX_COST, self.unique_max_cost),
+            (TY_INT_SGBM_MEDFILTER_THRESH, self.median_filter_thresh),
+            (TY_INT_SGBM_LRC_DIFF, self.lrc_diff),
+        ]
+    }
+
+    fn bools(&self) -> [(TY_FEATURE_ID_LIST, Option<bool>); 3] {
+        use TY_FEATURE_ID_LIST::*;
+        [
+            (TY_BOOL_SGBM_HFILTER_HALF_WIN, self.hfilter_half_win),
+            (TY_BOOL_SGBM_MEDFILTER, self.median_filter),
+            (TY_BOOL_SGBM_LRC, self.lrc),
+        ]
+    }
+
+    pub fn preset(preset: SgbmPreset) -> Self {
+        match preset {
+            SgbmPreset::Fast => SgbmParams {
+                image_num: Some(1),
+                match_win_width: Some(5),
+                match_win_height: Some(5),
+                median_filter: Some(false),
+                lrc: Some(false),
+                ..Default::default()
+            },
+            SgbmPreset::Smooth => SgbmParams {
+                match_win_width: Some(9),
+                match_win_height: Some(9),
+                p1: Some(16),
+                p2: Some(128),
+                median_filter: Some(true),
+                median_filter_thresh: Some(10),
+                ..Default::default()
+            },
+            SgbmPreset::Reflective => SgbmParams {
+                unique_factor: Some(25),
+                unique_absdiff: Some(15),
+                median_filter: Some(true),
+                median_filter_thresh: Some(5),
+                lrc: Some(true),
+                lrc_diff: Some(1),
+                ..Default::default()
+            },
+        }
+    }
+
+    /// Fields of `other` that are set override those of `self`, e.g. to adjust a preset.
+    pub fn merge(&self, other: &SgbmParams) -> SgbmParams {
+        macro_rules! pick {
+            ($($f:ident),*) => { SgbmParams { $($f: other.$f.or(self.$f)),* } };
+        }
+        pick!(image_num, disparity_num, disparity_offset, match_win_width, match_win_height, p1, p2, p1_scale,
+              unique_factor, unique_absdiff, unique_max_cost, hfilter_half_win, median_filter, median_filter_thresh,
+              lrc, lrc_diff)
+    }
+
+    pub fn read(dev: &DeviceHandle) -> Result<Self> {
+        let int = |feat| dev.read_opt(DEPTH, feat, DeviceHandle::get_int);
+        let bool_ = |feat| dev.read_opt(DEPTH, feat, DeviceHandle::get_bool);
+        use TY_FEATURE_ID_LIST::*;
+        Ok(SgbmParams {
+            image_num: int(TY_INT_SGBM_IMAGE_NUM)?,
+            disparity_num: int(TY_INT_SGBM_DISPARITY_NUM)?,
+            disparity_offset: int(TY_INT_SGBM_DISPARITY_OFFSET)?,
+            match_win_width: int(TY_INT_SGBM_MATCH_WIN_WIDTH)?,
+            match_win_height: int(TY_INT_SGBM_MATCH_WIN_HEIGHT)?,
+            p1: int(TY_INT_SGBM_SEMI_PARAM_P1)?,
+            p2: int(TY_INT_SGBM_SEMI_PARAM_P2)?,
+            p1_scale: int(TY_INT_SGBM_SEMI_PARAM_P1_SCALE)?,
+            unique_factor: int(TY_INT_SGBM_UNIQUE_FACTOR)?,
+            unique_absdiff: int(TY_INT_SGBM_UNIQUE_ABSDIFF)?,
+            unique_max_cost: int(TY_INT_SGBM_UNIQUE_MAX_COST)?,
+            hfilter_half_win: bool_(TY_BOOL_SGBM_HFILTER_HALF_WIN)?,
+            median_filter: bool_(TY_BOOL_SGBM_MEDFILTER)?,
+            median_filter_thresh: int(TY_INT_SGBM_MEDFILTER_THRESH)?,
+            lrc: bool_(TY_BOOL_SGBM_LRC)?,
+            lrc_diff: int(TY_INT_SGBM_LRC_DIFF)?,
+        })
+    }
+
+    /// Check the `Some` fields against the device without writing anything.
+    ///
+    /// Features the device lacks fail with [`ErrorCode::NotImplemented`], integers outside their range with
+    /// [`ErrorCode::OutOfRange`].
+    pub fn validate(&self, dev: &DeviceHandle) -> Result<()> {
+        self.check(|feat| dev.has_feature(DEPTH, feat), |feat| dev.read_opt(DEPTH, feat, DeviceHandle::get_int_range))
+    }
+
+    /// `range` is `None` for integer features the device lacks.
+    fn check(
+        &self,
+        mut has: impl FnMut(TY_FEATURE_ID_LIST) -> Result<bool>,
+        mut range: impl FnMut(TY_FEATURE_ID_LIST) -> Result<Option<IntRange>>,
+    ) -> Result<()> {
+        for (feat, _) in self.bools().into_iter().filter(|(_, v)| v.is_some()) {
+            if !has(feat)? {
+                return Err(ErrorCode::NotImplemented.into());
+            }
+        }
+        for (feat, value) in self.ints() {
+            let Some(value) = value else { continue };
+            let range = range(feat)?.ok_or(ErrorCode::NotImplemented)?;
+            if !range.contains(value) {
+                return Err(ErrorCode::OutOfRange.into());
+            }
+        }
+        Ok(())
+    }
+
+    /// [`validate`](Self::validate), then write the `Some` fields; nothing is written if validation fails.
+    pub fn apply(&self, dev: &DeviceHandle) -> Result<()> {
+        self.validate(dev)?;
+        for (feat, on) in self.bools() {
+            if let Some(on) = on {
+                dev.set_bool(DEPTH, feat, on)?;
+            }
+        }
+        for (feat, value) in self.ints() {
+            if let Some(value) = value {
+                dev.set_int(DEPTH, feat, value)?;
+            }
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use bytemuck::TransparentWrapper;
+    use TY_FEATURE_ID_LIST::*;
+
+    #[test]
+    fn test_check() {
+        let range = |_| Ok(Some(IntRange::wrap(TY_INT_RANGE { min: 1, max: 64, inc: 1, reserved: [0] })));
+        let params = SgbmParams::preset(SgbmPreset::Reflective);
+        assert!(params.check(|_| Ok(true), range).is_ok());
+
+        let no_lrc = |feat| Ok(feat != TY_BOOL_SGBM_LRC);
+        assert_eq!(params.check(no_lrc, range).unwrap_err().errcode, ErrorCode::NotImplemented);
+        let no_lrc_diff = |feat| Ok(range(feat)?.filter(|_| feat != TY_INT_SGBM_LRC_DIFF));
+        assert_eq!(params.check(|_| Ok(true), no_lrc_diff).unwrap_err().errcode, ErrorCode::NotImplemented);
+
+        let wide = params.merge(&SgbmParams { lrc_diff: Some(100), ..Default::default() });
+        assert_eq!(wide.check(|_| Ok(true), range).unwrap_err().errcode, ErrorCode::OutOfRange);
+        assert!(SgbmParams::default().check(|_| Ok(false), |_| Ok(None)).is_ok());
+    }
+
+    #[test]
+    fn test_merge_and_serde() {
+        let base = SgbmParams::preset(SgbmPreset::Smooth);
+        let merged = base.merge(&SgbmParams { p2: Some(200), lrc: Some(true), ..Default::default() });
+        assert_eq!((merged.p1, merged.p2, merged.lrc), (Some(16), Some(200), Some(true)));
+
+        let yaml = serde_yaml::to_string(&merged).unwrap();
+        assert_eq!(serde_yaml::from_str::<SgbmParams>(&yaml).unwrap(), merged);
+        let partial: SgbmParams = serde_yaml::from_str("p1: 8\nlrc: false\n").unwrap();
+        assert_eq!((partial.p1, partial.lrc, partial.p2), (Some(8), Some(false), None));
+    }
+}
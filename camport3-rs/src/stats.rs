@@ -0,0 +1,373 @@
+use std::collections::VecDeque;
+use std::fmt::Write;
+use std::time::{Duration, Instant};
+use camport3_sys::*;
+
+use crate::ffi::*;
+use crate::capture::Frame;
+
+/// Default span of the rolling window rates are computed over.
+pub const DEFAULT_STATS_WINDOW: Duration = Duration::from_secs(10);
+
+#[derive(Debug, Clone, Copy, Default, PartialEq)]
+struct Counters {
+    packets_received: u64,
+    packets_lost: u64,
+    images_output: u64,
+    images_dropped: u64,
+    frames: u64,
+    fetch_timeouts: u64,
+    index_gaps: u64,
+}
+
+/// Device transport counters (`TY_STRUCT_CAM_STATISTICS`) and host-side fetch counters, with rates over a
+/// rolling window.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct StreamStats {
+    pub packets_received: u64,
+    pub packets_lost: u64,
+    pub images_output: u64,
+    pub images_dropped: u64,
+    /// Frames fetched by the host.
+    pub frames: u64,
+    pub fetch_timeouts: u64,
+    /// Image indices skipped between consecutive fetched frames.
+    pub index_gaps: u64,
+
+    /// Lost packets over received and lost packets, within the window.
+    pub packet_loss_ratio: f64,
+    pub packets_per_sec: f64,
+    pub frames_per_sec: f64,
+    pub dropped_per_sec: f64,
+    pub timeouts_per_sec: f64,
+    /// Mean, min and max interval between frame timestamps within the window.
+    pub frame_interval: Option<(Duration, Duration, Duration)>,
+}
+
+/// Collects [`StreamStats`] for one device.
+///
+/// Feed every fetch result to [`StreamMonitor::observe`] and call [`StreamMonitor::poll`] periodically.
+#[derive(Debug)]
+pub struct StreamMonitor {
+    window: Duration,
+    host: Counters,
+    samples: VecDeque<(Instant, Counters)>,
+    intervals: VecDeque<(Instant, Duration)>,
+    last_frame: Option<(i32, u64)>,
+}
+
+impl Default for StreamMonitor {
+    fn default() -> Self {
+        Self::new(DEFAULT_STATS_WINDOW)
+    }
+}
+
+impl StreamMonitor {
+    pub fn new(window: Duration) -> Self {
+        StreamMonitor {
+            window,
+            host: Counters::default(),
+            samples: VecDeque::new(),
+            intervals: VecDeque::new(),
+            last_frame: None,
+        }
+    }
+
+    /// Count the result of a `fetch_frame` call.
+    pub fn observe(&mut self, fetched: &Result<Frame>) {
+        self.observe_at(Instant::now(), fetched)
+    }
+
+    fn observe_at(&mut self, now: Instant, fetched: &Result<Frame>) {
+        match fetched {
+            Ok(frame) => {
+                self.host.frames += 1;
+                let (Some(index), Some(ts)) = (frame.image_index(), frame.timestamp()) else {
+                    return;
+                };
+                if let Some((last_index, last_ts)) = self.last_frame {
+                    // A smaller or repeated index means the device reset or wrapped it, which is no gap.
+                    if index > last_index {
+                        self.host.index_gaps += u64::from(index.abs_diff(last_index) - 1);
+                    }
+                    if ts > last_ts {
+                        self.intervals.push_back((now, Duration::from_micros(ts - last_ts)));
+                    }
+                }
+                self.last_frame = Some((index, ts));
+                self.expire(now);
+            }
+            Err(e) if e.errcode == ErrorCode::TIMEOUT => self.host.fetch_timeouts += 1,
+            Err(_) => {}
+        }
+    }
+
+    /// Read `TY_STRUCT_CAM_STATISTICS` and compute the stats.
+    pub fn poll(&mut self, dev: &DeviceHandle) -> Result<StreamStats> {
+        let raw: TY_CAMERA_STATISTICS = dev.get_struct(
+            TY_DEVICE_COMPONENT_LIST::TY_COMPONENT_DEVICE,
+            TY_FEATURE_ID_LIST::TY_STRUCT_CAM_STATISTICS,
+        )?;
+        Ok(self.sample_at(Instant::now(), &raw))
+    }
+
+    fn sample_at(&mut self, now: Instant, raw: &TY_CAMERA_STATISTICS) -> StreamStats {
+        let current = Counters {
+            packets_received: raw.packetReceived,
+            packets_lost: raw.packetLost,
+            images_output: raw.imageOutputed,
+            images_dropped: raw.imageDropped,
+            ..self.host
+        };
+        self.samples.push_back((now, current));
+        self.expire(now);
+
+        let mut stats = StreamStats {
+            packets_received: current.packets_received,
+            packets_lost: current.packets_lost,
+            images_output: current.images_output,
+            images_dropped: current.images_dropped,
+            frames: current.frames,
+            fetch_timeouts: current.fetch_timeouts,
+            index_gaps: current.index_gaps,
+            ..Default::default()
+        };
+
+        let (t0, first) = self.samples[0];
+        let secs = now.duration_since(t0).as_secs_f64();
+        if secs > 0.0 {
+            let rate = |f: fn(&Counters) -> u64| f(&current).saturating_sub(f(&first)) as f64 / secs;
+            stats.packets_per_sec = rate(|c| c.packets_received);
+            stats.frames_per_sec = rate(|c| c.frames);
+            stats.dropped_per_sec = rate(|c| c.images_dropped);
+            stats.timeouts_per_sec = rate(|c| c.fetch_timeouts);
+        }
+        let lost = current.packets_lost.saturating_sub(first.packets_lost);
+        let received = current.packets_received.saturating_sub(first.packets_received);
+        if lost + received > 0 {
+            stats.packet_loss_ratio = lost as f64 / (lost + received) as f64;
+        }
+
+        if !self.intervals.is_empty() {
+            let iv = self.intervals.iter().map(|(_, d)| *d);
+            let sum: Duration = iv.clone().sum();
+            stats.frame_interval = Some((
+                sum / self.intervals.len() as u32,
+                iv.clone().min().unwrap_or_default(),
+                iv.max().unwrap_or_default(),
+            ));
+        }
+        stats
+    }
+
+    fn expire(&mut self, now: Instant) {
+        let cutoff = now.checked_sub(self.window);
+        let old = |t: &Instant| cutoff.is_some_and(|c| *t < c);
+        // The oldest sample is kept as the base of the rates.
+        while self.samples.len() > 1 && old(&self.samples[1].0) {
+            self.samples.pop_front();
+        }
+        while self.intervals.front().is_some_and(|(t, _)| old(t)) {
+            self.intervals.pop_front();
+        }
+    }
+}
+
+/// Render stats in the Prometheus text exposition format, one `device` label per entry.
+pub fn prometheus_text(stats: &[(&str, &StreamStats)]) -> String {
+    type Metric = (&'static str, &'static str, &'static str, fn(&StreamStats) -> Option<f64>);
+    const METRICS: &[Metric] = &[
+        ("camport3_packets_received_total", "counter", "Packets received by the device.", |s| Some(s.packets_received as f64)),
+        ("camport3_packets_lost_total", "counter", "Packets lost, as counted by the device.", |s| Some(s.packets_lost as f64)),
+        ("camport3_images_output_total", "counter", "Images sent by the device.", |s| Some(s.images_output as f64)),
+        ("camport3_images_dropped_total", "counter", "Images dropped by the device.", |s| Some(s.images_dropped as f64)),
+        ("camport3_frames_total", "counter", "Frames fetched by the host.", |s| Some(s.frames as f64)),
+        ("camport3_fetch_timeouts_total", "counter", "Frame fetches that timed out.", |s| Some(s.fetch_timeouts as f64)),
+        ("camport3_image_index_gaps_total", "counter", "Image indices skipped between fetched frames.", |s| Some(s.index_gaps as f64)),
+        ("camport3_packet_loss_ratio", "gauge", "Packet loss ratio within the window.", |s| Some(s.packet_loss_ratio)),
+        ("camport3_packets_per_second", "gauge", "Received packet rate within the window.", |s| Some(s.packets_per_sec)),
+        ("camport3_frames_per_second", "gauge", "Fetched frame rate within the window.", |s| Some(s.frames_per_sec)),
+        ("camport3_images_dropped_per_second", "gauge", "Dropped image rate within the window.", |s| Some(s.dropped_per_sec)),
+        ("camport3_fetch_timeouts_per_second", "gauge", "Fetch timeout rate within the window.", |s| Some(s.timeouts_per_sec)),
+        ("camport3_frame_interval_seconds", "gauge", "Mean interval between frame timestamps.", |s| s.frame_interval.map(|(mean, _, _)| mean.as_secs_f64())),
+    ];
+
+    let mut out = String::new();
+    for (name, kind, help, value) in METRICS {
+        let _ = writeln!(out, "# HELP {name} {help}");
+        let _ = writeln!(out, "# TYPE {name} {kind}");
+        for (device, s) in stats {
+            if let Some(v) = value(s) {
+                let device = device.replace('\\', "\\\\").replace('"', "\\\"").replace('\n', "\\n");
+                let _ = writeln!(out, "{name}{{device=\"{device}\"}} {v}");
+            }
+        }
+    }
+    out
+}
+
+#[cfg(feature = "metrics-http")]
+mod http {
+    use std::io::{Read, Write};
+    use std::net::{SocketAddr, TcpListener, ToSocketAddrs};
+    use std::sync::atomic::{AtomicBool, Ordering};
+    use std::sync::Arc;
+    use std::thread::{self, JoinHandle};
+    use std::time::Duration;
+
+    /// Minimal HTTP server answering every request with the text returned by its source.
+    #[derive(Debug)]
+    pub struct MetricsServer {
+        addr: SocketAddr,
+        stop: Arc<AtomicBool>,
+        thread: Option<JoinHandle<()>>,
+    }
+
+    impl MetricsServer {
+        /// Serve `source()`, typically built with [`prometheus_text`](super::prometheus_text), on `addr`.
+        pub fn bind<F>(addr: impl ToSocketAddrs, source: F) -> std::io::Result<Self>
+        where
+            F: Fn() -> String + Send + 'static,
+        {
+            let listener = TcpListener::bind(addr)?;
+            let addr = listener.local_addr()?;
+            listener.set_nonblocking(true)?;
+            let stop = Arc::new(AtomicBool::new(false));
+            let thread_stop = stop.clone();
+            let thread = thread::Builder::new()
+                .name("camport3-metrics".into())
+                .spawn(move || {
+                    while !thread_stop.load(Ordering::Acquire) {
+                        let mut conn = match listener.accept() {
+                            Ok((conn, _)) => conn,
+                            Err(e) if e.kind() == std::io::ErrorKind::WouldBlock => {
+                                thread::sleep(Duration::from_millis(50));
+                                continue;
+                            }
+                            Err(e) => {
+                                log::warn!("metrics accept failed: {e}");
+                                continue;
+                            }
+                        };
+                        let _ = conn.set_nonblocking(false);
+                        let _ = conn.set_read_timeout(Some(Duration::from_secs(1)));
+                        let mut request = [0u8; 1024];
+                        let _ = conn.read(&mut request);
+                        let body = source();
+                        let _ = write!(
+                            conn,
+                            "HTTP/1.1 200 OK\r\nContent-Type: text/plain; version=0.0.4\r\nContent-Length: {}\r\nConnection: close\r\n\r\n{body}",
+                            body.len(),
+                        );
+                    }
+                })?;
+            Ok(MetricsServer { addr, stop, thread: Some(thread) })
+        }
+
+        /// Address the server listens on, e.g. the port picked for port 0.
+        pub fn local_addr(&self) -> SocketAddr {
+            self.addr
+        }
+    }
+
+    impl Drop for MetricsServer {
+        fn drop(&mut self) {
+            self.stop.store(true, Ordering::Release);
+            if let Some(thread) = self.thread.take() {
+                let _ = thread.join();
+            }
+        }
+    }
+}
+
+#[cfg(feature = "metrics-http")]
+pub use http::MetricsServer;
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn raw(received: u64, lost: u64) -> TY_CAMERA_STATISTICS {
+        TY_CAMERA_STATISTICS { packetReceived: received, packetLost: lost, imageOutputed: 0, imageDropped: 0, rsvd: [0; 1024] }
+    }
+
+    #[test]
+    fn test_rates() {
+        let t0 = Instant::now();
+        let mut monitor = StreamMonitor::new(Duration::from_secs(10));
+        monitor.sample_at(t0, &raw(0, 0));
+
+        let frame = |index, ts| Ok(Frame::from_parts(index, ts));
+        monitor.observe_at(t0, &frame(0, 0));
+        monitor.observe_at(t0 + Duration::from_millis(100), &frame(1, 100_000));
+        monitor.observe_at(t0 + Duration::from_millis(300), &frame(4, 300_000));
+        monitor.observe_at(t0 + Duration::from_millis(400), &Err(ErrorCode::TIMEOUT.into()));
+
+        let stats = monitor.sample_at(t0 + Duration::from_secs(1), &raw(900, 100));
+        assert_eq!(stats.frames, 3);
+        assert_eq!(stats.index_gaps, 2);
+        assert_eq!(stats.fetch_timeouts, 1);
+        assert_eq!(stats.packets_per_sec, 900.0);
+        assert_eq!(stats.packet_loss_ratio, 0.1);
+        assert_eq!(stats.frame_interval, Some((Duration::from_millis(150), Duration::from_millis(100), Duration::from_millis(200))));
+
+        // Samples older than the window no longer count.
+        monitor.sample_at(t0 + Duration::from_secs(30), &raw(900, 100));
+        let stats = monitor.sample_at(t0 + Duration::from_secs(45), &raw(1900, 100));
+        assert_eq!(stats.packet_loss_ratio, 0.0);
+        assert_eq!(stats.packets_per_sec, 1000.0 / 15.0);
+        assert_eq!(stats.frame_interval, None);
+    }
+
+    #[test]
+    fn test_index_reset() {
+        let t0 = Instant::now();
+        let mut monitor = StreamMonitor::default();
+        let frame = |index, ts| Ok(Frame::from_parts(index, ts));
+        monitor.observe_at(t0, &frame(i32::MAX - 1, 0));
+        monitor.observe_at(t0, &frame(i32::MAX, 1));
+        // Wrapped, then a gap of one.
+        monitor.observe_at(t0, &frame(0, 2));
+        monitor.observe_at(t0, &frame(2, 3));
+        // Reset by the device, then a gap of two.
+        monitor.observe_at(t0, &frame(0, 4));
+        monitor.observe_at(t0, &frame(0, 5));
+        monitor.observe_at(t0, &frame(3, 6));
+        let stats = monitor.sample_at(t0, &raw(0, 0));
+        assert_eq!(stats.index_gaps, 3);
+    }
+
+    #[test]
+    fn test_prometheus_text() {
+        let stats = StreamStats { packets_lost: 3, ..Default::default() };
+        let text = prometheus_text(&[("cam\"1", &stats), ("cam\n2", &stats)]);
+        assert!(text.contains("# TYPE camport3_packets_lost_total counter\n"));
+        assert!(text.contains("camport3_packets_lost_total{device=\"cam\\\"1\"} 3\n"));
+        assert!(text.contains("camport3_packets_lost_total{device=\"cam\\n2\"} 3\n"));
+        assert!(!text.contains("camport3_frame_interval_seconds{"));
+
+        let stats = StreamStats { dropped_per_sec: 1.5, timeouts_per_sec: 0.25, ..Default::default() };
+        let text = prometheus_text(&[("cam", &stats)]);
+        assert!(text.contains("camport3_images_dropped_per_second{device=\"cam\"} 1.5\n"));
+        assert!(text.contains("camport3_fetch_timeouts_per_second{device=\"cam\"} 0.25\n"));
+    }
+
+    #[cfg(feature = "metrics-http")]
+    #[test]
+    fn test_metrics_server() {
+        use std::io::{Read, Write};
+        use std::net::TcpStream;
+
+        let stats = StreamStats { frames: 7, ..Default::default() };
+        let server = MetricsServer::bind("127.0.0.1:0", move || prometheus_text(&[("cam", &stats)])).unwrap();
+        let mut conn = TcpStream::connect(server.local_addr()).unwrap();
+        conn.write_all(b"GET /metrics HTTP/1.1\r\nHost: localhost\r\n\r\n").unwrap();
+        let mut response = String::new();
+        conn.read_to_string(&mut response).unwrap();
+
+        let (head, body) = response.split_once("\r\n\r\n").unwrap();
+        assert!(head.starts_with("HTTP/1.1 200 OK\r\n"));
+        assert!(head.contains(&format!("Content-Length: {}\r\n", body.len())));
+        assert!(body.contains("camport3_frames_total{device=\"cam\"} 7\n"));
+    }
+}
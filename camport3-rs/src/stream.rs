@@ -0,0 +1,108 @@
+use std::pin::Pin;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Arc;
+use std::task::{Context as TaskContext, Poll};
+use std::thread;
+use futures::channel::mpsc;
+use futures::executor::block_on;
+use futures::{SinkExt, Stream};
+
+use crate::capture::*;
+use crate::ffi::*;
+
+/// Frames buffered between the fetch thread and the consumer before the thread waits.
+const STREAM_CHANNEL_SIZE: usize = 4;
+
+/// Asynchronous frame stream backed by a dedicated thread calling `TYFetchFrame`.
+///
+/// Dropping the stream stops the thread within one fetch timeout; the thread then drops the
+/// capture session, which stops capturing. Any fetch error other than a timeout, e.g.
+/// [`ErrorCode::DeviceOffline`], is the last item of the stream.
+#[derive(Debug)]
+pub struct FrameStream {
+    rx: mpsc::Receiver<Result<Frame>>,
+    stop: Arc<AtomicBool>,
+}
+
+impl CaptureSession {
+    /// Start capturing if needed and move the session to a fetch thread polling every `fetch_timeout_ms`.
+    pub fn into_stream(mut self, fetch_timeout_ms: i32) -> Result<FrameStream> {
+        if !self.is_capturing() {
+            self.start()?;
+        }
+        FrameStream::spawn(move || self.fetch_frame(fetch_timeout_ms))
+    }
+}
+
+impl FrameStream {
+    fn spawn<F>(fetch: F) -> Result<Self>
+    where F: FnMut() -> Result<Frame> + Send + 'static
+    {
+        let (tx, rx) = mpsc::channel(STREAM_CHANNEL_SIZE);
+        let stop = Arc::new(AtomicBool::new(false));
+        let thread_stop = stop.clone();
+        thread::Builder::new()
+            .name("camport3-fetch".into())
+            .spawn(move || fetch_loop(fetch, tx, thread_stop))
+            .map_err(|_| ErrorCode::OutOfMemory)?;
+        Ok(FrameStream { rx, stop })
+    }
+}
+
+fn fetch_loop(mut fetch: impl FnMut() -> Result<Frame>, mut tx: mpsc::Sender<Result<Frame>>, stop: Arc<AtomicBool>) {
+    while !stop.load(Ordering::Acquire) {
+        let item = match fetch() {
+            Err(e) if e.errcode == ErrorCode::TIMEOUT => continue,
+            item => item,
+        };
+        let failed = item.is_err();
+        if block_on(tx.send(item)).is_err() || failed {
+            break;
+        }
+    }
+}
+
+impl Stream for FrameStream {
+    type Item = Result<Frame>;
+
+    fn poll_next(mut self: Pin<&mut Self>, cx: &mut TaskContext<'_>) -> Poll<Option<Self::Item>> {
+        Pin::new(&mut self.rx).poll_next(cx)
+    }
+}
+
+impl Drop for FrameStream {
+    fn drop(&mut self) {
+        self.stop.store(true, Ordering::Release);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::mpsc as std_mpsc;
+    use std::time::Duration;
+    use futures::StreamExt;
+    use super::*;
+
+    #[test]
+    fn test_stream_ends_on_error() {
+        let mut results =
+            vec![Err(ErrorCode::NotInited.into()), Err(ErrorCode::TIMEOUT.into()), Ok(Frame::from_parts(1, 10))];
+        let mut stream = FrameStream::spawn(move || results.pop().expect("fetched after the stream ended")).unwrap();
+        assert_eq!(block_on(stream.next()).unwrap().unwrap().image_index(), Some(1));
+        assert_eq!(block_on(stream.next()).unwrap().unwrap_err().errcode, ErrorCode::NotInited);
+        assert!(block_on(stream.next()).is_none());
+    }
+
+    #[test]
+    fn test_stream_drop_stops_thread() {
+        // Dropped with the fetch closure when the thread ends, like the capture session.
+        let (alive, ended) = std_mpsc::channel::<()>();
+        let stream = FrameStream::spawn(move || {
+            let _alive = &alive;
+            thread::sleep(Duration::from_millis(1));
+            Err(ErrorCode::TIMEOUT.into())
+        }).unwrap();
+        drop(stream);
+        assert_eq!(ended.recv_timeout(Duration::from_secs(5)), Err(std_mpsc::RecvTimeoutError::Disconnected));
+    }
+}
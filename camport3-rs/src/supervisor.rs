@@ -0,0 +1,266 @@
+use std::sync::Arc;
+use std::thread;
+use std::time::Duration;
+use camport3_sys::*;
+
+use crate::ffi::*;
+use crate::capture::{CaptureSession, Frame, DEFAULT_BUFFER_COUNT};
+use crate::profile::Profile;
+
+/// Reconnection policy of a [`Supervisor`].
+#[derive(Debug, Clone)]
+pub struct SupervisorConfig {
+    /// Components enabled on every (re)open, on top of those recorded in the profile.
+    pub components: TY_COMPONENT_ID,
+    pub buffer_count: usize,
+    /// Consecutive fetch timeouts after which the device is considered lost.
+    pub max_timeouts: u32,
+    pub initial_backoff: Duration,
+    pub max_backoff: Duration,
+    /// Reconnection attempts before giving up; `None` retries forever.
+    pub max_attempts: Option<u32>,
+}
+
+impl Default for SupervisorConfig {
+    fn default() -> Self {
+        SupervisorConfig {
+            components: TY_DEVICE_COMPONENT_LIST::TY_COMPONENT_DEPTH_CAM as TY_COMPONENT_ID,
+            buffer_count: DEFAULT_BUFFER_COUNT,
+            max_timeouts: 5,
+            initial_backoff: Duration::from_millis(500),
+            max_backoff: Duration::from_secs(30),
+            max_attempts: None,
+        }
+    }
+}
+
+/// State transitions reported by a [`Supervisor`].
+#[derive(Debug, Clone)]
+pub enum SupervisorState {
+    /// Capturing; reported after the initial start and after every recovery.
+    Running,
+    /// The device went offline or stopped delivering frames.
+    Lost(DeviceError),
+    /// About to wait `delay` before reconnection attempt `attempt`, counted from 1.
+    Reconnecting { attempt: u32, delay: Duration },
+    /// `max_attempts` were exhausted; the next `fetch_frame` starts over.
+    GaveUp(DeviceError),
+}
+
+/// Exponential backoff, doubling from `initial` up to `max`.
+#[derive(Debug, Clone)]
+struct Backoff {
+    max: Duration,
+    next: Duration,
+}
+
+impl Backoff {
+    fn new(initial: Duration, max: Duration) -> Self {
+        Backoff { max, next: initial.min(max) }
+    }
+
+    fn next_delay(&mut self) -> Duration {
+        let delay = self.next;
+        self.next = (delay * 2).min(self.max);
+        delay
+    }
+}
+
+/// Whether a fetch error means the device is gone, given the number of consecutive timeouts so far.
+fn is_lost(error: &DeviceError, timeouts: u32, max_timeouts: u32) -> bool {
+    match error.errcode {
+        ErrorCode::DeviceOffline => true,
+        ErrorCode::TIMEOUT => timeouts >= max_timeouts,
+        _ => false,
+    }
+}
+
+type StateListener = Box<dyn FnMut(&SupervisorState) + Send>;
+
+/// Owns an open device and keeps it capturing across disconnects.
+///
+/// [`fetch_frame`](Self::fetch_frame) detects `TY_EVENT_DEVICE_OFFLINE` and runs of fetch timeouts. It then closes the
+/// device, waits for it to reappear on any interface with exponential backoff, reopens it, reapplies the last
+/// [`Profile`] and component set, and restarts capture before fetching again.
+///
+/// The old device is only closed once nothing else holds its handle: clones from [`device`](Self::device) and
+/// frames from [`CaptureSession::fetch_shared_frame`] keep it open, and reopening then fails or reaches a stale
+/// device. Drop them when [`SupervisorState::Lost`] is reported.
+pub struct Supervisor {
+    ctx: Context,
+    id: String,
+    config: SupervisorConfig,
+    profile: Profile,
+    session: Option<CaptureSession>,
+    timeouts: u32,
+    listener: StateListener,
+}
+
+impl std::fmt::Debug for Supervisor {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("Supervisor")
+            .field("id", &self.id)
+            .field("config", &self.config)
+            .field("session", &self.session)
+            .field("timeouts", &self.timeouts)
+            .finish_non_exhaustive()
+    }
+}
+
+impl Supervisor {
+    /// Open the device `id`, snapshot its configuration and start capturing.
+    ///
+    /// `on_state` is called from the thread calling into the supervisor.
+    pub fn open<F>(ctx: Context, id: &str, config: SupervisorConfig, on_state: F) -> Result<Self>
+    where
+        F: FnMut(&SupervisorState) + Send + 'static,
+    {
+        let dev = open_device(&ctx, id)?;
+        dev.enable_components(config.components)?;
+        let profile = Profile::snapshot(&dev)?;
+        let mut sup = Supervisor {
+            ctx,
+            id: id.to_owned(),
+            config,
+            profile,
+            session: None,
+            timeouts: 0,
+            listener: Box::new(on_state),
+        };
+        sup.start(dev)?;
+        Ok(sup)
+    }
+
+    pub fn device_id(&self) -> &str {
+        &self.id
+    }
+
+    /// The device, `None` while it is lost.
+    ///
+    /// Clones of the handle must be dropped when the device is lost, see [`Supervisor`].
+    pub fn device(&self) -> Option<&Arc<DeviceHandle>> {
+        self.session.as_ref().map(CaptureSession::device)
+    }
+
+    /// Configuration reapplied after reconnecting.
+    pub fn profile(&self) -> &Profile {
+        &self.profile
+    }
+
+    /// Re-snapshot the configuration, after the application changed features on [`device`](Self::device).
+    pub fn update_profile(&mut self) -> Result<()> {
+        let dev = self.device().ok_or(ErrorCode::DeviceOffline)?;
+        self.profile = Profile::snapshot(dev)?;
+        Ok(())
+    }
+
+    pub fn set_profile(&mut self, profile: Profile) {
+        self.profile = profile;
+    }
+
+    fn start(&mut self, dev: Arc<DeviceHandle>) -> Result<()> {
+        let mut session = CaptureSession::new(dev, self.config.buffer_count)?;
+        session.start()?;
+        self.session = Some(session);
+        self.timeouts = 0;
+        (self.listener)(&SupervisorState::Running);
+        Ok(())
+    }
+
+    /// Fetch the next frame, recovering the device first if it was lost.
+    ///
+    /// Timeouts below `max_timeouts` and other errors are returned as is, as is the last error if recovery gives up.
+    pub fn fetch_frame(&mut self, timeout_ms: i32) -> Result<Frame> {
+        loop {
+            let Some(session) = &self.session else {
+                self.recover(ErrorCode::DeviceOffline.into())?;
+                continue;
+            };
+            let error = match session.fetch_frame(timeout_ms) {
+                Ok(frame) => {
+                    self.timeouts = 0;
+                    return Ok(frame);
+                }
+                Err(e) => e,
+            };
+            if error.errcode == ErrorCode::TIMEOUT {
+                self.timeouts += 1;
+            } else {
+                self.timeouts = 0;
+            }
+            if !is_lost(&error, self.timeouts, self.config.max_timeouts) {
+                return Err(error);
+            }
+            self.recover(error)?;
+        }
+    }
+
+    /// Close the device and reopen it until it comes back, or `max_attempts` is exhausted.
+    fn recover(&mut self, reason: DeviceError) -> Result<()> {
+        // Dropping the session stops capture and clears the queue; dropping the last handle closes the device.
+        let dev = self.session.take().map(|session| Arc::clone(session.device()));
+        (self.listener)(&SupervisorState::Lost(reason));
+        if let Some(dev) = dev {
+            if Arc::strong_count(&dev) > 1 {
+                log::warn!("{} is still held elsewhere and stays open while reconnecting", self.id);
+            }
+        }
+
+        let mut backoff = Backoff::new(self.config.initial_backoff, self.config.max_backoff);
+        let mut attempt = 0;
+        loop {
+            attempt += 1;
+            let delay = backoff.next_delay();
+            (self.listener)(&SupervisorState::Reconnecting { attempt, delay });
+            thread::sleep(delay);
+            let res = match self.reopen() {
+                Ok(dev) => self.start(dev),
+                Err(e) => Err(e),
+            };
+            let Err(e) = res else {
+                return Ok(());
+            };
+            log::info!("reconnecting {} failed: {e}", self.id);
+            if self.config.max_attempts.is_some_and(|max| attempt >= max) {
+                (self.listener)(&SupervisorState::GaveUp(e));
+                return Err(e);
+            }
+        }
+    }
+
+    fn reopen(&self) -> Result<Arc<DeviceHandle>> {
+        let dev = open_device(&self.ctx, &self.id)?;
+        let report = self.profile.apply(&dev, false)?;
+        for failed in &report.failed {
+            log::warn!("reapplying {} on {} failed: {}", failed.name, self.id, failed.error);
+        }
+        dev.enable_components(self.config.components)?;
+        Ok(dev)
+    }
+}
+
+fn open_device(ctx: &Context, id: &str) -> Result<Arc<DeviceHandle>> {
+    let iface = ctx.find_device(id)?.ok_or(ErrorCode::DeviceOffline)?;
+    Ok(Arc::new(iface.open_device(id)?))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_backoff() {
+        let mut backoff = Backoff::new(Duration::from_millis(100), Duration::from_millis(500));
+        let delays: Vec<_> = (0..5).map(|_| backoff.next_delay().as_millis()).collect();
+        assert_eq!(delays, [100, 200, 400, 500, 500]);
+    }
+
+    #[test]
+    fn test_is_lost() {
+        let err = |errcode| DeviceError { errcode, firmware_errcode: None };
+        assert!(is_lost(&err(ErrorCode::DeviceOffline), 0, 5));
+        assert!(!is_lost(&err(ErrorCode::TIMEOUT), 4, 5));
+        assert!(is_lost(&err(ErrorCode::TIMEOUT), 5, 5));
+        assert!(!is_lost(&err(ErrorCode::Busy), 10, 5));
+    }
+}
@@ -0,0 +1,215 @@
+use std::collections::VecDeque;
+use std::net::Ipv4Addr;
+use std::thread;
+use std::time::{Duration, Instant, SystemTime, UNIX_EPOCH};
+use serde::{Deserialize, Serialize};
+use strum_macros::FromRepr;
+use camport3_sys::*;
+
+use crate::ffi::*;
+use crate::feature::DEVICE;
+use crate::capture::{Frame, Image};
+
+/// Device clock source, `TY_ENUM_TIME_SYNC_TYPE`.
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Eq, Hash, FromRepr)]
+#[repr(u32)]
+pub enum TimeSyncType {
+    None = TY_TIME_SYNC_TYPE_LIST::TY_TIME_SYNC_TYPE_NONE as u32,
+    Host = TY_TIME_SYNC_TYPE_LIST::TY_TIME_SYNC_TYPE_HOST as u32,
+    Ntp = TY_TIME_SYNC_TYPE_LIST::TY_TIME_SYNC_TYPE_NTP as u32,
+    Ptp = TY_TIME_SYNC_TYPE_LIST::TY_TIME_SYNC_TYPE_PTP as u32,
+    Can = TY_TIME_SYNC_TYPE_LIST::TY_TIME_SYNC_TYPE_CAN as u32,
+    PtpMaster = TY_TIME_SYNC_TYPE_LIST::TY_TIME_SYNC_TYPE_PTP_MASTER as u32,
+}
+
+impl TimeSyncType {
+    /// Whether device timestamps are microseconds since the Unix epoch.
+    pub fn is_wall_clock(self) -> bool {
+        matches!(self, TimeSyncType::Host | TimeSyncType::Ntp | TimeSyncType::Ptp | TimeSyncType::PtpMaster)
+    }
+}
+
+impl DeviceHandle {
+    pub fn get_time_sync_type(&self) -> Result<TimeSyncType> {
+        let raw = self.get_enum(DEVICE, TY_FEATURE_ID_LIST::TY_ENUM_TIME_SYNC_TYPE)?;
+        TimeSyncType::from_repr(raw).ok_or_else(|| ErrorCode::NotImplemented.into())
+    }
+
+    pub fn set_time_sync_type(&self, sync: TimeSyncType) -> Result<()> {
+        self.set_enum(DEVICE, TY_FEATURE_ID_LIST::TY_ENUM_TIME_SYNC_TYPE, sync as u32)
+    }
+
+    /// `TY_INT_NTP_SERVER_IP`, stored with the first octet in the most significant byte.
+    pub fn get_ntp_server_ip(&self) -> Result<Ipv4Addr> {
+        let raw = self.get_int(DEVICE, TY_FEATURE_ID_LIST::TY_INT_NTP_SERVER_IP)?;
+        Ok(Ipv4Addr::from(raw as u32))
+    }
+
+    pub fn set_ntp_server_ip(&self, ip: Ipv4Addr) -> Result<()> {
+        self.set_int(DEVICE, TY_FEATURE_ID_LIST::TY_INT_NTP_SERVER_IP, u32::from(ip) as i32)
+    }
+
+    pub fn is_time_sync_ready(&self) -> Result<bool> {
+        self.get_bool(DEVICE, TY_FEATURE_ID_LIST::TY_BOOL_TIME_SYNC_READY)
+    }
+
+    /// Poll `TY_BOOL_TIME_SYNC_READY` until set, failing with [`ErrorCode::TIMEOUT`] after `timeout`.
+    pub fn wait_time_sync_ready(&self, timeout: Duration) -> Result<()> {
+        const POLL_PERIOD: Duration = Duration::from_millis(100);
+        let deadline = Instant::now() + timeout;
+        loop {
+            if self.is_time_sync_ready()? {
+                return Ok(());
+            }
+            let now = Instant::now();
+            if now >= deadline {
+                return Err(ErrorCode::TIMEOUT.into());
+            }
+            thread::sleep(POLL_PERIOD.min(deadline - now));
+        }
+    }
+}
+
+/// [`DeviceHandle::wait_time_sync_ready`] on a helper thread.
+#[cfg(feature = "async")]
+pub async fn wait_time_sync_ready_async(dev: std::sync::Arc<DeviceHandle>, timeout: Duration) -> Result<()> {
+    let (tx, rx) = futures::channel::oneshot::channel();
+    thread::Builder::new()
+        .name("camport3-time-sync".into())
+        .spawn(move || {
+            let _ = tx.send(dev.wait_time_sync_ready(timeout));
+        })
+        .map_err(|_| ErrorCode::OutOfMemory)?;
+    rx.await.unwrap_or_else(|_| Err(ErrorCode::ERROR.into()))
+}
+
+/// Maximum number of observations [`Clock`] fits its drift on.
+pub const CLOCK_WINDOW: usize = 256;
+
+/// Maps device timestamps (`TY_IMAGE_DATA::timestamp`, `TY_IMU_DATA::timestamp`, microseconds) to host time.
+///
+/// With a wall-clock sync type the timestamps are used as is. Otherwise the device clock is fitted against host
+/// arrival times: the slope by least squares, the offset on the lower envelope, since transport only adds delay.
+#[derive(Debug, Clone)]
+pub struct Clock {
+    sync: TimeSyncType,
+    samples: VecDeque<(u64, i64)>,
+    // host_us = device_us * slope + offset
+    fit: Option<(f64, f64)>,
+}
+
+impl Clock {
+    pub fn new(sync: TimeSyncType) -> Self {
+        Clock { sync, samples: VecDeque::with_capacity(CLOCK_WINDOW), fit: None }
+    }
+
+    pub fn sync_type(&self) -> TimeSyncType {
+        self.sync
+    }
+
+    /// Record that a sample stamped `device_us` arrived at `host`. Ignored with a wall-clock sync type.
+    pub fn observe(&mut self, device_us: u64, host: SystemTime) {
+        if self.sync.is_wall_clock() {
+            return;
+        }
+        let host_us = match host.duration_since(UNIX_EPOCH) {
+            Ok(d) => d.as_micros() as i64,
+            Err(e) => -(e.duration().as_micros() as i64),
+        };
+        if self.samples.len() == CLOCK_WINDOW {
+            self.samples.pop_front();
+        }
+        self.samples.push_back((device_us, host_us));
+        self.fit = Some(self.refit());
+    }
+
+    /// Record the arrival of a frame that was just fetched.
+    pub fn observe_frame(&mut self, frame: &Frame) {
+        if let Some(ts) = frame.timestamp() {
+            self.observe(ts, SystemTime::now());
+        }
+    }
+
+    fn refit(&self) -> (f64, f64) {
+        let n = self.samples.len() as f64;
+        let (d0, h0) = self.samples[0];
+        // Relative to the first sample, to keep the sums well conditioned.
+        let points = || self.samples.iter().map(|&(d, h)| ((d as f64) - d0 as f64, (h - h0) as f64));
+        let (sx, sy) = points().fold((0.0, 0.0), |(sx, sy), (x, y)| (sx + x, sy + y));
+        let (mx, my) = (sx / n, sy / n);
+        let (sxx, sxy) = points().fold((0.0, 0.0), |(sxx, sxy), (x, y)| (sxx + (x - mx) * (x - mx), sxy + (x - mx) * (y - my)));
+        let slope = if sxx > 0.0 { sxy / sxx } else { 1.0 };
+        let offset = points().map(|(x, y)| y - slope * x).fold(f64::INFINITY, f64::min);
+        (slope, h0 as f64 + offset - slope * d0 as f64)
+    }
+
+    /// Device clock drift in parts per million, positive when it runs faster than the host clock.
+    ///
+    /// `None` until two observations are fitted.
+    pub fn drift_ppm(&self) -> Option<f64> {
+        match self.fit {
+            Some((slope, _)) if self.samples.len() > 1 => Some((1.0 / slope - 1.0) * 1e6),
+            _ => None,
+        }
+    }
+
+    /// Host time of a device timestamp; `None` until the first observation when not synchronised.
+    pub fn to_system_time(&self, device_us: u64) -> Option<SystemTime> {
+        let host_us = if self.sync.is_wall_clock() {
+            device_us as f64
+        } else {
+            let (slope, offset) = self.fit?;
+            device_us as f64 * slope + offset
+        };
+        let d = Duration::from_micros(host_us.abs().round() as u64);
+        if host_us >= 0.0 { UNIX_EPOCH.checked_add(d) } else { UNIX_EPOCH.checked_sub(d) }
+    }
+
+    pub fn image_time(&self, image: &Image) -> Option<SystemTime> {
+        self.to_system_time(image.timestamp)
+    }
+
+    pub fn imu_time(&self, imu: &TY_IMU_DATA) -> Option<SystemTime> {
+        self.to_system_time(imu.timestamp)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_time_sync_type() {
+        assert_eq!(TimeSyncType::from_repr(3), Some(TimeSyncType::Ptp));
+        assert_eq!(TimeSyncType::from_repr(9), None);
+        assert!(!TimeSyncType::None.is_wall_clock());
+    }
+
+    #[test]
+    fn test_clock_wall() {
+        let clock = Clock::new(TimeSyncType::Ptp);
+        assert_eq!(clock.to_system_time(1_500_000), Some(UNIX_EPOCH + Duration::from_micros(1_500_000)));
+    }
+
+    #[test]
+    fn test_clock_drift() {
+        let mut clock = Clock::new(TimeSyncType::None);
+        assert_eq!(clock.to_system_time(0), None);
+
+        // Device runs 100 ppm fast, booted at host time 1000 s; arrivals take 2 to 5 ms.
+        let boot = UNIX_EPOCH + Duration::from_secs(1000);
+        for i in 0..100u64 {
+            let device_us = i * 100_000;
+            let latency = 2000 + (i * 7919) % 3000;
+            let host_us = (device_us as f64 / 1.0001) as u64 + latency;
+            clock.observe(device_us, boot + Duration::from_micros(host_us));
+        }
+        let drift = clock.drift_ppm().unwrap();
+        assert!((drift - 100.0).abs() < 20.0, "drift {drift}");
+
+        let mapped = clock.to_system_time(5_000_000).unwrap();
+        let expected = boot + Duration::from_micros((5_000_000f64 / 1.0001) as u64 + 2000);
+        let err = mapped.duration_since(expected).unwrap_or_else(|e| e.duration());
+        assert!(err < Duration::from_micros(500), "error {err:?}");
+    }
+}
@@ -0,0 +1,192 @@
+use std::collections::BTreeSet;
+use serde::{Deserialize, Serialize};
+use camport3_sys::*;
+
+use crate::ffi::*;
+use crate::feature::DEPTH;
+
+/// Dual modulation frequencies of `TY_STRUCT_TOF_FREQ`.
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct TofFreq {
+    pub freq1: u32,
+    pub freq2: u32,
+}
+
+impl From<TY_TOF_FREQ> for TofFreq {
+    fn from(raw: TY_TOF_FREQ) -> Self {
+        TofFreq { freq1: raw.freq1, freq2: raw.freq2 }
+    }
+}
+
+impl From<TofFreq> for TY_TOF_FREQ {
+    fn from(f: TofFreq) -> Self {
+        TY_TOF_FREQ { freq1: f.freq1, freq2: f.freq2 }
+    }
+}
+
+/// ToF parameters of the depth component. `None` fields are absent on read and left alone on apply.
+#[derive(Serialize, Deserialize, Debug, Clone, Default, PartialEq, Eq)]
+#[serde(default)]
+pub struct TofConfig {
+    /// `TY_STRUCT_TOF_FREQ`.
+    pub freq: Option<TofFreq>,
+    /// `TY_INT_TOF_CHANNEL`, modulation frequency channel.
+    pub channel: Option<i32>,
+    /// `TY_INT_TOF_MODULATION_THRESHOLD`.
+    pub modulation_threshold: Option<i32>,
+    /// `TY_BOOL_TOF_ANTI_INTERFERENCE`, cooperate with other ToF devices nearby.
+    pub anti_interference: Option<bool>,
+    /// `TY_INT_TOF_ANTI_SUNLIGHT_INDEX`.
+    pub anti_sunlight_index: Option<i32>,
+    /// `TY_INT_TOF_HDR_RATIO`.
+    pub hdr_ratio: Option<i32>,
+    /// `TY_INT_TOF_JITTER_THRESHOLD`.
+    pub jitter_threshold: Option<i32>,
+    /// `TY_INT_FILTER_THRESHOLD`, noise filter, 0 to disable.
+    pub filter_threshold: Option<i32>,
+    /// `TY_INT_MAX_SPECKLE_SIZE`.
+    pub max_speckle_size: Option<i32>,
+    /// `TY_INT_MAX_SPECKLE_DIFF`.
+    pub max_speckle_diff: Option<i32>,
+}
+
+impl TofConfig {
+    fn ints(&self) -> [(TY_FEATURE_ID_LIST, Option<i32>); 8] {
+        use TY_FEATURE_ID_LIST::*;
+        [
+            (TY_INT_TOF_CHANNEL, self.channel),
+            (TY_INT_TOF_MODULATION_THRESHOLD, self.modulation_threshold),
+            (TY_INT_TOF_ANTI_SUNLIGHT_INDEX, self.anti_sunlight_index),
+            (TY_INT_TOF_HDR_RATIO, self.hdr_ratio),
+            (TY_INT_TOF_JITTER_THRESHOLD, self.jitter_threshold),
+            (TY_INT_FILTER_THRESHOLD, self.filter_threshold),
+            (TY_INT_MAX_SPECKLE_SIZE, self.max_speckle_size),
+            (TY_INT_MAX_SPECKLE_DIFF, self.max_speckle_diff),
+        ]
+    }
+
+    pub fn read(dev: &DeviceHandle) -> Result<Self> {
+        use TY_FEATURE_ID_LIST::*;
+        let int = |feat| dev.read_opt(DEPTH, feat, DeviceHandle::get_int);
+        let freq = dev.read_opt(DEPTH, TY_STRUCT_TOF_FREQ, DeviceHandle::get_struct::<TY_TOF_FREQ>)?.map(Into::into);
+        let anti_interference = dev.read_opt(DEPTH, TY_BOOL_TOF_ANTI_INTERFERENCE, DeviceHandle::get_bool)?;
+        Ok(TofConfig {
+            freq,
+            channel: int(TY_INT_TOF_CHANNEL)?,
+            modulation_threshold: int(TY_INT_TOF_MODULATION_THRESHOLD)?,
+            anti_interference,
+            anti_sunlight_index: int(TY_INT_TOF_ANTI_SUNLIGHT_INDEX)?,
+            hdr_ratio: int(TY_INT_TOF_HDR_RATIO)?,
+            jitter_threshold: int(TY_INT_TOF_JITTER_THRESHOLD)?,
+            filter_threshold: int(TY_INT_FILTER_THRESHOLD)?,
+            max_speckle_size: int(TY_INT_MAX_SPECKLE_SIZE)?,
+            max_speckle_diff: int(TY_INT_MAX_SPECKLE_DIFF)?,
+        })
+    }
+
+    /// Write the `Some` fields. Integers outside the feature's range fail with [`ErrorCode::OutOfRange`] before
+    /// anything is written.
+    pub fn apply(&self, dev: &DeviceHandle) -> Result<()> {
+        use TY_FEATURE_ID_LIST::*;
+        for (feat, value) in self.ints() {
+            if let Some(value) = value {
+                if !dev.get_int_range(DEPTH, feat)?.contains(value) {
+                    return Err(ErrorCode::OutOfRange.into());
+                }
+            }
+        }
+        // Frequencies and channel first, the thresholds depend on the modulation.
+        if let Some(freq) = self.freq {
+            dev.set_struct(DEPTH, TY_STRUCT_TOF_FREQ, &TY_TOF_FREQ::from(freq))?;
+        }
+        for (feat, value) in self.ints() {
+            if let Some(value) = value {
+                dev.set_int(DEPTH, feat, value)?;
+            }
+        }
+        if let Some(on) = self.anti_interference {
+            dev.set_bool(DEPTH, TY_BOOL_TOF_ANTI_INTERFERENCE, on)?;
+        }
+        Ok(())
+    }
+}
+
+/// Pick a distinct value from each range, in input order; `None` if that is impossible.
+///
+/// Ranges are served by ascending maximum, each taking the lowest free value on its increment grid.
+fn plan_channels(ranges: &[(i32, i32, i32)]) -> Option<Vec<i32>> {
+    let mut order: Vec<usize> = (0..ranges.len()).collect();
+    order.sort_by_key(|&i| (ranges[i].1, ranges[i].0));
+    let mut used = BTreeSet::new();
+    let mut out = vec![0; ranges.len()];
+    for i in order {
+        let (min, max, inc) = ranges[i];
+        let step = inc.max(1) as usize;
+        let value = (min..=max).step_by(step).find(|v| !used.contains(v))?;
+        used.insert(value);
+        out[i] = value;
+    }
+    Some(out)
+}
+
+/// Give ToF devices sharing a cell distinct `TY_INT_TOF_CHANNEL`s and turn on their anti-interference mode.
+///
+/// With `freqs` non-empty, device `i` also gets `freqs[i]`; it must then hold distinct pairs, at least one per
+/// device, or the call fails with [`ErrorCode::InvalidParameter`]. When the channel ranges cannot be made distinct
+/// it fails with [`ErrorCode::OutOfRange`]. Nothing is written unless a plan is found. Returns what was applied,
+/// in device order.
+pub fn assign_tof_channels(devs: &[&DeviceHandle], freqs: &[TofFreq]) -> Result<Vec<TofConfig>> {
+    if !freqs.is_empty() {
+        let distinct: BTreeSet<_> = freqs.iter().map(|f| (f.freq1, f.freq2)).collect();
+        if freqs.len() < devs.len() || distinct.len() != freqs.len() {
+            return Err(ErrorCode::InvalidParameter.into());
+        }
+    }
+    let ranges = devs.iter()
+        .map(|dev| {
+            let r = dev.get_int_range(DEPTH, TY_FEATURE_ID_LIST::TY_INT_TOF_CHANNEL)?;
+            Ok((r.min(), r.max(), r.inc()))
+        })
+        .collect::<Result<Vec<_>>>()?;
+    let channels = plan_channels(&ranges).ok_or(ErrorCode::OutOfRange)?;
+
+    let configs: Vec<TofConfig> = channels.into_iter().enumerate()
+        .map(|(i, channel)| TofConfig {
+            freq: freqs.get(i).copied(),
+            channel: Some(channel),
+            anti_interference: Some(true),
+            ..Default::default()
+        })
+        .collect();
+    for (dev, config) in devs.iter().zip(&configs) {
+        config.apply(dev)?;
+    }
+    Ok(configs)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_plan_channels() {
+        assert_eq!(plan_channels(&[(0, 3, 1), (0, 3, 1), (0, 3, 1)]), Some(vec![0, 1, 2]));
+        // The narrow range is served first, so the wide one moves out of its way.
+        assert_eq!(plan_channels(&[(0, 3, 1), (0, 0, 1)]), Some(vec![1, 0]));
+        assert_eq!(plan_channels(&[(0, 8, 4), (0, 8, 4), (0, 8, 4)]), Some(vec![0, 4, 8]));
+        assert_eq!(plan_channels(&[(0, 1, 1), (0, 1, 1), (0, 1, 1)]), None);
+        assert_eq!(plan_channels(&[]), Some(vec![]));
+    }
+
+    #[test]
+    fn test_serde() {
+        let config = TofConfig {
+            freq: Some(TofFreq { freq1: 80, freq2: 100 }),
+            channel: Some(2),
+            anti_interference: Some(true),
+            ..Default::default()
+        };
+        let yaml = serde_yaml::to_string(&config).unwrap();
+        assert_eq!(serde_yaml::from_str::<TofConfig>(&yaml).unwrap(), config);
+    }
+}
@@ -0,0 +1,329 @@
+use std::ops::Mul;
+use serde::{Deserialize, Serialize};
+use camport3_sys::*;
+
+use crate::ffi::*;
+use crate::feature::ComponentId;
+use crate::mapper::Point3;
+
+/// Rigid transform of a `TY_CAMERA_EXTRINSIC`: rotation, then translation in millimetres.
+///
+/// `a * b` applies `b` first, like the matrix product, so `base_from_robot * robot_from_camera` maps camera points
+/// into the base frame.
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq)]
+pub struct Transform {
+    /// Row-major 3x3 rotation matrix.
+    pub rotation: [[f32; 3]; 3],
+    pub translation: [f32; 3],
+}
+
+/// Unit quaternion.
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq)]
+pub struct Quaternion {
+    pub w: f32,
+    pub x: f32,
+    pub y: f32,
+    pub z: f32,
+}
+
+/// Intrinsic Z-Y-X angles in radians: yaw about Z, then pitch about the new Y, then roll about the new X.
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq)]
+pub struct EulerAngles {
+    pub roll: f32,
+    pub pitch: f32,
+    pub yaw: f32,
+}
+
+impl Default for Transform {
+    fn default() -> Self {
+        Self::IDENTITY
+    }
+}
+
+impl From<TY_CAMERA_EXTRINSIC> for Transform {
+    /// The last row is assumed to be `0 0 0 1`.
+    fn from(raw: TY_CAMERA_EXTRINSIC) -> Self {
+        let d = raw.data;
+        Transform {
+            rotation: [[d[0], d[1], d[2]], [d[4], d[5], d[6]], [d[8], d[9], d[10]]],
+            translation: [d[3], d[7], d[11]],
+        }
+    }
+}
+
+impl From<Transform> for TY_CAMERA_EXTRINSIC {
+    fn from(t: Transform) -> Self {
+        let (r, p) = (t.rotation, t.translation);
+        TY_CAMERA_EXTRINSIC {
+            data: [
+                r[0][0], r[0][1], r[0][2], p[0],
+                r[1][0], r[1][1], r[1][2], p[1],
+                r[2][0], r[2][1], r[2][2], p[2],
+                0.0, 0.0, 0.0, 1.0,
+            ],
+        }
+    }
+}
+
+fn mat_mul(a: &[[f32; 3]; 3], b: &[[f32; 3]; 3]) -> [[f32; 3]; 3] {
+    std::array::from_fn(|i| std::array::from_fn(|j| (0..3).map(|k| a[i][k] * b[k][j]).sum()))
+}
+
+fn mat_vec(a: &[[f32; 3]; 3], v: [f32; 3]) -> [f32; 3] {
+    std::array::from_fn(|i| (0..3).map(|k| a[i][k] * v[k]).sum())
+}
+
+impl Transform {
+    pub const IDENTITY: Transform = Transform {
+        rotation: [[1.0, 0.0, 0.0], [0.0, 1.0, 0.0], [0.0, 0.0, 1.0]],
+        translation: [0.0; 3],
+    };
+
+    pub fn from_translation(translation: [f32; 3]) -> Self {
+        Transform { translation, ..Self::IDENTITY }
+    }
+
+    /// `q` need not be normalised.
+    pub fn from_quaternion(q: Quaternion, translation: [f32; 3]) -> Self {
+        let n = (q.w * q.w + q.x * q.x + q.y * q.y + q.z * q.z).sqrt();
+        let (w, x, y, z) = (q.w / n, q.x / n, q.y / n, q.z / n);
+        let rotation = [
+            [1.0 - 2.0 * (y * y + z * z), 2.0 * (x * y - w * z), 2.0 * (x * z + w * y)],
+            [2.0 * (x * y + w * z), 1.0 - 2.0 * (x * x + z * z), 2.0 * (y * z - w * x)],
+            [2.0 * (x * z - w * y), 2.0 * (y * z + w * x), 1.0 - 2.0 * (x * x + y * y)],
+        ];
+        Transform { rotation, translation }
+    }
+
+    pub fn from_euler(angles: EulerAngles, translation: [f32; 3]) -> Self {
+        let (sr, cr) = angles.roll.sin_cos();
+        let (sp, cp) = angles.pitch.sin_cos();
+        let (sy, cy) = angles.yaw.sin_cos();
+        let rotation = [
+            [cy * cp, cy * sp * sr - sy * cr, cy * sp * cr + sy * sr],
+            [sy * cp, sy * sp * sr + cy * cr, sy * sp * cr - cy * sr],
+            [-sp, cp * sr, cp * cr],
+        ];
+        Transform { rotation, translation }
+    }
+
+    /// Inverse of a rigid transform: transposed rotation and rotated, negated translation.
+    pub fn inverse(&self) -> Transform {
+        let r = self.rotation;
+        let rotation = std::array::from_fn(|i| std::array::from_fn(|j| r[j][i]));
+        let t = mat_vec(&rotation, self.translation);
+        Transform { rotation, translation: [-t[0], -t[1], -t[2]] }
+    }
+
+    /// Inverse computed by `TYInvertExtrinsic`, which also handles matrices that are not exactly rigid.
+    pub fn inverse_sdk(&self) -> Result<Transform> {
+        ty_invert_extrinsic(&(*self).into()).map(Transform::from)
+    }
+
+    /// `self * next`: apply `next`, then `self`.
+    pub fn compose(&self, next: &Transform) -> Transform {
+        let t = mat_vec(&self.rotation, next.translation);
+        Transform {
+            rotation: mat_mul(&self.rotation, &next.rotation),
+            translation: std::array::from_fn(|i| t[i] + self.translation[i]),
+        }
+    }
+
+    pub fn transform_point(&self, p: Point3) -> Point3 {
+        let v = mat_vec(&self.rotation, [p.x, p.y, p.z]);
+        let t = self.translation;
+        Point3 { x: v[0] + t[0], y: v[1] + t[1], z: v[2] + t[2] }
+    }
+
+    /// Transform a batch of points with `TYMapPoint3dToPoint3d`.
+    pub fn transform_points(&self, points: &[Point3]) -> Result<Vec<Point3>> {
+        let mut out = points.to_vec();
+        ty_map_point3d_to_point3d(&(*self).into(), points, &mut out)?;
+        Ok(out)
+    }
+
+    pub fn to_quaternion(&self) -> Quaternion {
+        let r = self.rotation;
+        let trace = r[0][0] + r[1][1] + r[2][2];
+        // Divide by the largest of the four candidates for numerical stability.
+        if trace > 0.0 {
+            let s = (trace + 1.0).sqrt() * 2.0;
+            Quaternion { w: s / 4.0, x: (r[2][1] - r[1][2]) / s, y: (r[0][2] - r[2][0]) / s, z: (r[1][0] - r[0][1]) / s }
+        } else if r[0][0] > r[1][1] && r[0][0] > r[2][2] {
+            let s = (1.0 + r[0][0] - r[1][1] - r[2][2]).sqrt() * 2.0;
+            Quaternion { w: (r[2][1] - r[1][2]) / s, x: s / 4.0, y: (r[0][1] + r[1][0]) / s, z: (r[0][2] + r[2][0]) / s }
+        } else if r[1][1] > r[2][2] {
+            let s = (1.0 + r[1][1] - r[0][0] - r[2][2]).sqrt() * 2.0;
+            Quaternion { w: (r[0][2] - r[2][0]) / s, x: (r[0][1] + r[1][0]) / s, y: s / 4.0, z: (r[1][2] + r[2][1]) / s }
+        } else {
+            let s = (1.0 + r[2][2] - r[0][0] - r[1][1]).sqrt() * 2.0;
+            Quaternion { w: (r[1][0] - r[0][1]) / s, x: (r[0][2] + r[2][0]) / s, y: (r[1][2] + r[2][1]) / s, z: s / 4.0 }
+        }
+    }
+
+    /// At pitch ±90° roll and yaw are not unique; roll is then reported as 0.
+    pub fn to_euler(&self) -> EulerAngles {
+        let r = self.rotation;
+        let pitch = (-r[2][0]).clamp(-1.0, 1.0).asin();
+        if r[2][0].abs() < 1.0 - 1e-6 {
+            EulerAngles { roll: r[2][1].atan2(r[2][2]), pitch, yaw: r[1][0].atan2(r[0][0]) }
+        } else {
+            EulerAngles { roll: 0.0, pitch, yaw: (-r[0][1]).atan2(r[1][1]) }
+        }
+    }
+}
+
+impl Mul for Transform {
+    type Output = Transform;
+
+    fn mul(self, rhs: Transform) -> Transform {
+        self.compose(&rhs)
+    }
+}
+
+impl DeviceHandle {
+    /// `TY_STRUCT_EXTRINSIC_TO_DEPTH` of a component, mapping its points into the depth camera frame.
+    pub fn extrinsic_to_depth(&self, comp: impl ComponentId) -> Result<Transform> {
+        self.get_struct::<TY_CAMERA_EXTRINSIC>(comp, TY_FEATURE_ID_LIST::TY_STRUCT_EXTRINSIC_TO_DEPTH)
+            .map(Transform::from)
+    }
+}
+
+#[cfg(feature = "nalgebra")]
+mod nalgebra_interop {
+    use nalgebra::{Isometry3, Matrix3, Matrix4, Rotation3, Translation3, UnitQuaternion};
+    use super::Transform;
+
+    impl From<Transform> for Isometry3<f32> {
+        fn from(t: Transform) -> Self {
+            let r = t.rotation;
+            let m = Matrix3::from_fn(|i, j| r[i][j]);
+            let rotation = UnitQuaternion::from_rotation_matrix(&Rotation3::from_matrix_unchecked(m));
+            Isometry3::from_parts(Translation3::from(t.translation), rotation)
+        }
+    }
+
+    impl From<Isometry3<f32>> for Transform {
+        fn from(iso: Isometry3<f32>) -> Self {
+            let m = iso.rotation.to_rotation_matrix().into_inner();
+            Transform {
+                rotation: std::array::from_fn(|i| std::array::from_fn(|j| m[(i, j)])),
+                translation: iso.translation.vector.into(),
+            }
+        }
+    }
+
+    impl From<Transform> for Matrix4<f32> {
+        fn from(t: Transform) -> Self {
+            Matrix4::from_row_slice(&{ camport3_sys::TY_CAMERA_EXTRINSIC::from(t).data })
+        }
+    }
+}
+
+#[cfg(feature = "glam")]
+mod glam_interop {
+    use glam::{Affine3A, Mat3A, Mat4, Vec3A};
+    use super::Transform;
+
+    impl From<Transform> for Affine3A {
+        fn from(t: Transform) -> Self {
+            let r = t.rotation;
+            let col = |j: usize| Vec3A::new(r[0][j], r[1][j], r[2][j]);
+            Affine3A::from_mat3_translation(Mat3A::from_cols(col(0), col(1), col(2)).into(), t.translation.into())
+        }
+    }
+
+    impl From<Affine3A> for Transform {
+        fn from(a: Affine3A) -> Self {
+            let cols = a.matrix3.to_cols_array_2d();
+            Transform {
+                rotation: std::array::from_fn(|i| std::array::from_fn(|j| cols[j][i])),
+                translation: a.translation.into(),
+            }
+        }
+    }
+
+    impl From<Transform> for Mat4 {
+        fn from(t: Transform) -> Self {
+            Affine3A::from(t).into()
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::f32::consts::FRAC_PI_2;
+
+    fn assert_close(a: &Transform, b: &Transform) {
+        let flat = |t: &Transform| t.rotation.into_iter().flatten().chain(t.translation).collect::<Vec<_>>();
+        for (x, y) in flat(a).into_iter().zip(flat(b)) {
+            assert!((x - y).abs() < 1e-4, "{a:?} != {b:?}");
+        }
+    }
+
+    fn sample() -> Transform {
+        Transform::from_euler(EulerAngles { roll: 0.3, pitch: -0.4, yaw: 1.2 }, [10.0, -20.0, 300.0])
+    }
+
+    #[test]
+    fn test_inverse_and_compose() {
+        let t = sample();
+        assert_close(&(t * t.inverse()), &Transform::IDENTITY);
+        assert_close(&(t.inverse() * t), &Transform::IDENTITY);
+
+        // 90° about Z after a shift along X: (1, 0, 0) -> (2, 0, 0) -> (0, 2, 0).
+        let rot = Transform::from_euler(EulerAngles { roll: 0.0, pitch: 0.0, yaw: FRAC_PI_2 }, [0.0; 3]);
+        let p = (rot * Transform::from_translation([1.0, 0.0, 0.0])).transform_point(Point3 { x: 1.0, y: 0.0, z: 0.0 });
+        let (x, y, z) = ({ p.x }, { p.y }, { p.z });
+        assert!(x.abs() < 1e-5 && (y - 2.0).abs() < 1e-5 && z.abs() < 1e-5);
+    }
+
+    #[test]
+    fn test_quaternion_and_euler() {
+        let t = sample();
+        assert_close(&Transform::from_quaternion(t.to_quaternion(), t.translation), &t);
+        assert_close(&Transform::from_euler(t.to_euler(), t.translation), &t);
+        let e = t.to_euler();
+        assert!((e.roll - 0.3).abs() < 1e-5 && (e.pitch + 0.4).abs() < 1e-5 && (e.yaw - 1.2).abs() < 1e-5);
+
+        // Rotations by 180° take the non-trace branches.
+        for angles in [(FRAC_PI_2 * 2.0, 0.0, 0.0), (0.0, 0.0, FRAC_PI_2 * 2.0)] {
+            let t = Transform::from_euler(EulerAngles { roll: angles.0, pitch: angles.1, yaw: angles.2 }, [0.0; 3]);
+            assert_close(&Transform::from_quaternion(t.to_quaternion(), [0.0; 3]), &t);
+        }
+    }
+
+    #[test]
+    fn test_extrinsic_roundtrip() {
+        let t = sample();
+        let raw = TY_CAMERA_EXTRINSIC::from(t);
+        assert_eq!({ raw.data }[3], 10.0);
+        assert_eq!({ raw.data }[15], 1.0);
+        assert_eq!(Transform::from(raw), t);
+        let yaml = serde_yaml::to_string(&t).unwrap();
+        assert_eq!(serde_yaml::from_str::<Transform>(&yaml).unwrap(), t);
+    }
+
+    #[cfg(feature = "nalgebra")]
+    #[test]
+    fn test_nalgebra() {
+        let t = sample();
+        let iso = nalgebra::Isometry3::from(t);
+        assert_close(&Transform::from(iso), &t);
+        let p = iso.transform_point(&nalgebra::Point3::new(1.0, 2.0, 3.0));
+        let q = t.transform_point(Point3 { x: 1.0, y: 2.0, z: 3.0 });
+        assert!((p.x - { q.x }).abs() < 1e-3 && (p.y - { q.y }).abs() < 1e-3 && (p.z - { q.z }).abs() < 1e-3);
+    }
+
+    #[cfg(feature = "glam")]
+    #[test]
+    fn test_glam() {
+        let t = sample();
+        let affine = glam::Affine3A::from(t);
+        assert_close(&Transform::from(affine), &t);
+        let p = affine.transform_point3(glam::Vec3::new(1.0, 2.0, 3.0));
+        let q = t.transform_point(Point3 { x: 1.0, y: 2.0, z: 3.0 });
+        assert!((p.x - { q.x }).abs() < 1e-3 && (p.y - { q.y }).abs() < 1e-3 && (p.z - { q.z }).abs() < 1e-3);
+    }
+}
@@ -1,10 +1,10 @@
 use bytemuck::TransparentWrapper;
-use std::{fmt::{Debug, Display}, net::IpAddr, str::FromStr};
+use std::{borrow::Cow, fmt::Display, net::{AddrParseError, IpAddr}, str::FromStr};
 use serde::{ser::SerializeStruct, Serialize};
 use macaddr::MacAddr;
 use camport3_sys::*;
 
-use crate::utils::{bit_is_set, cstr_to_str};
+use crate::utils::{bit_is_set, carr_to_str};
 use crate::ffi::*;
 
 impl VersionInfo {
@@ -45,12 +45,12 @@ impl Display for VersionInfo {
 }
 
 impl InterfaceInfo {
-    pub fn name(&self) -> &str {
-        cstr_to_str(self.0.name.as_ptr())
+    pub fn name(&self) -> Cow<'_, str> {
+        carr_to_str(&self.0.name)
     }
 
-    pub fn id(&self) -> &str {
-        cstr_to_str(self.0.id.as_ptr())
+    pub fn id(&self) -> Cow<'_, str> {
+        carr_to_str(&self.0.id)
     }
 
     pub fn type_(&self) -> TY_INTERFACE_TYPE {
@@ -91,29 +91,25 @@ pub fn fmt_ty_interface_type(self_: TY_INTERFACE_TYPE) -> String {
 // }
 
 impl NetInfo {
-    pub fn mac(&self) -> MacAddr {
-        let s = cstr_to_str(self.0.mac.as_ptr());
-        MacAddr::from_str(s).unwrap()
+    pub fn mac(&self) -> std::result::Result<MacAddr, macaddr::ParseError> {
+        MacAddr::from_str(&carr_to_str(&self.0.mac))
     }
-    pub fn ip(&self) -> IpAddr {
-        let s = cstr_to_str(self.0.ip.as_ptr());
-        IpAddr::from_str(s).unwrap()
+    pub fn ip(&self) -> std::result::Result<IpAddr, AddrParseError> {
+        IpAddr::from_str(&carr_to_str(&self.0.ip))
     }
-    pub fn netmask(&self) -> IpAddr {
-        let s = cstr_to_str(self.0.netmask.as_ptr());
-        IpAddr::from_str(s).unwrap()
+    pub fn netmask(&self) -> std::result::Result<IpAddr, AddrParseError> {
+        IpAddr::from_str(&carr_to_str(&self.0.netmask))
     }
-    pub fn gateway(&self) -> Option<IpAddr> {
-        let s = cstr_to_str(self.0.gateway.as_ptr());
+    pub fn gateway(&self) -> std::result::Result<Option<IpAddr>, AddrParseError> {
+        let s = carr_to_str(&self.0.gateway);
         if s.is_empty() {
-            None
+            Ok(None)
         } else {
-            Some(IpAddr::from_str(s).unwrap())
+            IpAddr::from_str(&s).map(Some)
         }
     }
-    pub fn broadcast(&self) -> IpAddr {
-        let s = cstr_to_str(self.0.broadcast.as_ptr());
-        IpAddr::from_str(s).unwrap()
+    pub fn broadcast(&self) -> std::result::Result<IpAddr, AddrParseError> {
+        IpAddr::from_str(&carr_to_str(&self.0.broadcast))
     }
 }
 
@@ -131,20 +127,20 @@ impl DeviceBaseInfo {
         TransparentWrapper::wrap_ref(&self.0.iface)
     }
 
-    pub fn id(&self) -> &str {
-        cstr_to_str(self.0.id.as_ptr())
+    pub fn id(&self) -> Cow<'_, str> {
+        carr_to_str(&self.0.id)
     }
 
-    pub fn vender_name(&self) -> &str {
-        cstr_to_str(self.0.vendorName.as_ptr())
+    pub fn vender_name(&self) -> Cow<'_, str> {
+        carr_to_str(&self.0.vendorName)
     }
 
-    pub fn user_defined_name(&self) -> &str {
-        cstr_to_str(self.0.userDefinedName.as_ptr())
+    pub fn user_defined_name(&self) -> Cow<'_, str> {
+        carr_to_str(&self.0.userDefinedName)
     }
 
-    pub fn model_name(&self) -> &str {
-        cstr_to_str(self.0.modelName.as_ptr())
+    pub fn model_name(&self) -> Cow<'_, str> {
+        carr_to_str(&self.0.modelName)
     }
 
     pub fn hardware_version(&self) -> &VersionInfo {
@@ -174,16 +170,32 @@ impl DeviceBaseInfo {
         }
     }
 
-    pub fn build_hash(&self) -> &str {
-        cstr_to_str(self.0.buildHash.as_ptr())
+    pub fn build_hash(&self) -> Cow<'_, str> {
+        carr_to_str(&self.0.buildHash)
+    }
+
+    pub fn config_version(&self) -> Cow<'_, str> {
+        carr_to_str(&self.0.configVersion)
     }
+}
 
-    pub fn config_version(&self) -> &str {
-        cstr_to_str(self.0.configVersion.as_ptr())
+impl Context {
+    /// Refresh all interfaces and return the one the device `id` is attached to.
+    pub fn find_device(&self, id: &str) -> Result<Option<InterfaceHandle>> {
+        self.update_interface_list()?;
+        let n = self.get_interface_number()?;
+        for info in self.get_interface_list(n)? {
+            let iface = self.open_interface(&info.id())?;
+            iface.update_device_list()?;
+            if iface.has_device(id)? {
+                return Ok(Some(iface));
+            }
+        }
+        Ok(None)
     }
 }
 
-impl InterfaceHandle<'_> {
+impl InterfaceHandle {
     pub fn update_device_list(&self) -> Result<()> {
         ty_update_device_list(self)
     }
@@ -200,12 +212,47 @@ impl InterfaceHandle<'_> {
         ty_open_device(self, id)
     }
 
+    /// Open a device even if the firmware reports faults, leaving the policy to the caller.
+    pub fn open_device_with_faults(&self, id: &str) -> Result<(DeviceHandle, FirmwareFaults)> {
+        ty_open_device_with_faults(self, id)
+    }
+
     pub fn open_device_with_ip(&self, ip: &str) -> Result<DeviceHandle> {
         ty_open_device_with_ip(self, ip)
     }
 
 }
 
+impl DeviceHandle {
+    pub fn get_device_info(&self) -> Result<DeviceBaseInfo> {
+        ty_get_device_info(self)
+    }
+
+    pub fn get_component_ids(&self) -> Result<TY_COMPONENT_ID> {
+        ty_get_component_ids(self)
+    }
+
+    pub fn get_enabled_components(&self) -> Result<TY_COMPONENT_ID> {
+        ty_get_enabled_components(self)
+    }
+
+    pub fn enable_components(&self, ids: TY_COMPONENT_ID) -> Result<()> {
+        ty_enable_components(self, ids)
+    }
+
+    pub fn disable_components(&self, ids: TY_COMPONENT_ID) -> Result<()> {
+        ty_disable_components(self, ids)
+    }
+
+    pub fn get_frame_buffer_size(&self) -> Result<usize> {
+        ty_get_frame_buffer_size(self)
+    }
+
+    pub fn send_soft_trigger(&self) -> Result<()> {
+        ty_send_soft_trigger(self)
+    }
+}
+
 
 #[cfg(test)]
 mod tests {
@@ -214,10 +261,10 @@ mod tests {
     const VALID_ID: &str = "eth-30:0e:d5:57:c2:ea9b04a8c0";
 
     fn setup_context() -> Context {
-        let ctx = Context::new();
-        ctx.update_interface_list();
-        let out = ctx.get_interface_list(0);
-        assert!(out.len() >= 1);
+        let ctx = Context::new().unwrap();
+        ctx.update_interface_list().unwrap();
+        let out = ctx.get_interface_list(0).unwrap();
+        assert!(!out.is_empty());
         ctx
     }
 
@@ -225,18 +272,18 @@ mod tests {
     fn test_device() {
         let ctx = setup_context();
 
-        let iface: InterfaceHandle<'_> = ctx.open_interface(VALID_ID).unwrap();
+        let iface: InterfaceHandle = ctx.open_interface(VALID_ID).unwrap();
         iface.update_device_list().unwrap();
 
         let mut dev_ids = Vec::new();
         for dev in iface.get_device_list(0).unwrap() {
             assert!(!dev.id().is_empty());
-            dev_ids.push(dev.id().to_owned());
+            dev_ids.push(dev.id().into_owned());
         }
 
-        assert!(dev_ids.len() > 0);
+        assert!(!dev_ids.is_empty());
         let dev_id = &dev_ids[0];
-        let dev = iface.open_device(dev_id).unwrap();
+        let _dev = iface.open_device(dev_id).unwrap();
 
     }
 }
\ No newline at end of file
@@ -1,8 +1,21 @@
-use std::ffi::CStr;
+use std::borrow::Cow;
+use std::ffi::{c_char, CStr};
 use std::ops::BitAnd;
 
-pub(crate) fn cstr_to_str<'a>(s: *const i8) -> &'a str {
-    unsafe {CStr::from_ptr(s).to_str().unwrap()}
+pub(crate) fn cstr_to_str<'a>(s: *const c_char) -> Cow<'a, str> {
+    if s.is_null() {
+        return Cow::Borrowed("");
+    }
+    unsafe {CStr::from_ptr(s).to_string_lossy()}
+}
+
+/// Decode a fixed-size C char array, stopping at the first NUL or at the end of the array.
+pub(crate) fn carr_to_str(s: &[c_char]) -> Cow<'_, str> {
+    let bytes: &[u8] = bytemuck::cast_slice(s);
+    match CStr::from_bytes_until_nul(bytes) {
+        Ok(s) => s.to_string_lossy(),
+        Err(_) => String::from_utf8_lossy(bytes),
+    }
 }
 
 pub(crate) fn bit_is_set<T>(a: T, b: T) -> bool
@@ -10,4 +23,4 @@ where T: BitAnd + Copy,
     <T as BitAnd>::Output: PartialEq<T>,
 {
     (a & b).eq(&b)
-}
\ No newline at end of file
+}
@@ -0,0 +1,208 @@
+use std::collections::HashMap;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{mpsc, Arc};
+use std::thread::{self, JoinHandle};
+use std::time::Duration;
+use camport3_sys::*;
+
+use crate::ffi::*;
+
+/// Change in the set of connected devices, as seen by a [`DeviceWatcher`].
+#[derive(Debug, Clone)]
+pub enum WatchEvent {
+    Added(Box<DeviceBaseInfo>),
+    Removed(String),
+    /// Same device ID, but e.g. a new IP address, interface or name.
+    Changed { old: Box<DeviceBaseInfo>, new: Box<DeviceBaseInfo> },
+}
+
+/// Which devices a [`DeviceWatcher`] reports. The default matches everything.
+#[derive(Debug, Clone, Default)]
+pub struct DeviceFilter {
+    /// Mask of `TY_INTERFACE_TYPE_LIST` values; `None` accepts all interfaces.
+    pub interface_types: Option<TY_INTERFACE_TYPE>,
+    /// Accepted model names; empty accepts all models.
+    pub model_names: Vec<String>,
+}
+
+impl DeviceFilter {
+    pub fn matches(&self, info: &DeviceBaseInfo) -> bool {
+        let iface_ok = self.interface_types
+            .is_none_or(|mask| info.iface().type_() & mask != 0);
+        let model_ok = self.model_names.is_empty()
+            || self.model_names.iter().any(|name| *name == info.model_name());
+        iface_ok && model_ok
+    }
+}
+
+/// Devices currently connected on any interface and accepted by `filter`, keyed by device ID.
+///
+/// An interface that fails to open or list its devices is logged and skipped, its devices are left out.
+pub fn scan_devices(ctx: &Context, filter: &DeviceFilter) -> Result<HashMap<String, DeviceBaseInfo>> {
+    ctx.update_interface_list()?;
+    ctx.update_all_device_list()?;
+    let n = ctx.get_interface_number()?;
+    let mut out = HashMap::new();
+    for iface_info in ctx.get_interface_list(n)? {
+        let id = iface_info.id();
+        let devices = ctx.open_interface(&id).and_then(|iface| iface.get_device_list(0));
+        let devices = match devices {
+            Ok(devices) => devices,
+            Err(e) => {
+                log::warn!("skipping interface {id}: {e}");
+                continue;
+            }
+        };
+        for info in devices {
+            if filter.matches(&info) {
+                out.entry(info.id().into_owned()).or_insert(info);
+            }
+        }
+    }
+    Ok(out)
+}
+
+/// Events turning the `old` scan into the `new` one.
+pub fn diff_devices(old: &HashMap<String, DeviceBaseInfo>, new: &HashMap<String, DeviceBaseInfo>) -> Vec<WatchEvent> {
+    let removed = old.keys()
+        .filter(|id| !new.contains_key(*id))
+        .map(|id| WatchEvent::Removed(id.clone()));
+    let added_or_changed = new.iter().filter_map(|(id, info)| match old.get(id) {
+        None => Some(WatchEvent::Added(Box::new(*info))),
+        Some(prev) if !same_state(prev, info) => Some(WatchEvent::Changed { old: Box::new(*prev), new: Box::new(*info) }),
+        Some(_) => None,
+    });
+    removed.chain(added_or_changed).collect()
+}
+
+fn same_state(a: &DeviceBaseInfo, b: &DeviceBaseInfo) -> bool {
+    a.iface().id() == b.iface().id()
+        && a.user_defined_name() == b.user_defined_name()
+        && <(u32, u32, u32)>::from(*a.firmware_version()) == (*b.firmware_version()).into()
+        && a.config_version() == b.config_version()
+        && a.get_net_info().map(|net| net.ip().ok()) == b.get_net_info().map(|net| net.ip().ok())
+        && a.get_usb_info().map(|usb| (usb.bus(), usb.addr())) == b.get_usb_info().map(|usb| (usb.bus(), usb.addr()))
+}
+
+/// Background thread polling the device lists of all interfaces.
+///
+/// Scan errors are logged and retried at the next period. Dropping the watcher stops the thread.
+#[derive(Debug)]
+pub struct DeviceWatcher {
+    stop: Arc<AtomicBool>,
+    thread: Option<JoinHandle<()>>,
+}
+
+impl DeviceWatcher {
+    /// Call `callback` for every change, from the watcher thread.
+    ///
+    /// Devices already connected are reported as [`WatchEvent::Added`] by the first scan.
+    pub fn spawn<F>(ctx: Context, period: Duration, filter: DeviceFilter, mut callback: F) -> Result<Self>
+    where
+        F: FnMut(WatchEvent) + Send + 'static,
+    {
+        Self::start(ctx, period, filter, move |event| {
+            callback(event);
+            true
+        })
+    }
+
+    /// Like [`DeviceWatcher::spawn`], delivering the events on a channel. The thread exits once the receiver is dropped.
+    pub fn channel(ctx: Context, period: Duration, filter: DeviceFilter) -> Result<(Self, mpsc::Receiver<WatchEvent>)> {
+        let (tx, rx) = mpsc::channel();
+        let watcher = Self::start(ctx, period, filter, move |event| tx.send(event).is_ok())?;
+        Ok((watcher, rx))
+    }
+
+    fn start<F>(ctx: Context, period: Duration, filter: DeviceFilter, mut sink: F) -> Result<Self>
+    where
+        F: FnMut(WatchEvent) -> bool + Send + 'static,
+    {
+        let stop = Arc::new(AtomicBool::new(false));
+        let thread_stop = stop.clone();
+        let thread = thread::Builder::new()
+            .name("camport3-watcher".into())
+            .spawn(move || {
+                let mut known = HashMap::new();
+                while !thread_stop.load(Ordering::Acquire) {
+                    match scan_devices(&ctx, &filter) {
+                        Ok(current) => {
+                            for event in diff_devices(&known, &current) {
+                                if !sink(event) {
+                                    return;
+                                }
+                            }
+                            known = current;
+                        }
+                        Err(e) => log::warn!("device scan failed: {e}"),
+                    }
+                    thread::park_timeout(period);
+                }
+            })
+            .map_err(|_| ErrorCode::OutOfMemory)?;
+        Ok(DeviceWatcher { stop, thread: Some(thread) })
+    }
+}
+
+impl Drop for DeviceWatcher {
+    fn drop(&mut self) {
+        self.stop.store(true, Ordering::Release);
+        if let Some(thread) = self.thread.take() {
+            thread.thread().unpark();
+            if thread.join().is_err() {
+                log::warn!("device watcher thread panicked");
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::mem::MaybeUninit;
+    use bytemuck::TransparentWrapper;
+    use super::*;
+
+    fn device(id: &str, model: &str, iface_type: TY_INTERFACE_TYPE) -> DeviceBaseInfo {
+        let mut raw: TY_DEVICE_BASE_INFO = unsafe { MaybeUninit::zeroed().assume_init() };
+        for (dst, src) in raw.id.iter_mut().zip(id.bytes()) {
+            *dst = src as _;
+        }
+        for (dst, src) in raw.modelName.iter_mut().zip(model.bytes()) {
+            *dst = src as _;
+        }
+        raw.iface.type_ = iface_type;
+        DeviceBaseInfo::wrap(raw)
+    }
+
+    #[test]
+    fn test_diff_devices() {
+        use TY_INTERFACE_TYPE_LIST::*;
+        let a = device("a", "FM851-E2", TY_INTERFACE_USB);
+        let b = device("b", "FM851-E2", TY_INTERFACE_USB);
+        let mut b2 = b;
+        b2.0.userDefinedName[0] = b'x' as _;
+
+        let old = HashMap::from([("a".to_string(), a), ("b".to_string(), b)]);
+        let new = HashMap::from([("b".to_string(), b2), ("c".to_string(), device("c", "PS801", TY_INTERFACE_ETHERNET))]);
+        let events = diff_devices(&old, &new);
+        assert_eq!(events.len(), 3);
+        assert!(events.iter().any(|e| matches!(e, WatchEvent::Removed(id) if id == "a")));
+        assert!(events.iter().any(|e| matches!(e, WatchEvent::Added(info) if info.id() == "c")));
+        assert!(events.iter().any(|e| matches!(e, WatchEvent::Changed { new, .. } if new.user_defined_name() == "x")));
+        assert!(diff_devices(&new, &new).is_empty());
+    }
+
+    #[test]
+    fn test_filter() {
+        use TY_INTERFACE_TYPE_LIST::*;
+        let usb = device("a", "FM851-E2", TY_INTERFACE_USB);
+        let eth = device("b", "PS801", TY_INTERFACE_ETHERNET);
+        assert!(DeviceFilter::default().matches(&usb));
+
+        let filter = DeviceFilter { interface_types: Some(TY_INTERFACE_USB), model_names: vec![] };
+        assert!(filter.matches(&usb) && !filter.matches(&eth));
+
+        let filter = DeviceFilter { interface_types: None, model_names: vec!["PS801".into()] };
+        assert!(!filter.matches(&usb) && filter.matches(&eth));
+    }
+}
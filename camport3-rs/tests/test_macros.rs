@@ -1,3 +1,4 @@
+#[allow(unused_imports)]
 use camport3_rs::gen_bitflags_enum;
 
 fn main() {}
\ No newline at end of file
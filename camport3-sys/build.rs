@@ -1,14 +1,50 @@
 use std::env;
-use std::path::PathBuf;
+use std::fs;
+use std::path::{Path, PathBuf};
 
-fn main() {
-    let prefix = env::var("CONDA_PREFIX").expect("CONDA_PREFIX not exists!");
-    println!("cargo:rustc-link-search={prefix}/lib");
-    println!("cargo:rustc-link-lib=tycam");
+const HEADERS: [&str; 5] = ["TYVer.h", "TYApi.h", "TYCoordinateMapper.h", "TYImageProc.h", "TyIsp.h"];
+
+/// Where the vendor SDK was found.
+#[derive(Debug, Default)]
+struct Sdk {
+    /// Directory containing `camport3/TYApi.h`.
+    include: Option<PathBuf>,
+    lib_dirs: Vec<PathBuf>,
+    /// pkg-config already printed the link flags.
+    linked: bool,
+}
+
+impl Sdk {
+    fn from_prefix(prefix: &Path) -> Self {
+        Sdk {
+            include: Some(prefix.join("include")),
+            lib_dirs: vec![prefix.join("lib")],
+            linked: false,
+        }
+    }
+}
 
+/// `CAMPORT3_SDK_DIR`, then pkg-config, then `CONDA_PREFIX`.
+fn find_sdk(link: bool) -> Option<Sdk> {
+    if let Some(dir) = env::var_os("CAMPORT3_SDK_DIR") {
+        return Some(Sdk::from_prefix(Path::new(&dir)));
+    }
+    for name in ["camport3", "tycam"] {
+        if let Ok(lib) = pkg_config::Config::new().cargo_metadata(link).probe(name) {
+            return Some(Sdk {
+                include: lib.include_paths.into_iter().find(|p| p.join("camport3/TYApi.h").exists()),
+                lib_dirs: lib.link_paths,
+                linked: link,
+            });
+        }
+    }
+    env::var_os("CONDA_PREFIX").map(|prefix| Sdk::from_prefix(Path::new(&prefix)))
+}
+
+fn generate_bindings(include: &Path) -> String {
     let mut builder = bindgen::Builder::default()
         .header("wrapper.h")
-        .clang_arg(format!("-I{prefix}/include"))
+        .clang_arg(format!("-I{}", include.display()))
         .parse_callbacks(Box::new(bindgen::CargoCallbacks::new()))
         .default_enum_style(bindgen::EnumVariation::Rust {non_exhaustive: false})
         .constified_enum_module("TY_INTERFACE_TYPE_LIST")
@@ -18,22 +54,142 @@ fn main() {
         // .wrap_static_fns(true) // TODO
     ;
 
-    for p in ["TYVer.h", "TYApi.h", "TYCoordinateMapper.h", "TYImageProc.h", "TyIsp.h"] {
-        builder = builder.allowlist_file(format!("{prefix}/include/camport3/{p}"));
+    for p in HEADERS {
+        builder = builder.allowlist_file(include.join("camport3").join(p).to_string_lossy());
     }
 
     let bindings = builder.generate()
         .expect("Unable to generate bindings");
+    bindings.to_string()
+}
 
-    // Write the bindings to the $OUT_DIR/bindings.rs file.
-    let out_path = PathBuf::from(env::var("OUT_DIR").unwrap());
-    bindings
-        .write_to_file(out_path.join("bindings.rs"))
-        .expect("Couldn't write bindings!");
-    bindings
-        .write_to_file("gen/bindings.rs")
-        .expect("Couldn't write bindings!");
+/// Split `s` at top-level commas, ignoring those nested in brackets.
+fn split_args(s: &str) -> Vec<&str> {
+    let (mut depth, mut start, mut out) = (0i32, 0, Vec::new());
+    for (i, c) in s.char_indices() {
+        match c {
+            '(' | '<' | '[' => depth += 1,
+            ')' | '>' | ']' if !s[..i].ends_with('-') => depth -= 1,
+            ',' if depth == 0 => {
+                out.push(s[start..i].trim());
+                start = i + 1;
+            }
+            _ => {}
+        }
+    }
+    out.push(s[start..].trim());
+    out.retain(|a| !a.is_empty());
+    out
+}
+
+/// What a wrapper returns when the library or its symbol is missing: `TY_STATUS_NOT_IMPLEMENTED`, or null.
+fn missing_value(name: &str, ret: &str) -> &'static str {
+    match ret.trim_start_matches("->").trim() {
+        "TY_STATUS" => "TY_STATUS_LIST::TY_STATUS_NOT_IMPLEMENTED",
+        r if r.starts_with("*const") => "::std::ptr::null()",
+        r if r.starts_with("*mut") => "::std::ptr::null_mut()",
+        "" => "()",
+        r => panic!("no missing-symbol value for {name} returning {r}"),
+    }
+}
 
+/// Replace the `extern "C"` blocks of `bindings` by wrappers calling through symbols resolved at runtime.
+///
+/// The wrappers keep the names and signatures of the functions they replace; `src/dlopen.rs` loads the library.
+/// When the library or the symbol is missing they return [`missing_value`] instead of calling.
+fn dlopen_bindings(bindings: &str) -> String {
+    let mut out = String::new();
+    let mut fields = String::new();
+    let mut loads = String::new();
+    let mut lines = bindings.lines();
+    while let Some(line) = lines.next() {
+        if line != "unsafe extern \"C\" {" && line != "extern \"C\" {" {
+            out.push_str(line);
+            out.push('\n');
+            continue;
+        }
+        let mut attrs = Vec::new();
+        let mut item = String::new();
+        for line in lines.by_ref().take_while(|l| *l != "}") {
+            let line = line.trim();
+            if line.starts_with("#[") {
+                attrs.push(line.to_owned());
+            } else {
+                item.push_str(line);
+                item.push(' ');
+            }
+        }
+        let item = item.trim().trim_end_matches(';');
+        let sig = item.strip_prefix("pub fn ").unwrap_or_else(|| panic!("unexpected extern item: {item}"));
+        let open = sig.find('(').expect("function without arguments");
+        let close = sig.rfind(')').expect("unterminated arguments");
+        let name = &sig[..open];
+        let args = split_args(&sig[open + 1..close]);
+        let ret = sig[close + 1..].trim();
+        let names: Vec<_> = args.iter().map(|a| a.split(':').next().unwrap().trim()).collect();
+        let types: Vec<_> = args.iter().map(|a| a.split_once(':').unwrap().1.trim()).collect();
 
+        fields.push_str(&format!("    {name}: Option<unsafe extern \"C\" fn({}) {ret}>,\n", types.join(", ")));
+        loads.push_str(&format!("            {name}: lib.get(b\"{name}\\0\").ok().map(|s| *s),\n"));
+        for attr in &attrs {
+            out.push_str(attr);
+            out.push('\n');
+        }
+        out.push_str(&format!(
+            "pub unsafe fn {name}({}) {ret} {{\n    match crate::dlopen::api().and_then(|api| api.{name}) {{\n        \
+             Some(f) => f({}),\n        None => {},\n    }}\n}}\n",
+            args.join(", "),
+            names.join(", "),
+            missing_value(name, ret),
+        ));
+    }
+    out.push_str(&format!(
+        "#[allow(non_snake_case)]\npub(crate) struct TyCamApi {{\n{fields}}}\n\
+         impl TyCamApi {{\n    unsafe fn load(lib: &libloading::Library) -> Self {{\n        TyCamApi {{\n{loads}        }}\n    }}\n}}\n"
+    ));
+    out
+}
 
+fn main() {
+    println!("cargo:rerun-if-env-changed=CAMPORT3_SDK_DIR");
+    println!("cargo:rerun-if-env-changed=CONDA_PREFIX");
+    let dlopen = env::var_os("CARGO_FEATURE_DLOPEN").is_some();
+    let prebuilt = env::var_os("CARGO_FEATURE_PREBUILT_BINDINGS").is_some();
+
+    let sdk = find_sdk(!dlopen);
+    match &sdk {
+        _ if dlopen => {
+            // Lets the loader also look where the SDK was found at build time.
+            if let Some(dir) = sdk.as_ref().and_then(|s| s.lib_dirs.first()) {
+                println!("cargo:rustc-env=CAMPORT3_BUILD_LIB_DIR={}", dir.display());
+            }
+        }
+        Some(sdk) if sdk.linked => {}
+        Some(sdk) => {
+            for dir in &sdk.lib_dirs {
+                println!("cargo:rustc-link-search={}", dir.display());
+            }
+            println!("cargo:rustc-link-lib=tycam");
+        }
+        None => {
+            println!("cargo:warning=camport3 SDK not found via CAMPORT3_SDK_DIR, pkg-config or CONDA_PREFIX; \
+                      linking tycam from the default search path");
+            println!("cargo:rustc-link-lib=tycam");
+        }
+    }
+
+    let bindings = if prebuilt {
+        println!("cargo:rerun-if-changed=gen/bindings.rs");
+        fs::read_to_string("gen/bindings.rs").expect("Couldn't read gen/bindings.rs")
+    } else {
+        // The headers shipped in `include/` match `gen/bindings.rs`, use them when the SDK has none.
+        let include = sdk.and_then(|s| s.include).unwrap_or_else(|| PathBuf::from("include"));
+        generate_bindings(&include)
+    };
+    let bindings = if dlopen { dlopen_bindings(&bindings) } else { bindings };
+
+    // Write the bindings to the $OUT_DIR/bindings.rs file.
+    let out_path = PathBuf::from(env::var("OUT_DIR").unwrap());
+    fs::write(out_path.join("bindings.rs"), bindings)
+        .expect("Couldn't write bindings!");
 }
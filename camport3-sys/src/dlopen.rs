@@ -0,0 +1,73 @@
+//! Runtime loading of `libtycam`, enabled by the `dlopen` feature.
+//!
+//! The generated functions load the library on first call. When the library, or a symbol missing from older
+//! `libtycam` builds, is not available they return `TY_STATUS_NOT_IMPLEMENTED` (null for pointers). Call [`load`]
+//! first to find out why the library could not be loaded.
+
+use std::ffi::OsString;
+use std::path::PathBuf;
+use std::sync::OnceLock;
+
+use libloading::Library;
+
+use crate::TyCamApi;
+
+/// Failure to load `libtycam`.
+#[derive(Debug, Clone)]
+pub struct LoadError {
+    /// Every path that was tried, with the loader's message.
+    pub attempts: Vec<(OsString, String)>,
+}
+
+impl std::fmt::Display for LoadError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "could not load the camport3 SDK library")?;
+        for (path, error) in &self.attempts {
+            write!(f, "; {}: {error}", path.to_string_lossy())?;
+        }
+        Ok(())
+    }
+}
+
+impl std::error::Error for LoadError {}
+
+static API: OnceLock<Result<(Library, TyCamApi), LoadError>> = OnceLock::new();
+
+/// Candidates in order: `CAMPORT3_LIB`, the system search path, then the SDK library directory found at build time.
+fn candidates() -> Vec<OsString> {
+    let name = libloading::library_filename("tycam");
+    let mut out = Vec::new();
+    if let Some(path) = std::env::var_os("CAMPORT3_LIB") {
+        out.push(path);
+    }
+    out.push(name.clone());
+    if let Some(dir) = option_env!("CAMPORT3_BUILD_LIB_DIR") {
+        out.push(PathBuf::from(dir).join(&name).into_os_string());
+    }
+    out
+}
+
+fn open() -> Result<(Library, TyCamApi), LoadError> {
+    let mut attempts = Vec::new();
+    for path in candidates() {
+        // SAFETY: loading the vendor SDK runs its initialisers, which have no preconditions.
+        match unsafe { Library::new(&path) } {
+            Ok(lib) => {
+                let api = unsafe { TyCamApi::load(&lib) };
+                return Ok((lib, api));
+            }
+            Err(e) => attempts.push((path, e.to_string())),
+        }
+    }
+    Err(LoadError { attempts })
+}
+
+/// Load `libtycam` if not done yet. The outcome is cached for the life of the process.
+pub fn load() -> Result<(), LoadError> {
+    API.get_or_init(open).as_ref().map(|_| ()).map_err(Clone::clone)
+}
+
+/// The resolved symbols, `None` if the library could not be loaded.
+pub(crate) fn api() -> Option<&'static TyCamApi> {
+    API.get_or_init(open).as_ref().ok().map(|(_, api)| api)
+}
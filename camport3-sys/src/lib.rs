@@ -2,11 +2,17 @@
 #![allow(non_camel_case_types)]
 #![allow(non_snake_case)]
 #![allow(improper_ctypes)]
-
-use bytemuck::{AnyBitPattern, NoUninit, Pod, Zeroable};
+#![allow(clippy::missing_safety_doc)]
 
 include!(concat!(env!("OUT_DIR"), "/bindings.rs"));
 
+#[cfg(feature = "dlopen")]
+mod dlopen;
+#[cfg(feature = "dlopen")]
+pub use dlopen::{load, LoadError};
+
+// Each constified enum module defines its own `Type` alias.
+#[allow(ambiguous_glob_reexports)]
 pub use TY_STATUS_LIST::*;
 pub use TY_INTERFACE_TYPE_LIST::*;
 pub use TY_FW_ERRORCODE_LIST::*;